@@ -20,6 +20,10 @@ class Uci():
 
         self.depth = 1
 
+        # Opening book options, applied whenever either one changes
+        self.own_book = False
+        self.book_file = ""
+
     def sanitize(self, input):
         return input.strip()
 
@@ -29,7 +33,15 @@ class Uci():
         print(f"id author RayHong")
 
         # Send engine options here
-        # None so far
+        print("option name OwnBook type check default false")
+        print("option name BookFile type string default <empty>")
+        print("option name SyzygyPath type string default <empty>")
+        print("option name SyzygyProbeLimit type spin default 7 min 0 max 7")
+        print("option name EvalFile type string default <empty>")
+        print("option name EvalNetwork type string default <empty>")
+        print("option name UseNNUE type check default false")
+        print("option name UCI_Chess960 type check default false")
+        print("option name UCI_Variant type combo default chess var chess var 3check var kingofthehill var atomic var antichess var crazyhouse")
         print("uciok")
 
         self.running = True
@@ -51,7 +63,7 @@ class Uci():
             print("readyok")
             pass
         elif tokens[0] == "setoption":
-            pass
+            self.process_setoption_command(tokens[1:])
         elif tokens[0] == "register":
             pass
         elif tokens[0] == "ucinewgame":
@@ -65,10 +77,13 @@ class Uci():
                 if len(tokens) > 2 and tokens[2] == "moves":
                     moves = tokens[3:]
             elif tokens[1] == "fen":
-                fen = tokens[2]
-
-                if len(tokens) > 3 and tokens[3] == "moves":
-                    moves = tokens[4:]
+                # The FEN is several tokens long, running up to "moves" if there are any
+                if "moves" in tokens:
+                    moves_index = tokens.index("moves")
+                    fen = " ".join(tokens[2:moves_index])
+                    moves = tokens[moves_index + 1:]
+                else:
+                    fen = " ".join(tokens[2:])
 
 
             self.engine.set_position(fen, moves)
@@ -110,8 +125,101 @@ class Uci():
         elif tokens[0] == "undo":
             self.engine.undo()
 
-
-
+        elif tokens[0] == "eval":
+            self.print_eval_breakdown()
+
+
+
+
+    def process_setoption_command(self, tokens):
+        # setoption name <id> [value <x>], where both id and x may contain spaces
+        if "name" not in tokens:
+            return
+
+        name_start = tokens.index("name") + 1
+        if "value" in tokens:
+            value_start = tokens.index("value")
+            name = " ".join(tokens[name_start:value_start])
+            value = " ".join(tokens[value_start + 1:])
+        else:
+            name = " ".join(tokens[name_start:])
+            value = ""
+
+        if name.lower() == "ownbook":
+            self.own_book = value.lower() == "true"
+            self.apply_book_options()
+        elif name.lower() == "bookfile":
+            self.book_file = "" if value == "<empty>" else value
+            self.apply_book_options()
+        elif name.lower() == "syzygypath" and self.mode == Mode.rust_minimax:
+            path = "" if value == "<empty>" else value
+            try:
+                max_pieces = self.engine.set_syzygy_path(path)
+                if path:
+                    print(f"info string found {max_pieces} piece tablebases")
+            except OSError as e:
+                print(f"info string could not load tablebases from {path}: {e}")
+                self.engine.set_syzygy_path("")
+        elif name.lower() == "syzygyprobelimit" and self.mode == Mode.rust_minimax:
+            self.engine.set_syzygy_probe_limit(max(0, min(7, int(value))))
+        elif name.lower() == "evalfile" and self.mode == Mode.rust_minimax:
+            path = "" if value == "<empty>" else value
+            try:
+                self.engine.set_eval_file(path)
+            except OSError as e:
+                print(f"info string could not load evaluation parameters from {path}: {e}")
+                self.engine.set_eval_file("")
+        elif name.lower() == "evalnetwork" and self.mode == Mode.rust_minimax:
+            path = "" if value == "<empty>" else value
+            try:
+                self.engine.set_nnue_file(path)
+            except OSError as e:
+                print(f"info string could not load network from {path}: {e}")
+                self.engine.set_nnue_file("")
+        elif name.lower() == "usennue" and self.mode == Mode.rust_minimax:
+            self.engine.set_use_nnue(value.lower() == "true")
+        elif name.lower() == "uci_chess960" and self.mode == Mode.rust_minimax:
+            self.engine.set_chess960(value.lower() == "true")
+        elif name.lower() == "uci_variant" and self.mode == Mode.rust_minimax:
+            try:
+                self.engine.set_variant(value)
+            except ValueError as e:
+                print(f"info string {e}")
+
+    def apply_book_options(self):
+        if self.mode != Mode.rust_minimax:
+            return
+
+        if self.own_book and self.book_file:
+            try:
+                self.engine.set_book(self.book_file)
+            except OSError as e:
+                print(f"info string could not load book {self.book_file}: {e}")
+                self.engine.clear_book()
+        else:
+            self.engine.clear_book()
+
+    def print_eval_breakdown(self):
+        if self.mode != Mode.rust_minimax:
+            print("info string eval is only available with the rust engine")
+            return
+
+        breakdown = self.engine.eval_breakdown()
+        phase = breakdown.pop("phase")
+        total = breakdown.pop("total")
+
+        print(f"{'Term':<16}|{'White':^15}|{'Black':^15}|{'Total':^15}")
+        print(f"{'':<16}|{'MG':>7}{'EG':>7} |{'MG':>7}{'EG':>7} |{'MG':>7}{'EG':>7} ")
+        print("-" * 63)
+        for term, sides in breakdown.items():
+            white_mg, white_eg = sides["white"]
+            black_mg, black_eg = sides["black"]
+            name = term.replace("_", " ").capitalize()
+            print(f"{name:<16}|{white_mg:>7}{white_eg:>7} |{black_mg:>7}{black_eg:>7} "
+                  f"|{white_mg - black_mg:>7}{white_eg - black_eg:>7} ")
+        print("-" * 63)
+        print(f"Phase: {phase}/24 (24 = opening, 0 = pawn ending)")
+        print(f"Final evaluation: {total / 100:+.2f} (white side)")
 
     def process_debug_command(self, token):
         if token == "on":
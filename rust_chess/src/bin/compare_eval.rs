@@ -0,0 +1,89 @@
+//! Compare a network against the hand-crafted evaluation on a set of positions labelled with
+//! game results, in the format the `tune` binary reads.
+//!
+//! Usage: compare_eval <network> <positions> [--qdepth <n>]
+//!
+//! For each evaluator this reports how well its scores predict the results (mean squared error
+//! with its own best fitting sigmoid scaling) and how long it takes per position, then how far
+//! the two evaluations are apart.
+
+use std::env;
+use std::process;
+use std::sync::Arc;
+use std::time::Instant;
+
+use rust_chess::engine::minimax::Minimax;
+use rust_chess::engine::nnue::Network;
+use rust_chess::engine::tuner::{best_k, load_positions, scored_error, white_score, TuningPosition};
+use rust_chess::enums::Colour;
+
+const USAGE: &str = "usage: compare_eval <network> <positions> [--qdepth <n>]";
+
+/// White's score and the result of every position, and the time taken per position in µs
+fn score_all<F>(positions: &mut [TuningPosition], mut score: F) -> (Vec<(i32, f64)>, f64)
+where
+    F: FnMut(&mut TuningPosition) -> i32,
+{
+    let start = Instant::now();
+    let scored: Vec<(i32, f64)> = positions.iter_mut().map(|position| (score(position), position.result)).collect();
+    let per_position = start.elapsed().as_secs_f64() * 1e6 / positions.len().max(1) as f64;
+    (scored, per_position)
+}
+
+fn report(name: &str, scored: &[(i32, f64)], per_position: f64) {
+    let k = best_k(scored);
+    println!("{:<12} error {:.6} (k = {:.3}), {:.1} µs per position", name, scored_error(scored, k), k, per_position);
+}
+
+fn main() {
+    let mut files = Vec::new();
+    let mut qdepth = 8;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--qdepth" => match args.next().and_then(|value| value.parse().ok()) {
+                Some(value) => qdepth = value,
+                None => {
+                    eprintln!("--qdepth takes a number\n{}", USAGE);
+                    process::exit(2);
+                }
+            },
+            _ => files.push(arg),
+        }
+    }
+    let [network_path, positions_path] = <[String; 2]>::try_from(files).unwrap_or_else(|_| {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    });
+
+    let network = Network::load(&network_path).unwrap_or_else(|e| {
+        eprintln!("could not load network from {}: {}", network_path, e);
+        process::exit(1);
+    });
+    let mut engine = Minimax::new(1, qdepth, false, true);
+    let mut positions = load_positions(&positions_path, &mut engine).unwrap_or_else(|e| {
+        eprintln!("could not load positions from {}: {}", positions_path, e);
+        process::exit(1);
+    });
+    println!("{} positions, network with {} hidden neurons", positions.len(), network.hidden_size());
+
+    let (classical, classical_time) = score_all(&mut positions, |position| white_score(&mut position.game));
+
+    let network = Arc::new(network);
+    for position in positions.iter_mut() {
+        position.game.set_network(Some(Arc::clone(&network)));
+    }
+    let (nnue, nnue_time) = score_all(&mut positions, |position| {
+        let game = &position.game;
+        let score = game.nnue_evaluate().unwrap();
+        if game.get_game_state().get_turn() == Colour::White { score } else { -score }
+    });
+
+    report("hand-crafted", &classical, classical_time);
+    report("nnue", &nnue, nnue_time);
+
+    let count = classical.len().max(1) as f64;
+    let mean_difference = classical.iter().zip(&nnue).map(|((a, _), (b, _))| (a - b).abs() as f64).sum::<f64>() / count;
+    let agreeing = classical.iter().zip(&nnue).filter(|((a, _), (b, _))| a.signum() == b.signum()).count();
+    println!("mean difference {:.1} cp, same side preferred in {:.1}% of positions", mean_difference, 100.0 * agreeing as f64 / count);
+}
@@ -0,0 +1,94 @@
+//! Tune the evaluation parameters against a set of positions labelled with game results.
+//!
+//! Usage: tune <positions> <output> [--params <file>] [--passes <n>] [--qdepth <n>] [--k <value>]
+//!
+//! Run a release build (`cargo run --release --bin tune -- ...`), every pass evaluates the whole set
+//! about twice per parameter value.
+//!
+//! Each line of the positions file is a FEN or EPD followed by the game result, e.g.
+//! `... w - - 0 1 [0.5]` or `... w - - c9 "1-0";`. The tuned parameters are written to the
+//! output file after every pass, in the format read by `EvalParams::load`.
+
+use std::env;
+use std::process;
+use std::sync::Arc;
+use std::time::Instant;
+
+use rust_chess::engine::eval_params::EvalParams;
+use rust_chess::engine::minimax::Minimax;
+use rust_chess::engine::tuner::{find_k, load_positions, local_search, mean_squared_error};
+
+const USAGE: &str = "usage: tune <positions> <output> [--params <file>] [--passes <n>] [--qdepth <n>] [--k <value>]";
+
+struct Args {
+    positions: String,
+    output: String,
+    params: Option<String>,
+    passes: usize,
+    qdepth: usize,
+    k: Option<f64>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut files = Vec::new();
+    let mut args = Args { positions: String::new(), output: String::new(), params: None, passes: 100, qdepth: 8, k: None };
+
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| iter.next().ok_or(format!("{} needs a value", name));
+        match arg.as_str() {
+            "--params" => args.params = Some(value("--params")?),
+            "--passes" => args.passes = value("--passes")?.parse().map_err(|_| "--passes takes a number")?,
+            "--qdepth" => args.qdepth = value("--qdepth")?.parse().map_err(|_| "--qdepth takes a number")?,
+            "--k" => args.k = Some(value("--k")?.parse().map_err(|_| "--k takes a number")?),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ => files.push(arg),
+        }
+    }
+
+    match <[String; 2]>::try_from(files) {
+        Ok([positions, output]) => {
+            args.positions = positions;
+            args.output = output;
+            Ok(args)
+        }
+        Err(_) => Err("expected a positions file and an output file".to_string()),
+    }
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|message| {
+        eprintln!("{}\n{}", message, USAGE);
+        process::exit(2);
+    });
+
+    let params = match &args.params {
+        Some(path) => EvalParams::load(path).unwrap_or_else(|e| {
+            eprintln!("could not load parameters from {}: {}", path, e);
+            process::exit(1);
+        }),
+        None => EvalParams::default(),
+    };
+
+    let start = Instant::now();
+    let mut engine = Minimax::new(1, args.qdepth, false, true);
+    let mut positions = load_positions(&args.positions, &mut engine).unwrap_or_else(|e| {
+        eprintln!("could not load positions from {}: {}", args.positions, e);
+        process::exit(1);
+    });
+    println!("loaded {} positions in {:.1}s", positions.len(), start.elapsed().as_secs_f64());
+
+    let k = args.k.unwrap_or_else(|| find_k(&mut positions, &Arc::new(params.clone())));
+    let initial_error = mean_squared_error(&mut positions, &Arc::new(params.clone()), k);
+    println!("k = {:.3}, initial error {:.6}", k, initial_error);
+
+    local_search(&mut positions, params, k, args.passes, |pass, error, tuned| {
+        println!("pass {}: error {:.6} ({:.0}s)", pass, error, start.elapsed().as_secs_f64());
+        if let Err(e) = tuned.save(&args.output) {
+            eprintln!("could not write {}: {}", args.output, e);
+            process::exit(1);
+        }
+    });
+
+    println!("tuned parameters written to {}", args.output);
+}
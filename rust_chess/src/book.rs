@@ -0,0 +1,256 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rand::Rng;
+
+use crate::enums::{File, PieceType};
+use crate::game_classes::game::Game;
+use crate::game_classes::game_state::CastlingRights;
+use crate::game_classes::polyglot_zobrist::polyglot_hash;
+use crate::moves::compact_move::{Move, MoveKind};
+use crate::moves::move_generator::MoveGenerator;
+use crate::square::Square;
+
+const ENTRY_SIZE: usize = 16;
+
+/// How to choose between several book moves for the same position
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BookPick {
+    /// Always play the highest weighted move
+    Best,
+    /// Play each move with probability proportional to its weight
+    WeightedRandom,
+}
+
+/// One 16 byte record of a Polyglot `.bin` book
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub mv: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            mv: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0u8; ENTRY_SIZE];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.mv.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+}
+
+/// Polyglot opening book, held in memory and sorted by position key
+pub struct OpeningBook {
+    entries: Vec<BookEntry>,
+}
+
+impl OpeningBook {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        Ok(Self::from_bytes(&bytes))
+    }
+
+    /// Parse raw book data. A trailing partial entry is ignored.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut entries: Vec<BookEntry> = bytes.chunks_exact(ENTRY_SIZE).map(BookEntry::from_bytes).collect();
+
+        // Books are sorted on disk, but don't rely on it for binary search
+        entries.sort_by_key(|entry| entry.key);
+        Self { entries }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// All raw entries stored for a position key
+    pub fn entries_for(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = self.entries.partition_point(|entry| entry.key <= key);
+        &self.entries[start..end]
+    }
+
+    /// Legal book moves for the current position together with their weights
+    pub fn book_moves(&self, game: &mut Game) -> Vec<(Move, u16)> {
+        let entries = self.entries_for(polyglot_hash(game));
+        if entries.is_empty() {
+            return Vec::new();
+        }
+
+        let colour = game.get_game_state().get_turn();
+        let mut legal_moves = Vec::new();
+        MoveGenerator::generate_legal_moves_into(game, colour, false, &mut legal_moves);
+
+        // Matching against generated moves fills in the move kind and drops illegal or corrupt entries
+        entries.iter()
+            .filter_map(|entry| {
+                let (from, to, promotion) = decode_move(entry.mv);
+                legal_moves.iter()
+                    .find(|mv| {
+                        let to_matches = mv.to_sq() == to
+                            || (mv.kind() == MoveKind::Castling && castling_rook_square(game, **mv) == to);
+                        mv.from_sq() == from && to_matches && mv.promotion_piece() == promotion
+                    })
+                    .map(|mv| (*mv, entry.weight))
+            })
+            .collect()
+    }
+
+    pub fn pick_move(&self, game: &mut Game, pick: BookPick) -> Option<Move> {
+        let moves = self.book_moves(game);
+
+        match pick {
+            BookPick::Best => moves.iter().max_by_key(|(_, weight)| *weight).map(|(mv, _)| *mv),
+            BookPick::WeightedRandom => {
+                let total: u32 = moves.iter().map(|(_, weight)| *weight as u32).sum();
+                if total == 0 {
+                    // All weights zero: fall back to a uniform choice
+                    return (!moves.is_empty()).then(|| moves[rand::rng().random_range(0..moves.len())].0);
+                }
+
+                let mut roll = rand::rng().random_range(0..total);
+                for (mv, weight) in &moves {
+                    if roll < *weight as u32 {
+                        return Some(*mv);
+                    }
+                    roll -= *weight as u32;
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Polyglot layout: bits 0-5 to square, bits 6-11 from square, bits 12-14 promotion piece
+fn decode_move(raw: u16) -> (Square, Square, Option<PieceType>) {
+    let to = Square::new((raw & 0x3F) as u8);
+    let from = Square::new(((raw >> 6) & 0x3F) as u8);
+    let promotion = match (raw >> 12) & 0x7 {
+        1 => Some(PieceType::Knight),
+        2 => Some(PieceType::Bishop),
+        3 => Some(PieceType::Rook),
+        4 => Some(PieceType::Queen),
+        _ => None,
+    };
+    (from, to, promotion)
+}
+
+/// Encode a move of the side to move in `game` the way Polyglot stores it, with castling
+/// written as king takes rook
+pub fn encode_move(game: &Game, mv: Move) -> u16 {
+    let to = if mv.kind() == MoveKind::Castling { castling_rook_square(game, mv) } else { mv.to_sq() };
+    let promotion = match mv.promotion_piece() {
+        Some(PieceType::Knight) => 1,
+        Some(PieceType::Bishop) => 2,
+        Some(PieceType::Rook) => 3,
+        Some(PieceType::Queen) => 4,
+        _ => 0,
+    };
+    to.index() as u16 | ((mv.from_sq().index() as u16) << 6) | (promotion << 12)
+}
+
+fn castling_rook_square(game: &Game, mv: Move) -> Square {
+    // Kingside castling lands on the g file. The rook's square comes from the game state,
+    // since in Chess960 it can start on any file.
+    let game_state = game.get_game_state();
+    let right = CastlingRights::side(game_state.get_turn(), mv.to_coords().file == File::G);
+    Square::from(game_state.castling_rook_coords(right))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book_from(entries: &[BookEntry]) -> OpeningBook {
+        let bytes: Vec<u8> = entries.iter().flat_map(|entry| entry.to_bytes()).collect();
+        OpeningBook::from_bytes(&bytes)
+    }
+
+    fn entry(game: &mut Game, uci: &str, weight: u16) -> BookEntry {
+        let colour = game.get_game_state().get_turn();
+        let mut moves = Vec::new();
+        MoveGenerator::generate_legal_moves_into(game, colour, false, &mut moves);
+        let mv = moves.into_iter().find(|mv| mv.to_string() == uci).unwrap();
+        BookEntry { key: polyglot_hash(game), mv: encode_move(game, mv), weight, learn: 0 }
+    }
+
+    #[test]
+    fn test_entry_round_trip() {
+        let entry = BookEntry { key: 0x463b96181691fc9c, mv: 0x031c, weight: 7, learn: 42 };
+        assert_eq!(BookEntry::from_bytes(&entry.to_bytes()), entry);
+    }
+
+    #[test]
+    fn test_pick_best_and_weighted() {
+        let mut game = Game::new();
+        let book = book_from(&[entry(&mut game, "d2d4", 5), entry(&mut game, "e2e4", 10), entry(&mut game, "a2a3", 0)]);
+
+        assert_eq!(book.book_moves(&mut game).len(), 3);
+        assert_eq!(book.pick_move(&mut game, BookPick::Best).unwrap().to_string(), "e2e4");
+
+        for _ in 0..50 {
+            let mv = book.pick_move(&mut game, BookPick::WeightedRandom).unwrap().to_string();
+            assert!(mv == "e2e4" || mv == "d2d4", "Zero weight move {} was picked", mv);
+        }
+
+        // Position not in the book
+        game.set_fenstr("8/8/8/8/8/8/8/K6k w - - 0 1");
+        assert_eq!(book.pick_move(&mut game, BookPick::Best), None);
+    }
+
+    #[test]
+    fn test_castling_is_stored_as_king_takes_rook() {
+        let mut game = Game::new();
+        game.set_fenstr("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+
+        let kingside = entry(&mut game, "e1g1", 1);
+        let (from, to, _) = decode_move(kingside.mv);
+        assert_eq!((from, to), (Square::E1, Square::H1));
+
+        let queenside = entry(&mut game, "e1c1", 1);
+        let (from, to, _) = decode_move(queenside.mv);
+        assert_eq!((from, to), (Square::E1, Square::A1));
+
+        let book = book_from(&[kingside, queenside]);
+        let mut moves: Vec<String> = book.book_moves(&mut game).iter().map(|(mv, _)| mv.to_string()).collect();
+        moves.sort();
+        assert_eq!(moves, ["e1c1", "e1g1"]);
+        assert!(book.book_moves(&mut game).iter().all(|(mv, _)| mv.kind() == MoveKind::Castling));
+    }
+
+    #[test]
+    fn test_chess960_castling_takes_the_castling_rook() {
+        let mut game = Game::new();
+        game.set_fenstr("4k3/8/8/8/8/8/8/1R2K1R1 w GB - 0 1");
+
+        let kingside = entry(&mut game, "e1g1", 1);
+        let (from, to, _) = decode_move(kingside.mv);
+        assert_eq!((from, to), (Square::E1, Square::G1));
+
+        let queenside = entry(&mut game, "e1c1", 1);
+        let (from, to, _) = decode_move(queenside.mv);
+        assert_eq!((from, to), (Square::E1, Square::B1));
+
+        let book = book_from(&[kingside, queenside]);
+        let mut moves: Vec<String> = book.book_moves(&mut game).iter().map(|(mv, _)| mv.to_string()).collect();
+        moves.sort();
+        assert_eq!(moves, ["e1c1", "e1g1"]);
+    }
+}
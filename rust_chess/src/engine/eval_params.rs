@@ -0,0 +1,254 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+
+use crate::engine::evaluator::{BISHOP_VALUE, KING_VALUE, KNIGHT_VALUE, PAWN_VALUE, QUEEN_VALUE, ROOK_VALUE};
+use crate::engine::pawn_structure::{
+    BACKWARD_PAWN, CONNECTED_PAWN, DOUBLED_PAWN, ISOLATED_PAWN, PASSED_ENEMY_KING_DISTANCE, PASSED_OWN_KING_DISTANCE,
+    PASSED_PAWN_EG, PASSED_PAWN_MG, UNSTOPPABLE_PAWN,
+};
+use crate::engine::piece_square_tables::{EG_PST, MG_PST};
+use crate::engine::positional::{
+    BISHOP_PAIR, KING_ATTACKER_SCALE, KING_ATTACK_UNIT, KING_ATTACK_WEIGHT, KING_OPEN_FILE, KING_SEMI_OPEN_FILE,
+    KNIGHT_OUTPOST, MOBILITY, MOBILITY_BASELINE, PAWN_SHIELD_CLOSE, PAWN_SHIELD_FAR, ROOK_ON_SEVENTH, ROOK_OPEN_FILE,
+    ROOK_SEMI_OPEN_FILE, TRAPPED_BISHOP, TRAPPED_ROOK,
+};
+
+/// The built in parameters, shared by every `Game` that isn't given its own
+pub static DEFAULT_EVAL_PARAMS: Lazy<Arc<EvalParams>> = Lazy::new(|| Arc::new(EvalParams::default()));
+
+const PIECE_NAMES: [&str; 6] = ["pawn", "knight", "bishop", "rook", "queen", "king"];
+
+/// Every weight the evaluator uses. Pairs are (middlegame, endgame); tables indexed by piece
+/// type are in `PieceType` order and piece-square tables are laid out rank 8 first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EvalParams {
+    pub piece_values: [i32; 6],
+    pub mg_pst: [[[i32; 8]; 8]; 6],
+    pub eg_pst: [[[i32; 8]; 8]; 6],
+
+    pub doubled_pawn: [i32; 2],
+    pub isolated_pawn: [i32; 2],
+    pub backward_pawn: [i32; 2],
+    pub connected_pawn: [i32; 2],
+    pub passed_pawn_mg: [i32; 8],
+    pub passed_pawn_eg: [i32; 8],
+    pub passed_enemy_king_distance: i32,
+    pub passed_own_king_distance: i32,
+    pub unstoppable_pawn: i32,
+
+    pub mobility: [[i32; 2]; 6],
+    pub mobility_baseline: [i32; 6],
+    pub king_attack_weight: [i32; 6],
+    pub king_attacker_scale: [i32; 8],
+    pub king_attack_unit: [i32; 2],
+    pub pawn_shield_close: [i32; 2],
+    pub pawn_shield_far: [i32; 2],
+    pub king_semi_open_file: [i32; 2],
+    pub king_open_file: [i32; 2],
+    pub bishop_pair: [i32; 2],
+    pub rook_open_file: [i32; 2],
+    pub rook_semi_open_file: [i32; 2],
+    pub rook_on_seventh: [i32; 2],
+    pub knight_outpost: [i32; 2],
+    pub trapped_bishop: [i32; 2],
+    pub trapped_rook: [i32; 2],
+}
+
+fn pair((mg, eg): (i32, i32)) -> [i32; 2] {
+    [mg, eg]
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        Self {
+            piece_values: [PAWN_VALUE, KNIGHT_VALUE, BISHOP_VALUE, ROOK_VALUE, QUEEN_VALUE, KING_VALUE],
+            mg_pst: MG_PST.map(|table| *table),
+            eg_pst: EG_PST.map(|table| *table),
+
+            doubled_pawn: pair(DOUBLED_PAWN),
+            isolated_pawn: pair(ISOLATED_PAWN),
+            backward_pawn: pair(BACKWARD_PAWN),
+            connected_pawn: pair(CONNECTED_PAWN),
+            passed_pawn_mg: PASSED_PAWN_MG,
+            passed_pawn_eg: PASSED_PAWN_EG,
+            passed_enemy_king_distance: PASSED_ENEMY_KING_DISTANCE,
+            passed_own_king_distance: PASSED_OWN_KING_DISTANCE,
+            unstoppable_pawn: UNSTOPPABLE_PAWN,
+
+            mobility: MOBILITY.map(pair),
+            mobility_baseline: MOBILITY_BASELINE,
+            king_attack_weight: KING_ATTACK_WEIGHT,
+            king_attacker_scale: KING_ATTACKER_SCALE,
+            king_attack_unit: pair(KING_ATTACK_UNIT),
+            pawn_shield_close: pair(PAWN_SHIELD_CLOSE),
+            pawn_shield_far: pair(PAWN_SHIELD_FAR),
+            king_semi_open_file: pair(KING_SEMI_OPEN_FILE),
+            king_open_file: pair(KING_OPEN_FILE),
+            bishop_pair: pair(BISHOP_PAIR),
+            rook_open_file: pair(ROOK_OPEN_FILE),
+            rook_semi_open_file: pair(ROOK_SEMI_OPEN_FILE),
+            rook_on_seventh: pair(ROOK_ON_SEVENTH),
+            knight_outpost: pair(KNIGHT_OUTPOST),
+            trapped_bishop: pair(TRAPPED_BISHOP),
+            trapped_rook: pair(TRAPPED_ROOK),
+        }
+    }
+}
+
+impl EvalParams {
+    /// The shared default parameters
+    pub fn shared() -> Arc<EvalParams> {
+        Arc::clone(&DEFAULT_EVAL_PARAMS)
+    }
+
+    /// Every parameter by name, as a flat slice of its values
+    pub fn fields_mut(&mut self) -> Vec<(String, &mut [i32])> {
+        let mut fields: Vec<(String, &mut [i32])> = vec![("piece_values".to_string(), &mut self.piece_values)];
+
+        for (name, table) in PIECE_NAMES.iter().zip(self.mg_pst.iter_mut()) {
+            fields.push((format!("mg_pst_{}", name), table.as_flattened_mut()));
+        }
+        for (name, table) in PIECE_NAMES.iter().zip(self.eg_pst.iter_mut()) {
+            fields.push((format!("eg_pst_{}", name), table.as_flattened_mut()));
+        }
+
+        let named: Vec<(&str, &mut [i32])> = vec![
+            ("doubled_pawn", &mut self.doubled_pawn),
+            ("isolated_pawn", &mut self.isolated_pawn),
+            ("backward_pawn", &mut self.backward_pawn),
+            ("connected_pawn", &mut self.connected_pawn),
+            ("passed_pawn_mg", &mut self.passed_pawn_mg),
+            ("passed_pawn_eg", &mut self.passed_pawn_eg),
+            ("passed_enemy_king_distance", std::slice::from_mut(&mut self.passed_enemy_king_distance)),
+            ("passed_own_king_distance", std::slice::from_mut(&mut self.passed_own_king_distance)),
+            ("unstoppable_pawn", std::slice::from_mut(&mut self.unstoppable_pawn)),
+            ("mobility", self.mobility.as_flattened_mut()),
+            ("mobility_baseline", &mut self.mobility_baseline),
+            ("king_attack_weight", &mut self.king_attack_weight),
+            ("king_attacker_scale", &mut self.king_attacker_scale),
+            ("king_attack_unit", &mut self.king_attack_unit),
+            ("pawn_shield_close", &mut self.pawn_shield_close),
+            ("pawn_shield_far", &mut self.pawn_shield_far),
+            ("king_semi_open_file", &mut self.king_semi_open_file),
+            ("king_open_file", &mut self.king_open_file),
+            ("bishop_pair", &mut self.bishop_pair),
+            ("rook_open_file", &mut self.rook_open_file),
+            ("rook_semi_open_file", &mut self.rook_semi_open_file),
+            ("rook_on_seventh", &mut self.rook_on_seventh),
+            ("knight_outpost", &mut self.knight_outpost),
+            ("trapped_bishop", &mut self.trapped_bishop),
+            ("trapped_rook", &mut self.trapped_rook),
+        ];
+        fields.extend(named.into_iter().map(|(name, values)| (name.to_string(), values)));
+
+        fields
+    }
+
+    /// Read parameters written by `to_text`. Each line is a name followed by its values, and a
+    /// trailing `\` continues it on the next line. Blank lines and `#` comments are skipped and
+    /// anything not listed keeps its default.
+    pub fn from_text(text: &str) -> io::Result<Self> {
+        let mut params = Self::default();
+        let invalid = |line: usize, message: String| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, message));
+
+        let mut entry = String::new();
+        let mut entry_start = 0;
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if entry.is_empty() {
+                entry_start = index + 1;
+            }
+            if let Some(continued) = line.strip_suffix('\\') {
+                entry.push_str(continued);
+                entry.push(' ');
+                continue;
+            }
+            entry.push_str(line);
+
+            let mut tokens = entry.split_whitespace();
+            if let Some(name) = tokens.next() {
+                let values = tokens
+                    .map(|token| token.parse::<i32>().map_err(|_| invalid(entry_start, format!("'{}' is not a number", token))))
+                    .collect::<io::Result<Vec<i32>>>()?;
+
+                let mut fields = params.fields_mut();
+                let Some((_, field)) = fields.iter_mut().find(|(field_name, _)| field_name == name) else {
+                    return Err(invalid(entry_start, format!("unknown parameter '{}'", name)));
+                };
+                if values.len() != field.len() {
+                    return Err(invalid(entry_start, format!("'{}' takes {} values, got {}", name, field.len(), values.len())));
+                }
+                field.copy_from_slice(&values);
+            }
+            entry.clear();
+        }
+
+        Ok(params)
+    }
+
+    /// Every parameter, one per line, piece-square tables one rank per line
+    pub fn to_text(&self) -> String {
+        let mut params = self.clone();
+        let mut text = String::new();
+
+        for (name, values) in params.fields_mut() {
+            let rows: Vec<String> = if name.contains("pst") {
+                values.chunks(8).map(format_values).collect()
+            } else {
+                vec![format_values(values)]
+            };
+            text.push_str(&format!("{} {}\n", name, rows.join(" \\\n    ")));
+        }
+
+        text
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_text(&fs::read_to_string(path)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+}
+
+fn format_values(values: &[i32]) -> String {
+    values.iter().map(|value| format!("{:4}", value)).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_round_trip() {
+        let mut params = EvalParams::default();
+        params.piece_values[1] = 300;
+        params.mg_pst[5][7][6] = 42;
+        params.trapped_rook = [-1, -2];
+
+        let text = params.to_text();
+        assert_eq!(EvalParams::from_text(&text).unwrap(), params);
+
+        let path = std::env::temp_dir().join(format!("rust_chess_eval_params_{}.txt", std::process::id()));
+        params.save(&path).unwrap();
+        assert_eq!(EvalParams::load(&path).unwrap(), params);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_partial_and_invalid_files() {
+        let params = EvalParams::from_text("# only override a couple\nbishop_pair 10 20\n\npiece_values 1 2 3 4 5 6 # comment\n").unwrap();
+        assert_eq!(params.bishop_pair, [10, 20]);
+        assert_eq!(params.piece_values, [1, 2, 3, 4, 5, 6]);
+        assert_eq!(params.rook_open_file, EvalParams::default().rook_open_file);
+
+        assert!(EvalParams::from_text("no_such_param 1").is_err());
+        assert!(EvalParams::from_text("bishop_pair 1").is_err());
+        assert!(EvalParams::from_text("bishop_pair 1 x").is_err());
+    }
+}
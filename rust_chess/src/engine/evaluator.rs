@@ -1,11 +1,15 @@
-use crate::engine::piece_square_tables::{BISHOP_PST, KING_PST, KNIGHT_PST, PAWN_PST, QUEEN_PST, ROOK_PST};
+use crate::engine::eval_params::EvalParams;
+use crate::engine::pawn_structure::{evaluate_passed_pawn_endgame, probe_pawns, PawnHashTable};
+use crate::engine::positional::{evaluate_positional, PositionalTerms};
 use crate::enums::{Colour, PieceType};
 use crate::coords::Coords;
 use crate::piece::Piece;
+use crate::game_classes::board_classes::board::Board;
 use crate::game_classes::game::Game;
 use crate::game_classes::game::GameResult;
 use crate::engine::minimax::INF;
 
+// Default piece values. The evaluator reads them through `EvalParams`, move ordering uses them as they are.
 pub const PAWN_VALUE: i32 = 100;
 pub const KNIGHT_VALUE: i32 = 320;
 pub const BISHOP_VALUE: i32 = 330;
@@ -13,12 +17,58 @@ pub const ROOK_VALUE: i32 = 500;
 pub const QUEEN_VALUE: i32 = 900;
 pub const KING_VALUE: i32 = 20000;
 
+/// Phase of the starting position; 0 means only kings and pawns are left
+pub const MAX_PHASE: i32 = 24;
+
 pub struct Evaluator;
 
+/// Every evaluation term for each side, indexed by `Colour`, as (middlegame, endgame) scores
+/// from that side's point of view
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EvalTrace {
+    /// Kings are left out, they always cancel
+    pub material: [(i32, i32); 2],
+    pub pst: [(i32, i32); 2],
+    /// Pawn structure plus the endgame passed pawn terms
+    pub pawn_structure: [(i32, i32); 2],
+    pub positional: PositionalTerms,
+    pub phase: i32,
+    /// Final score from white's point of view, exactly as the search sees it
+    pub total: i32,
+}
+
+impl EvalTrace {
+    /// Each term with its name, in the order they're reported
+    pub fn terms(&self) -> [(&'static str, [(i32, i32); 2]); 9] {
+        [
+            ("material", self.material),
+            ("pst", self.pst),
+            ("pawn_structure", self.pawn_structure),
+            ("mobility", self.positional.mobility),
+            ("king_safety", self.positional.king_safety),
+            ("bishop_pair", self.positional.bishop_pair),
+            ("rooks", self.positional.rooks),
+            ("outposts", self.positional.outposts),
+            ("trapped_pieces", self.positional.trapped_pieces),
+        ]
+    }
+}
+
 impl Evaluator {
     pub fn evaluate_game_result(game: &mut Game, game_result: Option<GameResult>, depth: usize, to_move: Colour) -> i32 {
+        Self::evaluate_with_pawn_table(game, game_result, depth, to_move, None)
+    }
+
+    /// As `evaluate_game_result`, reusing pawn structure scores cached in `pawn_table`
+    pub fn evaluate_with_pawn_table(
+        game: &mut Game,
+        game_result: Option<GameResult>,
+        depth: usize,
+        to_move: Colour,
+        pawn_table: Option<&mut PawnHashTable>,
+    ) -> i32 {
         match game_result {
-            Some(GameResult::Checkmate(loser)) => {
+            Some(GameResult::Checkmate(loser)) | Some(GameResult::VariantLoss(loser)) => {
                 if loser == to_move {
                     -INF + depth as i32
                 }
@@ -27,7 +77,7 @@ impl Evaluator {
                 }
             }
             Some(GameResult::Stalemate) | Some(GameResult::Draw) => 0,
-            None => Self::evaluate_pst(game)
+            None => Self::evaluate_position(game, pawn_table)
         }
     }
 
@@ -42,6 +92,36 @@ impl Evaluator {
         }
     }
 
+    /// Weight of a piece in the game phase, so that phase falls as material is traded
+    pub fn phase_weight(piece_type: PieceType) -> i32 {
+        match piece_type {
+            PieceType::Knight | PieceType::Bishop => 1,
+            PieceType::Rook => 2,
+            PieceType::Queen => 4,
+            PieceType::Pawn | PieceType::King => 0,
+        }
+    }
+
+    /// Game phase from the remaining non-pawn material, from `MAX_PHASE` (opening) down to 0.
+    /// Capped so early promotions don't push it past the opening.
+    pub fn game_phase(game: &Game) -> i32 {
+        let board = game.get_board();
+        let mut phase = 0;
+
+        for colour in [Colour::White, Colour::Black] {
+            for kind in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+                phase += board.get_piece_bit_board(Piece { kind, colour }).num_set_bits() * Self::phase_weight(kind);
+            }
+        }
+
+        phase.min(MAX_PHASE)
+    }
+
+    /// Blend middlegame and endgame scores by phase
+    pub fn taper(mg: i32, eg: i32, phase: i32) -> i32 {
+        (mg * phase + eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+
     fn pst_value(pst: &[[i32; 8]; 8], coords: Coords, colour: Colour) -> i32 {
         match colour {
             Colour::White => pst[8 - coords.rank as usize][coords.file as usize], // rank 0 is White’s back rank
@@ -49,32 +129,151 @@ impl Evaluator {
         }
     }
 
-    fn evaluate_piece(piece: Piece, coords: Coords) -> i32 {
-        match piece.kind {
-            PieceType::Pawn => PAWN_VALUE + Self::pst_value(&PAWN_PST, coords, piece.colour),
-            PieceType::Knight => KNIGHT_VALUE + Self::pst_value(&KNIGHT_PST, coords, piece.colour),
-            PieceType::Bishop => BISHOP_VALUE + Self::pst_value(&BISHOP_PST, coords, piece.colour),
-            PieceType::Rook => ROOK_VALUE+ Self::pst_value(&ROOK_PST, coords, piece.colour),
-            PieceType::Queen => QUEEN_VALUE+ Self::pst_value(&QUEEN_PST, coords, piece.colour),
-            PieceType::King => KING_VALUE + Self::pst_value(&KING_PST, coords, piece.colour)
-        }
+    /// Middlegame and endgame value of a piece on a square, material included
+    pub fn evaluate_piece(params: &EvalParams, piece: Piece, coords: Coords) -> (i32, i32) {
+        let kind = piece.kind as usize;
+        let value = params.piece_values[kind];
+        (
+            value + Self::pst_value(&params.mg_pst[kind], coords, piece.colour),
+            value + Self::pst_value(&params.eg_pst[kind], coords, piece.colour),
+        )
+    }
+
+    /// Material and piece-square scores of each side, indexed by `Colour`, summed piece by piece.
+    /// `Game` keeps the same scores up to date incrementally.
+    pub fn material_pst_from_scratch(board: &Board, params: &EvalParams) -> [(i32, i32); 2] {
+        let mut scores = [(0, 0); 2];
 
+        for (piece, square) in board.pieces() {
+            let (mg, eg) = Self::evaluate_piece(params, piece, Coords::from(square));
+            let score = &mut scores[piece.colour as usize];
+            score.0 += mg;
+            score.1 += eg;
+        }
 
+        scores
     }
 
     fn evaluate_pst(game: &mut Game) -> i32 {
         let current_player = game.get_game_state().get_turn();
+        let scores = game.get_material_pst();
+        debug_assert_eq!(
+            scores,
+            Self::material_pst_from_scratch(game.get_board(), game.get_eval_params()),
+            "Incremental material/PST scores out of step with the board"
+        );
 
-        let mut eval = 0;
+        let (own, other) = (scores[current_player as usize], scores[current_player.other() as usize]);
+        Self::taper(own.0 - other.0, own.1 - other.1, Self::game_phase(game))
+    }
 
-        for (piece, coords) in game.get_board().get_player_pieces(current_player) {
-            eval += Self::evaluate_piece(piece, coords);
-        }
+    fn evaluate_position(game: &mut Game, pawn_table: Option<&mut PawnHashTable>) -> i32 {
+        let pawns = probe_pawns(game, pawn_table);
+        let (pawn_mg, pawn_eg) = pawns.total();
+        let [white_passed, black_passed] = evaluate_passed_pawn_endgame(game, &pawns);
+        let (positional_mg, positional_eg) = evaluate_positional(game.get_board(), game.get_eval_params()).total();
+        let mg = pawn_mg + positional_mg;
+        let eg = pawn_eg + white_passed - black_passed + positional_eg;
 
-        for (piece, coords) in game.get_board().get_player_pieces(current_player.other()) {
-            eval -= Self::evaluate_piece(piece, coords);
+        // pawn and positional scores are from white's point of view
+        let sign = if game.get_game_state().get_turn() == Colour::White { 1 } else { -1 };
+        Self::evaluate_pst(game) + sign * Self::taper(mg, eg, Self::game_phase(game)) + game.variant().evaluation_adjustment(game)
+    }
+
+    /// Per-term, per-side breakdown of the static evaluation
+    pub fn eval_trace(game: &mut Game) -> EvalTrace {
+        let board = game.get_board();
+        let params = game.get_eval_params();
+        let mut material = [(0, 0); 2];
+        let mut pst = [(0, 0); 2];
+
+        for (piece, coords) in board.get_all_pieces() {
+            let side = piece.colour as usize;
+            if piece.kind != PieceType::King {
+                let value = params.piece_values[piece.kind as usize];
+                material[side].0 += value;
+                material[side].1 += value;
+            }
+            pst[side].0 += Self::pst_value(&params.mg_pst[piece.kind as usize], coords, piece.colour);
+            pst[side].1 += Self::pst_value(&params.eg_pst[piece.kind as usize], coords, piece.colour);
         }
 
-        eval
+        let pawns = probe_pawns(game, None);
+        let passed = evaluate_passed_pawn_endgame(game, &pawns);
+        let pawn_structure = [Colour::White, Colour::Black].map(|colour| {
+            let (mg, eg) = pawns.scores[colour as usize];
+            (mg, eg + passed[colour as usize])
+        });
+
+        let positional = evaluate_positional(board, params);
+        let phase = Self::game_phase(game);
+        let sign = if game.get_game_state().get_turn() == Colour::White { 1 } else { -1 };
+        let total = sign * Self::evaluate_position(game, None);
+
+        EvalTrace { material, pst, pawn_structure, positional, phase, total }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game_from_fen(fen: &str) -> Game {
+        let mut game = Game::new();
+        game.set_fenstr(fen);
+        game
+    }
+
+    #[test]
+    fn test_game_phase() {
+        assert_eq!(Evaluator::game_phase(&Game::new()), MAX_PHASE);
+
+        // Kings and pawns only
+        assert_eq!(Evaluator::game_phase(&game_from_fen("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1")), 0);
+
+        // Rook and knight each: 2 * (2 + 1)
+        assert_eq!(Evaluator::game_phase(&game_from_fen("3rk1n1/8/8/8/8/8/8/1N1RK3 w - - 0 1")), 6);
+
+        // Extra queens don't go past the opening phase
+        assert_eq!(Evaluator::game_phase(&game_from_fen("QQQQkQQQ/8/8/8/8/8/8/qqqqKqqq w - - 0 1")), MAX_PHASE);
+    }
+
+    #[test]
+    fn test_eval_trace_adds_up() {
+        let mut game = game_from_fen("r1bqk2r/pp2bppp/2n1pn2/2pp4/3P4/2PBPN2/PP1N1PPP/R1BQK2R w KQkq - 0 1");
+        let trace = Evaluator::eval_trace(&mut game);
+        assert_eq!(trace.material[0], trace.material[1]);
+        assert_eq!(trace.phase, MAX_PHASE);
+
+        let (mg, eg) = trace.terms().iter().fold((0, 0), |(mg, eg), (_, [white, black])| {
+            (mg + white.0 - black.0, eg + white.1 - black.1)
+        });
+        // The search tapers material/PST and the other terms separately, so allow for rounding
+        assert!((Evaluator::taper(mg, eg, trace.phase) - trace.total).abs() <= 1);
+        assert_eq!(trace.total, Evaluator::evaluate_game_result(&mut game, None, 0, Colour::White));
+
+        // Always from white's point of view
+        let mut black_to_move = game_from_fen("r1bqk2r/pp2bppp/2n1pn2/2pp4/3P4/2PBPN2/PP1N1PPP/R1BQK2R b KQkq - 0 1");
+        assert_eq!(Evaluator::eval_trace(&mut black_to_move).total, trace.total);
+    }
+
+    #[test]
+    fn test_taper_interpolates() {
+        assert_eq!(Evaluator::taper(100, -100, MAX_PHASE), 100);
+        assert_eq!(Evaluator::taper(100, -100, 0), -100);
+        assert_eq!(Evaluator::taper(100, -100, MAX_PHASE / 2), 0);
+    }
+
+    #[test]
+    fn test_king_placement_depends_on_phase() {
+        // With all the pieces on, a castled king beats a king wandering in the centre
+        let mut castled = game_from_fen("rnbq1rk1/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1RK1 w - - 0 1");
+        let mut central = game_from_fen("rnbq1rk1/pppppppp/8/8/4K3/8/PPPPPPPP/RNBQ1R2 w - - 0 1");
+        assert!(Evaluator::evaluate_pst(&mut castled) > Evaluator::evaluate_pst(&mut central));
+
+        // In a pawn ending the king belongs in the centre
+        let mut cornered = game_from_fen("6k1/pppppppp/8/8/8/8/PPPPPPPP/6K1 w - - 0 1");
+        let mut centralised = game_from_fen("6k1/pppppppp/8/8/4K3/8/PPPPPPPP/8 w - - 0 1");
+        assert!(Evaluator::evaluate_pst(&mut centralised) > Evaluator::evaluate_pst(&mut cornered));
     }
-}
\ No newline at end of file
+}
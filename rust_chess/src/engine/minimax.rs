@@ -1,13 +1,20 @@
 use std::any::Any;
 use std::collections::HashMap;
 
-use crate::game_classes::game::Game;
+use crate::game_classes::game::{Game, GameResult};
+use crate::moves::compact_move::Move;
 use crate::moves::move_generator::MoveGenerator;
 use crate::enums::{ChessMove, Colour};
-use crate::move_ordering::order_moves;
+use crate::move_ordering::{is_noisy_move, order_moves, order_moves_with_hints};
 use crate::engine::evaluator::Evaluator;
+use crate::engine::pawn_structure::PawnHashTable;
+use crate::book::{BookPick, OpeningBook};
+use crate::syzygy::tablebase::{Tablebase, Wdl};
+use crate::variants::{Standard, Variant};
 
 pub const INF: i32 = 30_000;
+// Tablebase wins score below any mate found by search
+pub const TB_WIN: i32 = INF - 1_000;
 
 #[derive(Clone, Copy)]
 pub enum Bound {
@@ -22,6 +29,7 @@ pub struct TTEntry {
     pub value: i32,
     pub bound: Bound,
     pub is_quiescence: bool,
+    pub best_move: Move,
 }
 
 pub struct EngineOptions {
@@ -29,20 +37,33 @@ pub struct EngineOptions {
     pub quiescence_max_depth: usize,
     pub use_transposition_tables: bool,
     pub magic_bitboards: bool,
+    pub book_pick: BookPick,
+    // tablebases are only probed with at most this many pieces on the board
+    pub syzygy_probe_limit: usize,
+    // evaluate with the game's network instead of the hand-crafted evaluation, when it has one
+    pub use_nnue: bool,
 }
 
 pub struct Minimax {
     pub engine_options: EngineOptions,
     pub tt: HashMap<u64, TTEntry>,
+    // pawn structure scores, which only change when pawns move
+    pub pawn_table: PawnHashTable,
 
     // Debugging counters
     pub nodes: usize,
     pub tt_hits: usize,
 
-    // move buffers: one Vec<ChessMove> per ply (0..=max_depth)
-    pub move_buffers: Vec<Vec<ChessMove>>,
+    // move buffers: one Vec<Move> per ply (0..=max_depth)
+    pub move_buffers: Vec<Vec<Move>>,
     // tactical buffers for quiescence (captures/promotions) per ply
-    pub tactical_buffers: Vec<Vec<ChessMove>>,
+    pub tactical_buffers: Vec<Vec<Move>>,
+    // two quiet moves per ply that most recently caused a beta cutoff
+    pub killer_moves: Vec<[Move; 2]>,
+    // consulted before searching when set
+    pub book: Option<OpeningBook>,
+    // used to filter root moves and cut off search once the position is in the tables
+    pub tablebase: Option<Tablebase>,
 }
 
 impl Minimax {
@@ -55,6 +76,9 @@ impl Minimax {
             quiescence_max_depth,
             use_transposition_tables: tt_tables,
             magic_bitboards: magic_bitboard,
+            book_pick: BookPick::WeightedRandom,
+            syzygy_probe_limit: 7,
+            use_nnue: false,
         };
 
         // preallocate per-ply buffers: need max_depth + 2 to be safe (root + depths)
@@ -73,13 +97,28 @@ impl Minimax {
         Self {
             engine_options: options,
             tt: HashMap::new(),
+            pawn_table: PawnHashTable::default(),
             nodes: 0,
             tt_hits: 0,
             move_buffers,
             tactical_buffers,
+            killer_moves: vec![[Move::NULL; 2]; buffer_count],
+            book: None,
+            tablebase: None,
         }
     }
 
+    /// Book move for the current position, if a book is loaded and has one.
+    /// Polyglot books only cover standard chess, so other variants never use them.
+    pub fn probe_book(&self, game: &mut Game) -> Option<ChessMove> {
+        if game.variant().name() != Standard.name() {
+            return None;
+        }
+        let book = self.book.as_ref()?;
+        book.pick_move(game, self.engine_options.book_pick)
+            .map(|mv| mv.to_chess_move(game))
+    }
+
     pub fn evaluate_move(&mut self, game: &mut Game, mv: &ChessMove) -> i32 {
         game.make_move(mv);
         let to_move = game.get_game_state().get_turn();
@@ -95,18 +134,76 @@ impl Minimax {
         );
 
         let game_result = game.is_game_over_with_moves(&self.move_buffers[ply], self.engine_options.magic_bitboards);
-        let out = Evaluator::evaluate_game_result(game, game_result, 0, to_move);
+        let out = self.evaluate(game, game_result, 0, to_move);
 
         game.undo_last_move();
         out
     }
 
+    /// Static evaluation with whichever evaluator the options ask for
+    fn evaluate(&mut self, game: &mut Game, game_result: Option<GameResult>, ply: usize, to_move: Colour) -> i32 {
+        if self.engine_options.use_nnue && game_result.is_none() {
+            if let Some(score) = game.nnue_evaluate() {
+                return score + game.variant().evaluation_adjustment(game);
+            }
+        }
+        Evaluator::evaluate_with_pawn_table(game, game_result, ply, to_move, Some(&mut self.pawn_table))
+    }
+
+    /// Play out the principal line of the quiescence search, so that what's left on the board is
+    /// quiet enough for its static evaluation to mean something. Returns how many moves were made,
+    /// for the caller to undo if it wants the original position back.
+    pub fn play_quiescence_line(&mut self, game: &mut Game) -> usize {
+        let max_depth = self.engine_options.quiescence_max_depth;
+        let use_magic = self.engine_options.magic_bitboards;
+        let mut played = 0;
+
+        while played < max_depth {
+            let to_move = game.get_game_state().get_turn();
+            let escape_check = game.is_player_in_check(to_move, use_magic);
+
+            let mut moves = Vec::new();
+            MoveGenerator::generate_legal_moves_into(game, to_move, use_magic, &mut moves);
+            if game.is_game_over_with_moves(&moves, use_magic).is_some() {
+                break;
+            }
+
+            // standing pat is only an option when not in check
+            let mut best_score = if escape_check { -INF } else { self.evaluate(game, None, 0, to_move) };
+            let mut best_move = None;
+            for mv in moves {
+                if !escape_check && !MoveGenerator::is_tactical_move(game, mv, use_magic) {
+                    continue;
+                }
+                game.make_compact_move(mv);
+                let score = -self.quiescence(game, -INF, INF, max_depth - played - 1, 1);
+                game.undo_last_move();
+
+                if score > best_score {
+                    best_score = score;
+                    best_move = Some(mv);
+                }
+            }
+
+            match best_move {
+                Some(mv) => {
+                    game.make_compact_move(mv);
+                    played += 1;
+                }
+                None => break,
+            }
+        }
+
+        played
+    }
+
     pub fn find_best_move(&mut self, game: &mut Game, colour: Colour) -> Option<ChessMove> {
-        let mut best_move: Option<ChessMove> = None;
+        let mut best_move: Option<Move> = None;
         let mut best_score: i32 = -INF;
+        self.clear_killer_moves();
 
         for depth in 1..=self.engine_options.max_depth {
-            let mut current_best: Option<ChessMove> = None;
+            let mut current_best: Option<Move> = None;
             let mut current_best_score = -INF;
 
             // root is ply 0
@@ -118,6 +215,7 @@ impl Minimax {
                 self.engine_options.magic_bitboards,
                 &mut self.move_buffers[root_ply],
             );
+            self.filter_root_moves(game);
             order_moves(&mut self.move_buffers[root_ply], game);
 
             // PV move promotion
@@ -130,9 +228,8 @@ impl Minimax {
 
             let len = self.move_buffers[root_ply].len();
             for i in 0..len {
-                // clone the move out (requires ChessMove: Clone)
-                let mv = self.move_buffers[root_ply][i].clone();
-                game.make_move(&mv);
+                let mv = self.move_buffers[root_ply][i];
+                game.make_compact_move(mv);
 
                 // recurse: pass ply = 1 for child
                 let score = -self.minimax(game, depth - 1, -INF, INF, colour.other(), 1);
@@ -151,10 +248,44 @@ impl Minimax {
             }
         }
 
-        best_move
+        best_move.map(|mv| mv.to_chess_move(game))
+    }
+
+    fn within_probe_limit(&self, game: &Game) -> bool {
+        game.get_board().all_occ().num_set_bits() as usize <= self.engine_options.syzygy_probe_limit
+    }
+
+    /// Drop root moves the tablebases show to be worse than the best one
+    fn filter_root_moves(&mut self, game: &mut Game) {
+        if !self.within_probe_limit(game) {
+            return;
+        }
+        if let Some(tablebase) = &self.tablebase {
+            tablebase.filter_root_moves(game, &mut self.move_buffers[0]);
+        }
+    }
+
+    fn probe_wdl(&self, game: &mut Game) -> Option<Wdl> {
+        if !self.within_probe_limit(game) {
+            return None;
+        }
+        self.tablebase.as_ref()?.probe_wdl(game)
+    }
+
+    pub fn clear_killer_moves(&mut self) {
+        self.killer_moves.iter_mut().for_each(|killers| *killers = [Move::NULL; 2]);
+    }
+
+    fn store_killer_move(&mut self, ply: usize, mv: Move) {
+        let killers = &mut self.killer_moves[ply];
+        if killers[0] != mv {
+            killers[1] = killers[0];
+            killers[0] = mv;
+        }
     }
     pub fn find_sorted_moves(&mut self, game: &mut Game, colour: Colour) -> Vec<(ChessMove, i32)> {
         let mut move_scores: Vec<(ChessMove, i32)> = Vec::new();
+        self.clear_killer_moves();
 
         // Use the configured max depth
         let depth = self.engine_options.max_depth;
@@ -168,11 +299,12 @@ impl Minimax {
             self.engine_options.magic_bitboards,
             &mut self.move_buffers[root_ply],
         );
+        self.filter_root_moves(game);
         order_moves(&mut self.move_buffers[root_ply], game);
 
         let len = self.move_buffers[root_ply].len();
         for i in 0..len {
-            let mv = self.move_buffers[root_ply][i].clone();
+            let mv = self.move_buffers[root_ply][i].to_chess_move(game);
             game.make_move(&mv);
 
             // Recurse with minimax at ply 1
@@ -193,9 +325,11 @@ impl Minimax {
     fn minimax(&mut self, game: &mut Game, depth: usize, mut alpha: i32, mut beta: i32, colour: Colour, ply: usize) -> i32 {
         self.nodes += 1;
         let hash = game.get_current_hash();
+        let mut hash_move = Move::NULL;
 
         if self.engine_options.use_transposition_tables {
             if let Some(entry) = self.tt.get(&hash) {
+                hash_move = entry.best_move;
                 if !entry.is_quiescence && entry.depth >= depth {
                     self.tt_hits += 1;
                     match entry.bound {
@@ -210,6 +344,17 @@ impl Minimax {
             }
         }
 
+        // Only probe right after a capture or pawn move, when the position has just entered the tables
+        if game.get_game_state().get_halfmove_clock() == 0 {
+            if let Some(wdl) = self.probe_wdl(game) {
+                return match wdl {
+                    Wdl::Win => TB_WIN - ply as i32,
+                    Wdl::Loss => -TB_WIN + ply as i32,
+                    _ => 0,
+                };
+            }
+        }
+
         // generate moves into buffer for this ply
         self.move_buffers[ply].clear();
         MoveGenerator::generate_legal_moves_into(
@@ -218,7 +363,7 @@ impl Minimax {
             self.engine_options.magic_bitboards,
             &mut self.move_buffers[ply],
         );
-        order_moves(&mut self.move_buffers[ply], game);
+        order_moves_with_hints(&mut self.move_buffers[ply], game, hash_move, &self.killer_moves[ply]);
 
         if let Some(result) = game.is_game_over_with_moves(&self.move_buffers[ply], self.engine_options.magic_bitboards) {
             return Evaluator::evaluate_game_result(game, Some(result), ply, colour);
@@ -230,11 +375,12 @@ impl Minimax {
 
         let orig_alpha = alpha;
         let mut best_score = -INF;
+        let mut best_move = Move::NULL;
 
         let len = self.move_buffers[ply].len();
         for i in 0..len {
-            let mv = self.move_buffers[ply][i].clone();
-            game.make_move(&mv);
+            let mv = self.move_buffers[ply][i];
+            game.make_compact_move(mv);
 
             // recursive call will generate into move_buffers[ply + 1]
             let score = -self.minimax(game, depth - 1, -beta, -alpha, colour.other(), ply+1);
@@ -243,8 +389,12 @@ impl Minimax {
 
             if score > best_score {
                 best_score = score;
+                best_move = mv;
             }
             if best_score >= beta {
+                if !is_noisy_move(mv, game) {
+                    self.store_killer_move(ply, mv);
+                }
                 break;
             }
             if best_score > alpha {
@@ -266,6 +416,7 @@ impl Minimax {
                 value: best_score,
                 bound,
                 is_quiescence: false,
+                best_move,
             };
 
             match self.tt.get(&hash) {
@@ -316,13 +467,15 @@ impl Minimax {
         let to_move = game.get_game_state().get_turn();
         let escape_check = game.is_player_in_check(to_move, self.engine_options.magic_bitboards);
 
+        let orig_alpha = alpha;
 
         // stand pat
-        let stand_pat = Evaluator::evaluate_game_result(game, None, ply, to_move);
+        let stand_pat = self.evaluate(game, None, ply, to_move);
         if max_depth == 0 || (!escape_check && stand_pat >= beta) {
             return stand_pat;
         }
-        if stand_pat > alpha {
+        // when in check standing pat isn't an option, so it mustn't narrow the window
+        if !escape_check && stand_pat > alpha {
             alpha = stand_pat;
         }
 
@@ -341,15 +494,16 @@ impl Minimax {
         }
 
         let mut best_score = if !escape_check {stand_pat} else {-INF};
+        let mut best_move = Move::NULL;
         let len = self.tactical_buffers[ply].len();
 
 
         for i in 0..len {
-            let mv = self.tactical_buffers[ply][i].clone();
-            if !escape_check && !MoveGenerator::is_tactical_move(game, &mv, self.engine_options.magic_bitboards) {
+            let mv = self.tactical_buffers[ply][i];
+            if !escape_check && !MoveGenerator::is_tactical_move(game, mv, self.engine_options.magic_bitboards) {
                 continue
             }
-            game.make_move(&mv);
+            game.make_compact_move(mv);
             let score = -self.quiescence(game, -beta, -alpha, max_depth - 1, ply + 1);
             game.undo_last_move();
 
@@ -358,6 +512,7 @@ impl Minimax {
             }
             if score > best_score {
                 best_score = score;
+                best_move = mv;
             }
             if score > alpha {
                 alpha = score;
@@ -366,11 +521,14 @@ impl Minimax {
 
         // store quiescence result if using TT (same logic as before)
         if self.engine_options.use_transposition_tables {
+            // beta cutoffs return early, so this is either exact or a fail low
+            let bound = if best_score <= orig_alpha { Bound::Upper } else { Bound::Exact };
             let new_entry = TTEntry {
                 depth: max_depth,
                 value: best_score,
-                bound: Bound::Exact,
+                bound,
                 is_quiescence: true,
+                best_move,
             };
             match self.tt.get(&hash) {
                 Some(existing) => {
@@ -393,10 +551,15 @@ impl Minimax {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Arc;
+    use crate::book::{encode_move, BookEntry};
     use crate::coords::Coords;
     use crate::enums::moves::NormalMove;
     use crate::enums::{ChessMove, Colour, File, PieceType};
     use crate::game_classes::game::Game;
+    use crate::game_classes::polyglot_zobrist::polyglot_hash;
+    use crate::square::Square;
+    use crate::variants::Crazyhouse;
 
     /// Helper: set up a starting chess position
     fn starting_game() -> Game {
@@ -418,7 +581,7 @@ mod tests {
         );
 
         // Pick the first move to evaluate
-        let mv = engine.move_buffers[0][0].clone();
+        let mv = engine.move_buffers[0][0].to_chess_move(&game);
         let eval = engine.evaluate_move(&mut game, &mv);
 
         // Evaluation should be within reasonable bounds for starting position
@@ -439,6 +602,91 @@ mod tests {
         assert!(score.abs() < 1000, "Quiescence evaluation out of bounds: {}", score);
     }
 
+    #[test]
+    fn test_quiescence_fail_low_is_stored_as_upper_bound() {
+        let mut game = starting_game();
+        let mut engine = Minimax::new(1, 2, true, true);
+
+        // Nothing beats a window far above the quiet starting position
+        let score = engine.quiescence(&mut game, 500, 600, 2, 0);
+        assert!(score <= 500);
+        let entry = engine.tt.get(&game.get_current_hash()).unwrap();
+        assert!(matches!(entry.bound, Bound::Upper));
+    }
+
+    #[test]
+    fn test_quiescence_in_check_ignores_stand_pat() {
+        // White is a rook up but in check, and every evasion loses material
+        let mut game = Game::new();
+        game.set_fenstr("2b4k/1R6/8/8/8/8/6P1/r6K w - - 0 1");
+        let mut engine = Minimax::new(1, 4, false, true);
+        let stand_pat = engine.evaluate(&mut game, None, 0, Colour::White);
+
+        let mut evasions = Vec::new();
+        MoveGenerator::generate_legal_moves_into(&mut game, Colour::White, true, &mut evasions);
+        let mut best = -INF;
+        for mv in evasions {
+            game.make_compact_move(mv);
+            best = best.max(-engine.quiescence(&mut game, -INF, INF, 3, 1));
+            game.undo_last_move();
+        }
+        assert!(best < stand_pat);
+
+        // The score is the best evasion, not a bound clipped at the stand pat
+        assert_eq!(engine.quiescence(&mut game, -INF, INF, 4, 0), best);
+    }
+
+    #[test]
+    fn test_book_is_only_used_in_standard_chess() {
+        let mut engine = Minimax::new(1, 2, false, true);
+        engine.engine_options.book_pick = BookPick::Best;
+        let mut game = starting_game();
+        let entry = BookEntry { key: polyglot_hash(&game), mv: encode_move(&game, Move::normal(Square::E2, Square::E4)), weight: 1, learn: 0 };
+        engine.book = Some(OpeningBook::from_bytes(&entry.to_bytes()));
+        assert_eq!(engine.probe_book(&mut game).unwrap().to_string(), "e2e4");
+
+        // Crazyhouse starts from the same position, but the book's moves don't apply to it
+        let mut crazyhouse = Game::with_variant(Arc::new(Crazyhouse));
+        assert_eq!(engine.probe_book(&mut crazyhouse), None);
+    }
+
+    #[test]
+    fn test_play_quiescence_line() {
+        let mut engine = Minimax::new(1, 4, false, true);
+
+        // White takes the hanging queen and black has nothing to win back
+        let mut game = Game::new();
+        game.set_fenstr("4k3/8/8/3q4/8/2N5/8/4K3 w - - 0 1");
+        assert_eq!(engine.play_quiescence_line(&mut game), 1);
+        assert_eq!(game.get_last_move().unwrap().to_string(), "c3xd5");
+        game.undo_last_move();
+
+        // Nothing to capture, the position is already quiet
+        let mut quiet = starting_game();
+        assert_eq!(engine.play_quiescence_line(&mut quiet), 0);
+    }
+
+    #[test]
+    fn test_use_nnue_switches_evaluator() {
+        use crate::engine::nnue::{Network, INPUTS};
+        use std::sync::Arc;
+
+        let mut engine = Minimax::new(1, 2, false, true);
+        let mut game = starting_game();
+        let classical = engine.evaluate(&mut game, None, 0, Colour::White);
+
+        // Only the output bias is set, so the network scores every position the same
+        let network = Network::new(1, vec![0; INPUTS], vec![0], vec![0, 0], 32);
+        game.set_network(Some(Arc::new(network)));
+        assert_eq!(engine.evaluate(&mut game, None, 0, Colour::White), classical);
+
+        engine.engine_options.use_nnue = true;
+        assert_eq!(engine.evaluate(&mut game, None, 0, Colour::White), 200);
+
+        // Game results don't go through the network
+        assert_eq!(engine.evaluate(&mut game, Some(GameResult::Stalemate), 0, Colour::White), 0);
+    }
+
     #[test]
     fn test_find_best_move_returns_valid_move() {
         let mut game = starting_game();
@@ -457,7 +705,7 @@ mod tests {
             false,
             &mut engine.move_buffers[0],
         );
-        assert!(engine.move_buffers[0].contains(&mv), "Best move is not legal");
+        assert!(engine.move_buffers[0].contains(&Move::from(&mv)), "Best move is not legal");
     }
 
     #[test]
@@ -473,9 +721,9 @@ mod tests {
             false,
             &mut engine.move_buffers[0],
         );
-        let mv = engine.move_buffers[0][0].clone();
+        let mv = engine.move_buffers[0][0];
 
-        game.make_move(&mv);
+        game.make_compact_move(mv);
         let score = -engine.minimax(&mut game, 0, -INF, INF, Colour::Black, 1);
         game.undo_last_move();
 
@@ -1,3 +1,8 @@
 pub mod minimax;
 pub mod evaluator;
-pub mod piece_square_tables;
\ No newline at end of file
+pub mod piece_square_tables;
+pub mod pawn_structure;
+pub mod positional;
+pub mod eval_params;
+pub mod tuner;
+pub mod nnue;
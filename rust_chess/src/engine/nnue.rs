@@ -0,0 +1,242 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::enums::Colour;
+use crate::game_classes::board_classes::board::Board;
+use crate::piece::Piece;
+
+/// One input per piece type, colour and square
+pub const INPUTS: usize = 768;
+
+// Hidden layer values are scaled by QA, the output weights and bias by QB
+const QA: i64 = 255;
+const QB: i64 = 64;
+// Network output to centipawns
+const SCALE: i64 = 400;
+
+const MAGIC: &[u8; 4] = b"RCNN";
+const VERSION: u32 = 1;
+
+/// A 768 -> N -> 1 network. Both sides' accumulators go through the hidden layer, the side to
+/// move's first, and the output layer sees all 2N clipped activations.
+///
+/// Files are little endian: `RCNN`, the format version and N as u32, then as i16 the feature
+/// weights (N per input), the N hidden biases, the 2N output weights and the output bias.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Network {
+    hidden: usize,
+    feature_weights: Vec<i16>,
+    feature_bias: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i16,
+}
+
+impl Network {
+    pub fn new(
+        hidden: usize,
+        feature_weights: Vec<i16>,
+        feature_bias: Vec<i16>,
+        output_weights: Vec<i16>,
+        output_bias: i16,
+    ) -> Self {
+        assert_eq!(feature_weights.len(), INPUTS * hidden, "Expected {} feature weights", INPUTS * hidden);
+        assert_eq!(feature_bias.len(), hidden, "Expected {} hidden biases", hidden);
+        assert_eq!(output_weights.len(), 2 * hidden, "Expected {} output weights", 2 * hidden);
+        Self { hidden, feature_weights, feature_bias, output_weights, output_bias }
+    }
+
+    pub fn hidden_size(&self) -> usize {
+        self.hidden
+    }
+
+    /// Input index of a piece seen from `perspective`, which always looks up the board from its
+    /// own back rank with its own pieces first
+    pub fn feature_index(perspective: Colour, piece: Piece, square: usize) -> usize {
+        let (side, square) = match perspective {
+            Colour::White => (piece.colour as usize, square),
+            Colour::Black => (piece.colour.other() as usize, square ^ 56),
+        };
+        side * 384 + piece.kind as usize * 64 + square
+    }
+
+    fn feature_weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden]
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        if bytes.len() < 12 || &bytes[..4] != MAGIC {
+            return Err(invalid("not a network file"));
+        }
+        let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        if version != VERSION {
+            return Err(invalid(&format!("unsupported network version {}", version)));
+        }
+        let hidden = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
+
+        let values: Vec<i16> = bytes[12..].chunks(2).map(|pair| i16::from_le_bytes([pair[0], *pair.get(1).unwrap_or(&0)])).collect();
+        let expected = INPUTS * hidden + hidden + 2 * hidden + 1;
+        if !bytes.len().is_multiple_of(2) || values.len() != expected {
+            return Err(invalid(&format!("expected {} weights for {} hidden neurons", expected, hidden)));
+        }
+
+        let (feature_weights, rest) = values.split_at(INPUTS * hidden);
+        let (feature_bias, rest) = rest.split_at(hidden);
+        let (output_weights, rest) = rest.split_at(2 * hidden);
+        Ok(Self::new(hidden, feature_weights.to_vec(), feature_bias.to_vec(), output_weights.to_vec(), rest[0]))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(12 + 2 * (INPUTS + 3) * self.hidden + 2);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.hidden as u32).to_le_bytes());
+
+        let values = self.feature_weights.iter().chain(&self.feature_bias).chain(&self.output_weights).chain([&self.output_bias]);
+        for value in values {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+}
+
+/// Hidden layer inputs for both perspectives, indexed by `Colour`, kept up to date as pieces
+/// are put down and picked up
+#[derive(Clone, Debug)]
+pub struct Accumulator {
+    network: Arc<Network>,
+    values: [Vec<i32>; 2],
+}
+
+impl Accumulator {
+    pub fn new(network: Arc<Network>, board: &Board) -> Self {
+        let bias: Vec<i32> = network.feature_bias.iter().map(|&bias| bias as i32).collect();
+        let mut accumulator = Self { network, values: [bias.clone(), bias] };
+
+        for (piece, coords) in board.get_all_pieces() {
+            accumulator.update(piece, coords.to_index(), 1);
+        }
+
+        accumulator
+    }
+
+    pub fn network(&self) -> &Arc<Network> {
+        &self.network
+    }
+
+    /// Add (`sign` 1) or remove (`sign` -1) a piece
+    pub fn update(&mut self, piece: Piece, square: usize, sign: i32) {
+        for perspective in [Colour::White, Colour::Black] {
+            let weights = self.network.feature_weights(Network::feature_index(perspective, piece, square));
+            for (value, &weight) in self.values[perspective as usize].iter_mut().zip(weights) {
+                *value += sign * weight as i32;
+            }
+        }
+    }
+
+    /// Evaluation in centipawns from `to_move`'s point of view
+    pub fn evaluate(&self, to_move: Colour) -> i32 {
+        let network = &self.network;
+        let (own, other) = (&self.values[to_move as usize], &self.values[to_move.other() as usize]);
+        let (own_weights, other_weights) = network.output_weights.split_at(network.hidden);
+
+        let activated = |value: i32| (value as i64).clamp(0, QA);
+        let mut sum: i64 = own.iter().zip(own_weights).map(|(&value, &weight)| activated(value) * weight as i64).sum();
+        sum += other.iter().zip(other_weights).map(|(&value, &weight)| activated(value) * weight as i64).sum::<i64>();
+
+        ((sum + network.output_bias as i64 * QA) * SCALE / (QA * QB)) as i32
+    }
+}
+
+impl PartialEq for Accumulator {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.network, &other.network) && self.values == other.values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use crate::enums::PieceType;
+    use crate::game_classes::game::Game;
+    use crate::moves::move_generator::MoveGenerator;
+
+    fn random_network(hidden: usize, seed: u64) -> Network {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut values = |count: usize, range: i16| (0..count).map(|_| rng.random_range(-range..=range)).collect::<Vec<i16>>();
+        let feature_weights = values(INPUTS * hidden, 40);
+        let feature_bias = values(hidden, 40);
+        let output_weights = values(2 * hidden, 64);
+        Network::new(hidden, feature_weights, feature_bias, output_weights, 5)
+    }
+
+    fn game_from_fen(fen: &str) -> Game {
+        let mut game = Game::new();
+        game.set_fenstr(fen);
+        game
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let network = random_network(8, 1);
+        let bytes = network.to_bytes();
+        assert_eq!(Network::from_bytes(&bytes).unwrap(), network);
+
+        assert!(Network::from_bytes(&bytes[..bytes.len() - 2]).is_err());
+        assert!(Network::from_bytes(b"nope").is_err());
+    }
+
+    #[test]
+    fn test_feature_index_mirrors_for_black() {
+        let white_pawn = Piece { kind: PieceType::Pawn, colour: Colour::White };
+        let black_pawn = Piece { kind: PieceType::Pawn, colour: Colour::Black };
+        // e2 for white looks the same as e7 for black
+        assert_eq!(Network::feature_index(Colour::White, white_pawn, 12), Network::feature_index(Colour::Black, black_pawn, 52));
+        assert_eq!(Network::feature_index(Colour::White, black_pawn, 12), 384 + 12);
+    }
+
+    #[test]
+    fn test_evaluation_is_colour_symmetric() {
+        let network = Arc::new(random_network(16, 2));
+        let mut white = game_from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 1");
+        let mut black = game_from_fen("rnbqk2r/pppp1ppp/5n2/2b1p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R b KQkq - 0 1");
+        white.set_network(Some(Arc::clone(&network)));
+        black.set_network(Some(network));
+        assert_eq!(white.nnue_evaluate(), black.nnue_evaluate());
+        assert!(white.nnue_evaluate().is_some());
+    }
+
+    #[test]
+    fn test_accumulator_kept_in_step() {
+        let network = Arc::new(random_network(8, 3));
+        // Captures, promotions with capture, en passant and castling are all available
+        let mut game = game_from_fen("r3k2r/1P6/8/3pP3/8/8/6p1/R3K2R w KQkq d6 0 1");
+        game.set_network(Some(Arc::clone(&network)));
+        let initial = game.nnue_evaluate();
+
+        let mut moves = Vec::new();
+        MoveGenerator::generate_legal_moves_into(&mut game, Colour::White, false, &mut moves);
+        for mv in moves {
+            game.make_compact_move(mv);
+            let incremental = game.get_accumulator().unwrap().clone();
+            assert!(incremental == Accumulator::new(Arc::clone(&network), game.get_board()), "After {}", mv);
+            game.undo_last_move();
+            assert_eq!(game.nnue_evaluate(), initial, "After undoing {}", mv);
+        }
+    }
+}
@@ -0,0 +1,381 @@
+use crate::engine::eval_params::EvalParams;
+use crate::enums::{Colour, PieceType};
+use crate::game_classes::board_classes::bit_board::{BitBoard, Squares};
+use crate::game_classes::board_classes::board::Board;
+use crate::game_classes::game::Game;
+use crate::piece::Piece;
+use crate::square::Square;
+
+// Default (middlegame, endgame) penalties and bonuses, in centipawns. The evaluator reads
+// them through `EvalParams`.
+pub const DOUBLED_PAWN: (i32, i32) = (-10, -20);
+pub const ISOLATED_PAWN: (i32, i32) = (-10, -15);
+pub const BACKWARD_PAWN: (i32, i32) = (-8, -10);
+pub const CONNECTED_PAWN: (i32, i32) = (8, 6);
+
+/// Passed pawn bonus by rank counted from the pawn's own side (index 1 is the starting rank)
+pub const PASSED_PAWN_MG: [i32; 8] = [0, 5, 10, 15, 25, 40, 60, 0];
+pub const PASSED_PAWN_EG: [i32; 8] = [0, 10, 15, 25, 45, 75, 120, 0];
+
+/// Endgame weights for the distance of each king to the square in front of a passed pawn
+pub const PASSED_ENEMY_KING_DISTANCE: i32 = 5;
+pub const PASSED_OWN_KING_DISTANCE: i32 = 2;
+
+/// Endgame bonus for a passed pawn the enemy king can't catch, once the enemy has only pawns left
+pub const UNSTOPPABLE_PAWN: i32 = 600;
+
+/// The pawn-only part of the evaluation, which depends on nothing but the pawns and so can be cached
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PawnEntry {
+    /// (middlegame, endgame) score of each side's pawns, indexed by `Colour`
+    pub scores: [(i32, i32); 2],
+    /// Passed pawns of each side, indexed by `Colour`
+    pub passed: [u64; 2],
+}
+
+impl PawnEntry {
+    /// Both sides' scores from white's point of view
+    pub fn total(&self) -> (i32, i32) {
+        let [white, black] = self.scores;
+        (white.0 - black.0, white.1 - black.1)
+    }
+}
+
+/// Number of slots in a default pawn hash table
+pub const PAWN_TABLE_SIZE: usize = 1 << 14;
+
+/// Pawn structure scores keyed by `Game::get_pawn_hash`, only valid for the `EvalParams` that filled it.
+/// Fixed size and indexed by the low bits of the key, a new entry replacing whatever shared its slot.
+pub struct PawnHashTable {
+    slots: Vec<Option<(u64, PawnEntry)>>,
+    mask: usize,
+}
+
+impl PawnHashTable {
+    /// Table with `size` slots, which must be a power of two
+    pub fn with_size(size: usize) -> Self {
+        assert!(size.is_power_of_two(), "Pawn table size must be a power of two");
+        Self { slots: vec![None; size], mask: size - 1 }
+    }
+
+    fn index(&self, key: u64) -> usize {
+        key as usize & self.mask
+    }
+
+    pub fn get(&self, key: u64) -> Option<PawnEntry> {
+        match self.slots[self.index(key)] {
+            Some((stored_key, entry)) if stored_key == key => Some(entry),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&mut self, key: u64, entry: PawnEntry) {
+        let index = self.index(key);
+        self.slots[index] = Some((key, entry));
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.fill(None);
+    }
+
+    /// Number of filled slots
+    pub fn len(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for PawnHashTable {
+    fn default() -> Self {
+        Self::with_size(PAWN_TABLE_SIZE)
+    }
+}
+
+pub(crate) fn file_mask(file: usize) -> u64 {
+    BitBoard::FILES[file].bits()
+}
+
+pub(crate) fn adjacent_files(file: usize) -> u64 {
+    let left = if file > 0 { file_mask(file - 1) } else { 0 };
+    let right = if file < 7 { file_mask(file + 1) } else { 0 };
+    left | right
+}
+
+/// Every rank strictly in front of `rank` from `colour`'s point of view
+pub(crate) fn forward_ranks(colour: Colour, rank: usize) -> u64 {
+    match colour {
+        Colour::White if rank == 7 => 0,
+        Colour::White => !0u64 << (8 * (rank + 1)),
+        Colour::Black => (1u64 << (8 * rank)) - 1,
+    }
+}
+
+/// Squares attacked by `colour`'s pawns on `pawns`
+pub fn pawn_attacks(colour: Colour, pawns: u64) -> u64 {
+    let pawns = BitBoard::from_bits(pawns);
+    let attacks = match colour {
+        Colour::White => pawns.north_west() | pawns.north_east(),
+        Colour::Black => pawns.south_west() | pawns.south_east(),
+    };
+    attacks.bits()
+}
+
+pub(crate) fn relative_rank(colour: Colour, square: usize) -> usize {
+    match colour {
+        Colour::White => square / 8,
+        Colour::Black => 7 - square / 8,
+    }
+}
+
+/// Square directly in front of a pawn
+fn front_square(colour: Colour, square: usize) -> usize {
+    match colour {
+        Colour::White => square + 8,
+        Colour::Black => square - 8,
+    }
+}
+
+pub(crate) fn distance(a: Square, b: Square) -> i32 {
+    a.distance(b) as i32
+}
+
+pub(crate) fn squares(bits: u64) -> Squares {
+    BitBoard::from_bits(bits).squares()
+}
+
+pub(crate) fn pawn_bits(board: &Board, colour: Colour) -> u64 {
+    board.get_piece_bit_board(Piece { kind: PieceType::Pawn, colour }).bits()
+}
+
+/// Doubled, isolated, backward, connected and passed pawns for both sides
+pub fn evaluate_pawns(board: &Board, params: &EvalParams) -> PawnEntry {
+    let mut entry = PawnEntry::default();
+
+    for colour in [Colour::White, Colour::Black] {
+        let own = pawn_bits(board, colour);
+        let enemy = pawn_bits(board, colour.other());
+        let enemy_attacks = pawn_attacks(colour.other(), enemy);
+        let (mut mg, mut eg) = (0, 0);
+
+        for square in squares(own) {
+            let file = square % 8;
+            let rank = square / 8;
+            let front = forward_ranks(colour, rank);
+            let neighbours = own & adjacent_files(file);
+
+            // Only the rear pawn of a doubled pair is penalised, so each extra pawn counts once
+            let doubled = own & file_mask(file) & front != 0;
+            if doubled {
+                mg += params.doubled_pawn[0];
+                eg += params.doubled_pawn[1];
+            }
+
+            if neighbours == 0 {
+                mg += params.isolated_pawn[0];
+                eg += params.isolated_pawn[1];
+            } else if neighbours & !front == 0
+                && enemy_attacks & (1u64 << front_square(colour, square)) != 0
+            {
+                // Every neighbour has already gone past it and it can't advance safely
+                mg += params.backward_pawn[0];
+                eg += params.backward_pawn[1];
+            }
+
+            let defended = pawn_attacks(colour.other(), 1u64 << square) & own != 0;
+            let phalanx = neighbours & BitBoard::RANKS[rank].bits() != 0;
+            if defended || phalanx {
+                mg += params.connected_pawn[0];
+                eg += params.connected_pawn[1];
+            }
+
+            let passed = !doubled && enemy & (file_mask(file) | adjacent_files(file)) & front == 0;
+            if passed {
+                let relative = relative_rank(colour, square);
+                mg += params.passed_pawn_mg[relative];
+                eg += params.passed_pawn_eg[relative];
+                entry.passed[colour as usize] |= 1u64 << square;
+            }
+        }
+
+        entry.scores[colour as usize] = (mg, eg);
+    }
+
+    entry
+}
+
+/// Pawn structure of the game, looked up in or added to `pawn_table` when one is given
+pub fn probe_pawns(game: &Game, pawn_table: Option<&mut PawnHashTable>) -> PawnEntry {
+    let Some(table) = pawn_table else {
+        return evaluate_pawns(game.get_board(), game.get_eval_params());
+    };
+    let key = game.get_pawn_hash();
+    table.get(key).unwrap_or_else(|| {
+        let entry = evaluate_pawns(game.get_board(), game.get_eval_params());
+        table.insert(key, entry);
+        entry
+    })
+}
+
+/// Endgame passed pawn terms that depend on the kings and the side to move, so can't be cached.
+/// Returned for each side, indexed by `Colour`.
+pub fn evaluate_passed_pawn_endgame(game: &Game, entry: &PawnEntry) -> [i32; 2] {
+    let board = game.get_board();
+    let all_occ = board.all_occ().bits();
+    let to_move = game.get_game_state().get_turn();
+    let params = game.get_eval_params();
+    let mut scores = [0; 2];
+
+    for colour in [Colour::White, Colour::Black] {
+        let eg = &mut scores[colour as usize];
+        // Atomic and Antichess kings can be lost, which leaves nothing to measure from
+        let own_king = king_square(board, colour);
+        let enemy_king = king_square(board, colour.other());
+
+        // Only kings and pawns left for the defender, so nothing but its king can stop a passer
+        let enemy_kings = board.get_piece_bit_board(Piece { kind: PieceType::King, colour: colour.other() }).bits();
+        let enemy_pieces = board.get_colour_occ(colour.other()).bits() & !pawn_bits(board, colour.other()) & !enemy_kings;
+        let mut unstoppable = false;
+
+        for square in squares(entry.passed[colour as usize]) {
+            let relative = relative_rank(colour, square) as i32;
+            let block = Square::new(front_square(colour, square) as u8);
+
+            // The further up the board, the more the kings' distances matter
+            let weight = (relative - 2).max(0);
+            if let Some(enemy_king) = enemy_king {
+                *eg += weight * distance(enemy_king, block) * params.passed_enemy_king_distance;
+            }
+            if let Some(own_king) = own_king {
+                *eg -= weight * distance(own_king, block) * params.passed_own_king_distance;
+            }
+
+            let Some(enemy_king) = enemy_king else {
+                continue;
+            };
+            if enemy_pieces == 0 && !unstoppable {
+                let path = file_mask(square % 8) & forward_ranks(colour, square / 8);
+                let promotion = Square::new(match colour {
+                    Colour::White => square % 8 + 56,
+                    Colour::Black => square % 8,
+                } as u8);
+
+                // Rule of the square, with the double step from the starting rank
+                let pawn_moves = if relative == 1 { 5 } else { 7 - relative };
+                let tempo = if to_move == colour.other() { 1 } else { 0 };
+                if all_occ & path == 0 && distance(enemy_king, promotion) - tempo > pawn_moves {
+                    unstoppable = true;
+                    *eg += params.unstoppable_pawn;
+                }
+            }
+        }
+    }
+
+    scores
+}
+
+/// Square of `colour`'s king, if it still has one
+pub(crate) fn king_square(board: &Board, colour: Colour) -> Option<Square> {
+    board.get_piece_bit_board(Piece { kind: PieceType::King, colour }).lsb().map(Square::from_index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game_from_fen(fen: &str) -> Game {
+        let mut game = Game::new();
+        game.set_fenstr(fen);
+        game
+    }
+
+    fn pawns(fen: &str) -> PawnEntry {
+        evaluate_pawns(game_from_fen(fen).get_board(), &EvalParams::default())
+    }
+
+    #[test]
+    fn test_symmetric_structure_is_even() {
+        let entry = pawns("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1");
+        assert_eq!((entry.total(), entry.passed), ((0, 0), [0, 0]));
+        let entry = pawns("4k3/pp3ppp/2p5/3p4/3P4/2P5/PP3PPP/4K3 w - - 0 1");
+        assert_eq!(entry.total(), (0, 0));
+    }
+
+    #[test]
+    fn test_doubled_and_isolated() {
+        // White's doubled, isolated c pawns against black's healthy pair
+        let entry = pawns("4k3/8/8/8/1pp5/2P5/2P5/4K3 w - - 0 1");
+        let expected_mg = 2 * ISOLATED_PAWN.0 + DOUBLED_PAWN.0 - 2 * CONNECTED_PAWN.0;
+        assert_eq!(entry.total().0, expected_mg);
+        assert_eq!(entry.passed, [0, 0]);
+    }
+
+    #[test]
+    fn test_backward_pawn() {
+        // d3 has no pawn beside or behind it and e5 guards d4
+        let backward = pawns("4k3/8/8/4p3/2P5/3P4/8/4K3 w - - 0 1");
+        let free = pawns("4k3/8/4p3/8/2P5/3P4/8/4K3 w - - 0 1");
+        assert_eq!(backward.scores[0], (free.scores[0].0 + BACKWARD_PAWN.0, free.scores[0].1 + BACKWARD_PAWN.1));
+    }
+
+    #[test]
+    fn test_passed_pawns() {
+        let entry = pawns("4k3/8/1P6/8/8/8/p7/4K3 w - - 0 1");
+        assert_eq!(entry.passed[Colour::White as usize], 1u64 << 41);
+        assert_eq!(entry.passed[Colour::Black as usize], 1u64 << 8);
+
+        // Further advanced passers are worth more
+        assert_eq!(entry.total().1, PASSED_PAWN_EG[5] - PASSED_PAWN_EG[6]);
+
+        // A pawn on the next file blocks it
+        assert_eq!(pawns("4k3/2p5/1P6/8/8/8/8/4K3 w - - 0 1").passed, [0, 0]);
+    }
+
+    #[test]
+    fn test_unstoppable_pawn() {
+        // The king is outside the square of the pawn
+        let game = game_from_fen("8/4k3/8/P7/8/8/8/4K3 w - - 0 1");
+        let entry = evaluate_pawns(game.get_board(), &EvalParams::default());
+        assert!(evaluate_passed_pawn_endgame(&game, &entry)[0] >= UNSTOPPABLE_PAWN);
+
+        // With black to move the king steps into the square
+        let game = game_from_fen("8/4k3/8/P7/8/8/8/4K3 b - - 0 1");
+        assert!(evaluate_passed_pawn_endgame(&game, &entry)[0] < UNSTOPPABLE_PAWN);
+
+        // A defending rook can stop it anyway
+        let game = game_from_fen("7r/4k3/8/P7/8/8/8/4K3 w - - 0 1");
+        assert!(evaluate_passed_pawn_endgame(&game, &entry)[0] < UNSTOPPABLE_PAWN);
+    }
+
+    #[test]
+    fn test_pawn_table_caches_by_pawn_hash() {
+        let mut table = PawnHashTable::default();
+        let game = game_from_fen("4k3/8/1P6/8/8/8/p7/4K3 w - - 0 1");
+        let entry = probe_pawns(&game, Some(&mut table));
+        assert_eq!(table.len(), 1);
+
+        // Same pawns, different pieces: served from the same entry
+        let game = game_from_fen("3k4/8/1P6/8/8/8/p7/3K4 w - - 0 1");
+        assert_eq!(probe_pawns(&game, Some(&mut table)), entry);
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn test_pawn_table_has_fixed_size() {
+        let mut table = PawnHashTable::with_size(4);
+        let entry = PawnEntry { scores: [(1, 2), (3, 4)], passed: [0, 0] };
+        for key in 0..100 {
+            table.insert(key, entry);
+        }
+        assert_eq!(table.len(), 4);
+
+        // Keys sharing a slot replace each other
+        assert_eq!(table.get(99), Some(entry));
+        assert_eq!(table.get(95), None);
+
+        table.clear();
+        assert!(table.is_empty());
+    }
+}
@@ -0,0 +1,319 @@
+use crate::engine::eval_params::EvalParams;
+use crate::engine::pawn_structure::{
+    adjacent_files, file_mask, forward_ranks, pawn_attacks, pawn_bits, relative_rank, squares,
+};
+use crate::enums::{Colour, PieceType};
+use crate::game_classes::board_classes::bit_board::BitBoard;
+use crate::game_classes::board_classes::board::Board;
+use crate::game_classes::board_classes::magic_bitboard::MAGIC_TABLES;
+use crate::game_classes::board_classes::piece_attacks::{KING_ATTACKS, KNIGHT_ATTACKS};
+use crate::piece::Piece;
+
+// Default (middlegame, endgame) weights, in centipawns. The evaluator reads them through `EvalParams`.
+
+/// Per square a piece can move to outside its baseline, indexed by `PieceType`
+pub const MOBILITY: [(i32, i32); 6] = [(0, 0), (4, 4), (5, 5), (2, 4), (1, 2), (0, 0)];
+/// Typical number of moves, so a piece with fewer is penalised rather than everything scoring positive
+pub const MOBILITY_BASELINE: [i32; 6] = [0, 4, 6, 7, 13, 0];
+
+/// How much each attacker on the king zone counts, per attacked zone square, indexed by `PieceType`
+pub const KING_ATTACK_WEIGHT: [i32; 6] = [0, 2, 2, 3, 5, 0];
+/// Percentage of the attack weight that counts, by number of attackers: a lone attacker isn't a threat
+pub const KING_ATTACKER_SCALE: [i32; 8] = [0, 0, 50, 75, 88, 94, 97, 99];
+pub const KING_ATTACK_UNIT: (i32, i32) = (-6, 0);
+/// Own pawns one and two ranks in front of the king, on its file and the two next to it
+pub const PAWN_SHIELD_CLOSE: (i32, i32) = (12, 0);
+pub const PAWN_SHIELD_FAR: (i32, i32) = (6, 0);
+/// Files on or next to the king without own pawns, or without any pawns
+pub const KING_SEMI_OPEN_FILE: (i32, i32) = (-15, 0);
+pub const KING_OPEN_FILE: (i32, i32) = (-25, 0);
+
+pub const BISHOP_PAIR: (i32, i32) = (30, 50);
+
+pub const ROOK_OPEN_FILE: (i32, i32) = (25, 10);
+pub const ROOK_SEMI_OPEN_FILE: (i32, i32) = (12, 8);
+/// On the 7th with the enemy king behind it or enemy pawns to pick off
+pub const ROOK_ON_SEVENTH: (i32, i32) = (20, 30);
+
+/// Knight on the 4th to 6th rank, defended by a pawn and out of reach of enemy pawns
+pub const KNIGHT_OUTPOST: (i32, i32) = (20, 10);
+
+/// Bishop taking the a7/h7 pawn and shut in by the b6/g6 pawn
+pub const TRAPPED_BISHOP: (i32, i32) = (-80, -80);
+/// Rook boxed in on the back rank by its own uncastled king
+pub const TRAPPED_ROOK: (i32, i32) = (-40, -10);
+
+/// Each term for each side, indexed by `Colour`, from that side's point of view
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PositionalTerms {
+    pub mobility: [(i32, i32); 2],
+    pub king_safety: [(i32, i32); 2],
+    pub bishop_pair: [(i32, i32); 2],
+    pub rooks: [(i32, i32); 2],
+    pub outposts: [(i32, i32); 2],
+    pub trapped_pieces: [(i32, i32); 2],
+}
+
+impl PositionalTerms {
+    /// Every term summed, from white's point of view
+    pub fn total(&self) -> (i32, i32) {
+        let terms = [self.mobility, self.king_safety, self.bishop_pair, self.rooks, self.outposts, self.trapped_pieces];
+        terms.iter().fold((0, 0), |(mg, eg), [white, black]| {
+            (mg + white.0 - black.0, eg + white.1 - black.1)
+        })
+    }
+}
+
+fn add(score: &mut (i32, i32), weight: [i32; 2], count: i32) {
+    score.0 += weight[0] * count;
+    score.1 += weight[1] * count;
+}
+
+fn piece_bits(board: &Board, kind: PieceType, colour: Colour) -> u64 {
+    board.get_piece_bit_board(Piece { kind, colour }).bits()
+}
+
+/// Squares a piece attacks, from the knight and king tables or the magic tables for sliders
+fn attacks(kind: PieceType, square: usize, occ: &BitBoard) -> u64 {
+    match kind {
+        PieceType::Knight => KNIGHT_ATTACKS[square],
+        PieceType::Bishop => MAGIC_TABLES.get_bishop_attacks(square, occ).bits(),
+        PieceType::Rook => MAGIC_TABLES.get_rook_attacks(square, occ).bits(),
+        PieceType::Queen => {
+            MAGIC_TABLES.get_bishop_attacks(square, occ).bits() | MAGIC_TABLES.get_rook_attacks(square, occ).bits()
+        }
+        PieceType::Pawn | PieceType::King => 0,
+    }
+}
+
+/// Mirror a square from white's side of the board to `colour`'s
+fn relative_square(colour: Colour, square: usize) -> usize {
+    match colour {
+        Colour::White => square,
+        Colour::Black => square ^ 56,
+    }
+}
+
+pub fn evaluate_positional(board: &Board, params: &EvalParams) -> PositionalTerms {
+    let mut terms = PositionalTerms::default();
+    let occ = board.all_occ();
+
+    for colour in [Colour::White, Colour::Black] {
+        let side = colour as usize;
+        let own_pawns = pawn_bits(board, colour);
+        let enemy_pawns = pawn_bits(board, colour.other());
+        let all_pawns = own_pawns | enemy_pawns;
+        let enemy_king = piece_bits(board, PieceType::King, colour.other());
+
+        // Squares attacked by enemy pawns or taken by own pieces don't count as mobility
+        let mobility_area = !board.get_colour_occ(colour).bits() & !pawn_attacks(colour.other(), enemy_pawns);
+
+        for kind in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+            for square in squares(piece_bits(board, kind, colour)) {
+                let moves = (attacks(kind, square, &occ) & mobility_area).count_ones() as i32;
+                add(&mut terms.mobility[side], params.mobility[kind as usize], moves - params.mobility_baseline[kind as usize]);
+            }
+        }
+
+        terms.king_safety[side] = king_safety(board, colour, &occ, params);
+
+        if piece_bits(board, PieceType::Bishop, colour).count_ones() >= 2 {
+            add(&mut terms.bishop_pair[side], params.bishop_pair, 1);
+        }
+
+        for square in squares(piece_bits(board, PieceType::Rook, colour)) {
+            let file = file_mask(square % 8);
+            if all_pawns & file == 0 {
+                add(&mut terms.rooks[side], params.rook_open_file, 1);
+            } else if own_pawns & file == 0 {
+                add(&mut terms.rooks[side], params.rook_semi_open_file, 1);
+            }
+
+            if relative_rank(colour, square) == 6 {
+                let seventh = 0xFFu64 << (square / 8 * 8);
+                let back_rank = match colour {
+                    Colour::White => 0xFFu64 << 56,
+                    Colour::Black => 0xFF,
+                };
+                if enemy_king & back_rank != 0 || enemy_pawns & seventh != 0 {
+                    add(&mut terms.rooks[side], params.rook_on_seventh, 1);
+                }
+            }
+        }
+
+        for square in squares(piece_bits(board, PieceType::Knight, colour)) {
+            let defended = pawn_attacks(colour.other(), 1u64 << square) & own_pawns != 0;
+            let attackable = enemy_pawns & adjacent_files(square % 8) & forward_ranks(colour, square / 8) != 0;
+            if (3..=5).contains(&relative_rank(colour, square)) && defended && !attackable {
+                add(&mut terms.outposts[side], params.knight_outpost, 1);
+            }
+        }
+
+        terms.trapped_pieces[side] = trapped_pieces(board, colour, params);
+    }
+
+    terms
+}
+
+/// Attacks on the squares around the king, plus the pawns and open files in front of it
+fn king_safety(board: &Board, colour: Colour, occ: &BitBoard, params: &EvalParams) -> (i32, i32) {
+    let mut score = (0, 0);
+    let king = piece_bits(board, PieceType::King, colour);
+    if king == 0 {
+        return score;
+    }
+    let king_square = king.trailing_zeros() as usize;
+    let zone = KING_ATTACKS[king_square] | king;
+
+    let mut attackers = 0;
+    let mut attack_weight = 0;
+    for kind in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+        for square in squares(piece_bits(board, kind, colour.other())) {
+            let zone_attacks = (attacks(kind, square, occ) & zone).count_ones() as i32;
+            if zone_attacks > 0 {
+                attackers += 1;
+                attack_weight += params.king_attack_weight[kind as usize] * zone_attacks;
+            }
+        }
+    }
+    let scale = params.king_attacker_scale[attackers.min(7)];
+    add(&mut score, params.king_attack_unit, attack_weight * scale / 100);
+
+    let own_pawns = pawn_bits(board, colour);
+    let all_pawns = own_pawns | pawn_bits(board, colour.other());
+    let king_file = king_square % 8;
+    let files = file_mask(king_file) | adjacent_files(king_file);
+
+    // The shield only matters while the king is still sheltering on its back ranks
+    if relative_rank(colour, king_square) <= 1 {
+        let rank_ahead = |ranks: usize| match colour {
+            Colour::White => 0xFFu64.checked_shl(8 * (king_square / 8 + ranks) as u32).unwrap_or(0),
+            Colour::Black => (0xFFu64 << 56).checked_shr(8 * (7 - king_square / 8 + ranks) as u32).unwrap_or(0),
+        };
+        add(&mut score, params.pawn_shield_close, (own_pawns & files & rank_ahead(1)).count_ones() as i32);
+        add(&mut score, params.pawn_shield_far, (own_pawns & files & rank_ahead(2)).count_ones() as i32);
+    }
+
+    for file in (king_file.saturating_sub(1)..=(king_file + 1).min(7)).map(file_mask) {
+        if all_pawns & file == 0 {
+            add(&mut score, params.king_open_file, 1);
+        } else if own_pawns & file == 0 {
+            add(&mut score, params.king_semi_open_file, 1);
+        }
+    }
+
+    score
+}
+
+fn trapped_pieces(board: &Board, colour: Colour, params: &EvalParams) -> (i32, i32) {
+    let mut score = (0, 0);
+    let bishops = piece_bits(board, PieceType::Bishop, colour);
+    let enemy_pawns = pawn_bits(board, colour.other());
+
+    // a7 shut in by b6, h7 by g6, mirrored for black
+    for (bishop_square, pawn_square) in [(48, 41), (55, 46)] {
+        let bishop_square = relative_square(colour, bishop_square);
+        let pawn_square = relative_square(colour, pawn_square);
+        if bishops & (1u64 << bishop_square) != 0 && enemy_pawns & (1u64 << pawn_square) != 0 {
+            add(&mut score, params.trapped_bishop, 1);
+        }
+    }
+
+    let king = piece_bits(board, PieceType::King, colour);
+    if king == 0 {
+        return score;
+    }
+    let king_square = king.trailing_zeros() as usize;
+    if relative_rank(colour, king_square) == 0 {
+        let king_file = king_square % 8;
+        for square in squares(piece_bits(board, PieceType::Rook, colour)) {
+            let rook_file = square % 8;
+            let boxed_in = match king_file {
+                5 | 6 => rook_file > king_file,
+                1 | 2 => rook_file < king_file,
+                _ => false,
+            };
+            if relative_rank(colour, square) == 0 && boxed_in {
+                add(&mut score, params.trapped_rook, 1);
+            }
+        }
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_classes::game::Game;
+
+    fn terms(fen: &str) -> PositionalTerms {
+        let mut game = Game::new();
+        game.set_fenstr(fen);
+        evaluate_positional(game.get_board(), &EvalParams::default())
+    }
+
+    #[test]
+    fn test_start_position_is_even() {
+        let start = terms("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(start.total(), (0, 0));
+        assert_eq!(start.bishop_pair, [BISHOP_PAIR, BISHOP_PAIR]);
+    }
+
+    #[test]
+    fn test_mobility() {
+        // A centralised knight has more squares than one in the corner
+        let central = terms("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1");
+        let corner = terms("4k3/8/8/8/8/8/8/N3K3 w - - 0 1");
+        assert_eq!(central.mobility[0].0, MOBILITY[1].0 * (8 - MOBILITY_BASELINE[1]));
+        assert_eq!(corner.mobility[0].0, MOBILITY[1].0 * (2 - MOBILITY_BASELINE[1]));
+
+        // Squares covered by enemy pawns don't count
+        let covered = terms("4k3/3p4/8/8/3N4/8/8/4K3 w - - 0 1");
+        assert_eq!(covered.mobility[0].0, MOBILITY[1].0 * (6 - MOBILITY_BASELINE[1]));
+    }
+
+    #[test]
+    fn test_king_safety() {
+        let sheltered = terms("4k3/8/8/8/8/8/5PPP/6K1 w - - 0 1");
+        let exposed = terms("4k3/8/8/8/8/5PPP/8/6K1 w - - 0 1");
+        let open = terms("4k3/8/8/8/8/8/5P1P/6K1 w - - 0 1");
+        assert!(sheltered.king_safety[0].0 > exposed.king_safety[0].0);
+        assert!(sheltered.king_safety[0].0 > open.king_safety[0].0);
+
+        // One attacker alone isn't scored, a second one is
+        let one_attacker = terms("4k3/8/8/8/8/8/5PPP/3q2K1 w - - 0 1");
+        let two_attackers = terms("4k3/8/8/8/8/7n/5PPP/3q2K1 w - - 0 1");
+        assert_eq!(one_attacker.king_safety[0], sheltered.king_safety[0]);
+        assert!(two_attackers.king_safety[0].0 < one_attacker.king_safety[0].0);
+    }
+
+    #[test]
+    fn test_rooks_and_outposts() {
+        let rooks = terms("4k3/pp6/8/8/8/8/1P6/R3K2R w - - 0 1");
+        assert_eq!(rooks.rooks[0], (ROOK_SEMI_OPEN_FILE.0 + ROOK_OPEN_FILE.0, ROOK_SEMI_OPEN_FILE.1 + ROOK_OPEN_FILE.1));
+
+        let seventh = terms("4k3/R7/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(seventh.rooks[0], (ROOK_OPEN_FILE.0 + ROOK_ON_SEVENTH.0, ROOK_OPEN_FILE.1 + ROOK_ON_SEVENTH.1));
+
+        // d5 knight backed by e4 with no black c or e pawn left to chase it
+        let outpost = terms("4k3/8/8/3N4/4P3/8/8/4K3 w - - 0 1");
+        assert_eq!(outpost.outposts[0], KNIGHT_OUTPOST);
+        let chased = terms("4k3/2p5/8/3N4/4P3/8/8/4K3 w - - 0 1");
+        assert_eq!(chased.outposts[0], (0, 0));
+    }
+
+    #[test]
+    fn test_trapped_pieces() {
+        let bishop = terms("4k3/B7/1p6/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(bishop.trapped_pieces[0], TRAPPED_BISHOP);
+
+        let black_bishop = terms("4k3/8/8/8/8/6P1/7b/4K3 w - - 0 1");
+        assert_eq!(black_bishop.trapped_pieces[1], TRAPPED_BISHOP);
+
+        let rook = terms("4k3/8/8/8/8/8/8/5K1R w - - 0 1");
+        assert_eq!(rook.trapped_pieces[0], TRAPPED_ROOK);
+        let castled = terms("4k3/8/8/8/8/8/8/5RK1 w - - 0 1");
+        assert_eq!(castled.trapped_pieces[0], (0, 0));
+    }
+}
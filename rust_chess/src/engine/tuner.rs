@@ -0,0 +1,251 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::engine::eval_params::EvalParams;
+use crate::engine::evaluator::Evaluator;
+use crate::engine::minimax::Minimax;
+use crate::enums::Colour;
+use crate::game_classes::game::Game;
+
+/// A position to tune on, already played out to a quiet one, and the result of the game it came
+/// from: 1.0 when white won, 0.5 for a draw and 0.0 when black won
+pub struct TuningPosition {
+    pub game: Game,
+    pub result: f64,
+}
+
+/// Split a line into its FEN and game result. Accepts EPD style results (`c9 "1-0";`) as well as
+/// a plain or bracketed result after the FEN (`1/2-1/2`, `[0.5]`).
+pub fn parse_position_line(line: &str) -> Result<(String, f64), String> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() < 4 {
+        return Err("expected a FEN followed by a result".to_string());
+    }
+
+    // EPD drops the move counters, FEN has them
+    let mut fen_len = 4;
+    while fen_len < tokens.len().min(6) && tokens[fen_len].parse::<u32>().is_ok() {
+        fen_len += 1;
+    }
+    let fen = tokens[..fen_len].join(" ");
+
+    for token in &tokens[fen_len..] {
+        let token = token.trim_matches(|c| matches!(c, '"' | '[' | ']' | ';' | '(' | ')'));
+        let result = match token {
+            "1-0" => Some(1.0),
+            "0-1" => Some(0.0),
+            "1/2-1/2" | "1/2" => Some(0.5),
+            _ => token.parse::<f64>().ok().filter(|result| (0.0..=1.0).contains(result)),
+        };
+        if let Some(result) = result {
+            return Ok((fen, result));
+        }
+    }
+
+    Err("no game result found".to_string())
+}
+
+/// Read positions from `text`, one per line, and play each through `engine`'s quiescence search.
+/// Blank lines and `#` comments are skipped.
+pub fn positions_from_text(text: &str, engine: &mut Minimax) -> io::Result<Vec<TuningPosition>> {
+    let mut positions = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (fen, result) = parse_position_line(line)
+            .map_err(|message| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", index + 1, message)))?;
+
+        let mut game = Game::new();
+        game.set_fenstr(&fen);
+        engine.play_quiescence_line(&mut game);
+        positions.push(TuningPosition { game, result });
+    }
+
+    Ok(positions)
+}
+
+pub fn load_positions<P: AsRef<Path>>(path: P, engine: &mut Minimax) -> io::Result<Vec<TuningPosition>> {
+    positions_from_text(&fs::read_to_string(path)?, engine)
+}
+
+/// Expected score for white given an evaluation in centipawns
+pub fn sigmoid(score: i32, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * score as f64 / 400.0))
+}
+
+/// Static evaluation from white's point of view
+pub fn white_score(game: &mut Game) -> i32 {
+    let score = Evaluator::evaluate_game_result(game, None, 0, Colour::White);
+    if game.get_game_state().get_turn() == Colour::White { score } else { -score }
+}
+
+/// Mean squared difference between game results and the results predicted from white's scores,
+/// given as (score, result) pairs
+pub fn scored_error(scored: &[(i32, f64)], k: f64) -> f64 {
+    if scored.is_empty() {
+        return 0.0;
+    }
+
+    let total: f64 = scored.iter().map(|&(score, result)| (result - sigmoid(score, k)).powi(2)).sum();
+    total / scored.len() as f64
+}
+
+/// The sigmoid scaling that best fits the scores to the results, found by narrowing in on the
+/// best of evenly spaced values
+pub fn best_k(scored: &[(i32, f64)]) -> f64 {
+    let (mut low, mut high) = (0.0, 4.0);
+    let mut best = 1.0;
+
+    for _ in 0..4 {
+        let step = (high - low) / 10.0;
+        let mut best_error = f64::MAX;
+        for i in 0..=10 {
+            let k = low + step * i as f64;
+            let error = scored_error(scored, k);
+            if error < best_error {
+                best_error = error;
+                best = k;
+            }
+        }
+        low = f64::max(best - step, 0.0);
+        high = best + step;
+    }
+
+    best
+}
+
+fn score_positions(positions: &mut [TuningPosition], params: &Arc<EvalParams>) -> Vec<(i32, f64)> {
+    positions
+        .iter_mut()
+        .map(|position| {
+            position.game.set_eval_params(Arc::clone(params));
+            (white_score(&mut position.game), position.result)
+        })
+        .collect()
+}
+
+/// Mean squared difference between the game results and the results predicted from evaluating
+/// with `params`
+pub fn mean_squared_error(positions: &mut [TuningPosition], params: &Arc<EvalParams>, k: f64) -> f64 {
+    scored_error(&score_positions(positions, params), k)
+}
+
+/// The sigmoid scaling that best fits the results with `params` as they are
+pub fn find_k(positions: &mut [TuningPosition], params: &Arc<EvalParams>) -> f64 {
+    best_k(&score_positions(positions, params))
+}
+
+/// Texel's local search: nudge every value up or down by one, keeping whatever lowers the error,
+/// until a whole pass changes nothing or `max_passes` are done. `on_pass` is called after each
+/// pass with its number, the error and the parameters so far.
+pub fn local_search<F>(
+    positions: &mut [TuningPosition],
+    params: EvalParams,
+    k: f64,
+    max_passes: usize,
+    mut on_pass: F,
+) -> EvalParams
+where
+    F: FnMut(usize, f64, &EvalParams),
+{
+    let mut best = Arc::new(params);
+    let mut best_error = mean_squared_error(positions, &best, k);
+    let mut layout: Vec<usize> = Vec::new();
+    for (_, values) in best.as_ref().clone().fields_mut() {
+        layout.push(values.len());
+    }
+
+    for pass in 1..=max_passes {
+        let mut improved = false;
+
+        for (field, &len) in layout.iter().enumerate() {
+            for index in 0..len {
+                for delta in [1, -1] {
+                    let mut trial = best.as_ref().clone();
+                    trial.fields_mut()[field].1[index] += delta;
+                    let trial = Arc::new(trial);
+
+                    let error = mean_squared_error(positions, &trial, k);
+                    if error < best_error {
+                        best_error = error;
+                        best = trial;
+                        improved = true;
+                        break;
+                    }
+                }
+            }
+        }
+
+        on_pass(pass, best_error, &best);
+        if !improved {
+            break;
+        }
+    }
+
+    Arc::try_unwrap(best).unwrap_or_else(|shared| shared.as_ref().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_position_line() {
+        let epd = parse_position_line("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - c9 \"1/2-1/2\";").unwrap();
+        assert_eq!(epd, ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq -".to_string(), 0.5));
+
+        let fen = parse_position_line("4k3/8/8/8/8/8/4P3/4K3 w - - 0 40 [1.0]").unwrap();
+        assert_eq!(fen, ("4k3/8/8/8/8/8/4P3/4K3 w - - 0 40".to_string(), 1.0));
+
+        assert_eq!(parse_position_line("4k3/8/8/8/8/8/4P3/4K3 w - - 0-1").unwrap().1, 0.0);
+        assert!(parse_position_line("4k3/8/8/8/8/8/4P3/4K3 w - -").is_err());
+        assert!(parse_position_line("4k3/8/8/8/8/8/4P3/4K3 w - - [2.0]").is_err());
+    }
+
+    #[test]
+    fn test_sigmoid() {
+        assert_eq!(sigmoid(0, 1.0), 0.5);
+        assert!(sigmoid(400, 1.0) > 0.9 && sigmoid(-400, 1.0) < 0.1);
+        assert!((sigmoid(150, 1.3) + sigmoid(-150, 1.3) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_positions_are_played_out_quietly() {
+        let mut engine = Minimax::new(1, 4, false, true);
+        let text = "# white to win the hanging queen\n4k3/8/8/3q4/8/2N5/8/4K3 w - - 0 1 [1.0]\n\n";
+        let positions = positions_from_text(text, &mut engine).unwrap();
+        assert_eq!(positions.len(), 1);
+        assert!(positions[0].game.get_last_move().is_some());
+
+        assert!(positions_from_text("not a position", &mut engine).is_err());
+    }
+
+    #[test]
+    fn test_local_search_lowers_error() {
+        let mut engine = Minimax::new(1, 2, false, true);
+        // Extra knights win, extra bishops are drawn, so their values should move apart
+        let text = "\
+            4k3/pppppppp/8/8/8/8/PPPPPPPP/1N2K1N1 w - - 0 1 [1.0]\n\
+            1n2k1n1/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1 [0.0]\n\
+            4k3/pppppppp/8/8/8/8/PPPPPPPP/2B1KB2 w - - 0 1 [0.5]\n\
+            2b1kb2/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1 [0.5]\n";
+        let mut positions = positions_from_text(text, &mut engine).unwrap();
+
+        let params = EvalParams::default();
+        let k = 1.0;
+        let initial_error = mean_squared_error(&mut positions, &Arc::new(params.clone()), k);
+
+        let mut passes = 0;
+        let tuned = local_search(&mut positions, params.clone(), k, 1, |pass, _, _| passes = pass);
+        assert_eq!(passes, 1);
+        assert!(mean_squared_error(&mut positions, &Arc::new(tuned.clone()), k) < initial_error);
+        assert!(tuned.piece_values[1] > params.piece_values[1]);
+        assert!(tuned.piece_values[2] < params.piece_values[2]);
+    }
+}
@@ -38,12 +38,21 @@ pub struct EnPassantMove {
     pub captured_coords: Coords,
 }
 
+// A piece put onto the board from the pocket in Crazyhouse
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DropMove {
+    pub colour: Colour,
+    pub piece_type: PieceType,
+    pub to: Coords,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ChessMove {
     Normal(NormalMove),
     Castling(CastlingMove),
     Promotion(PromotionMove),
     EnPassant(EnPassantMove),
+    Drop(DropMove),
 }
 
 pub enum ExecutedMove {
@@ -60,8 +69,15 @@ pub enum ExecutedMove {
     },
     EnPassant {
         mv: EnPassantMove
+    },
+    Drop {
+        mv: DropMove
+    },
+    // A capture in a variant where captures explode, with every piece the blast removed
+    Explosion {
+        capture: Box<ExecutedMove>,
+        exploded: Vec<(Piece, Coords)>
     }
-
 }
 
 impl ChessMove {
@@ -71,14 +87,17 @@ impl ChessMove {
             ChessMove::Castling(mv) => mv.colour,
             ChessMove::Promotion(mv) => mv.colour,
             ChessMove::EnPassant(mv) => mv.colour,
+            ChessMove::Drop(mv) => mv.colour,
         }
     }
+    /// Square the piece leaves, for a drop the square it's dropped on
     pub fn from(&self) -> Coords {
         match self {
             ChessMove::Normal(mv) => mv.from,
             ChessMove::Castling(mv) => mv.king_from,
             ChessMove::Promotion(mv) => mv.from,
             ChessMove::EnPassant(mv) => mv.from,
+            ChessMove::Drop(mv) => mv.to,
         }
     }
 
@@ -88,6 +107,19 @@ impl ChessMove {
             ChessMove::Castling(mv) => mv.king_to,
             ChessMove::Promotion(mv) => mv.to,
             ChessMove::EnPassant(mv) => mv.to,
+            ChessMove::Drop(mv) => mv.to,
+        }
+    }
+
+    /// UCI notation, with castling written as king takes rook when `chess960` is set
+    pub fn to_uci(&self, chess960: bool) -> String {
+        match self {
+            ChessMove::Normal(mv) => format!("{}{}", mv.from, mv.to),
+            ChessMove::Castling(mv) if chess960 => format!("{}{}", mv.king_from, mv.rook_from),
+            ChessMove::Castling(mv) => format!("{}{}", mv.king_from, mv.king_to),
+            ChessMove::Promotion(mv) => format!("{}{}{}", mv.from, mv.to, mv.promotion_piece_type),
+            ChessMove::EnPassant(mv) => format!("{}{}", mv.from, mv.to),
+            ChessMove::Drop(mv) => format!("{}@{}", mv.piece_type.to_string().to_uppercase(), mv.to),
         }
     }
 
@@ -97,6 +129,7 @@ impl ChessMove {
             ChessMove::Castling(_) => PieceType::King,
             ChessMove::Promotion(_) => PieceType::Pawn,
             ChessMove::EnPassant(_) => PieceType::Pawn,
+            ChessMove::Drop(mv) => mv.piece_type,
         }
     }
 }
@@ -111,8 +144,9 @@ impl ExecutedMove {
                 captured_piece.is_some()
             }
             ExecutedMove::EnPassant { mv: _ } => true,
-            ExecutedMove::Castling { mv: _ } => false
-
+            ExecutedMove::Castling { mv: _ } => false,
+            ExecutedMove::Drop { mv: _ } => false,
+            ExecutedMove::Explosion { .. } => true
         }
     }
 }
@@ -132,6 +166,9 @@ impl fmt::Display for ChessMove {
             ChessMove::EnPassant(mv) => {
                 write!(f, "{}{} e.p.", mv.from, mv.to)
             }
+            ChessMove::Drop(mv) => {
+                write!(f, "{}@{}", mv.piece_type.to_string().to_uppercase(), mv.to)
+            }
         }
     }
 }
@@ -163,6 +200,12 @@ impl fmt::Display for ExecutedMove {
             ExecutedMove::EnPassant { mv } => {
                 write!(f, "{}{} e.p.", mv.from, mv.to)
             }
+            ExecutedMove::Drop { mv } => {
+                write!(f, "{}@{}", mv.piece_type.to_string().to_uppercase(), mv.to)
+            }
+            ExecutedMove::Explosion { capture, .. } => {
+                write!(f, "{}", capture)
+            }
         }
     }
 }
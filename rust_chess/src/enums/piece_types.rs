@@ -15,6 +15,25 @@ pub enum PieceType {
 
 pub const PIECE_COUNT: usize = PieceType::King as usize + 1;
 
+/// Every piece type, indexed by its value
+pub const PIECE_TYPES: [PieceType; PIECE_COUNT] =
+    [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen, PieceType::King];
+
+impl PieceType {
+    /// The piece type of a FEN letter, in either case
+    pub fn from_char(c: char) -> Option<PieceType> {
+        match c.to_ascii_lowercase() {
+            'p' => Some(PieceType::Pawn),
+            'n' => Some(PieceType::Knight),
+            'b' => Some(PieceType::Bishop),
+            'r' => Some(PieceType::Rook),
+            'q' => Some(PieceType::Queen),
+            'k' => Some(PieceType::King),
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for PieceType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let symbol = match self {
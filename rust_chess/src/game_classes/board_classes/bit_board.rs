@@ -1,20 +1,77 @@
+use std::fmt;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl, Shr};
+
 use crate::coords::Coords;
-use crate::enums::{File};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// One bit per square, a1 is bit 0 and h8 bit 63
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
 pub struct BitBoard {
     bits: u64,
 }
 
+const fn file_masks() -> [BitBoard; 8] {
+    let mut files = [BitBoard::EMPTY; 8];
+    let mut file = 0;
+    while file < 8 {
+        files[file] = BitBoard::from_bits(0x0101_0101_0101_0101 << file);
+        file += 1;
+    }
+    files
+}
+
+const fn rank_masks() -> [BitBoard; 8] {
+    let mut ranks = [BitBoard::EMPTY; 8];
+    let mut rank = 0;
+    while rank < 8 {
+        ranks[rank] = BitBoard::from_bits(0xFF << (8 * rank));
+        rank += 1;
+    }
+    ranks
+}
+
+/// Diagonals indexed by `file - rank + 7` when `anti` is false, or anti-diagonals by `file + rank`
+const fn diagonal_masks(anti: bool) -> [BitBoard; 15] {
+    let mut diagonals = [BitBoard::EMPTY; 15];
+    let mut square = 0;
+    while square < 64 {
+        let (rank, file) = (square / 8, square % 8);
+        let index = if anti { file + rank } else { file + 7 - rank };
+        diagonals[index].bits |= 1 << square;
+        square += 1;
+    }
+    diagonals
+}
+
 impl BitBoard {
+    pub const EMPTY: BitBoard = BitBoard::from_bits(0);
+    pub const FULL: BitBoard = BitBoard::from_bits(!0);
+
+    /// Files a to h
+    pub const FILES: [BitBoard; 8] = file_masks();
+    /// Ranks 1 to 8
+    pub const RANKS: [BitBoard; 8] = rank_masks();
+    /// a1-h8 direction diagonals, indexed by `file - rank + 7` (0 is a8, 14 is h1)
+    pub const DIAGONALS: [BitBoard; 15] = diagonal_masks(false);
+    /// a8-h1 direction diagonals, indexed by `file + rank` (0 is a1, 14 is h8)
+    pub const ANTI_DIAGONALS: [BitBoard; 15] = diagonal_masks(true);
+
+    pub const FILE_A: BitBoard = Self::FILES[0];
+    pub const FILE_H: BitBoard = Self::FILES[7];
+    pub const RANK_1: BitBoard = Self::RANKS[0];
+    pub const RANK_8: BitBoard = Self::RANKS[7];
+
     pub fn new() -> Self {
         Self { bits: 0 }
     }
 
-    pub fn from_bits(bits: u64) -> Self {
+    pub const fn from_bits(bits: u64) -> Self {
         Self { bits }
     }
 
+    pub const fn from_square(square: usize) -> Self {
+        Self { bits: 1 << square }
+    }
+
     pub fn is_empty(&self) -> bool {
         self.bits == 0
     }
@@ -38,48 +95,161 @@ impl BitBoard {
     }
 
     pub fn num_set_bits(&self) -> i32 {
-        let mut out = 0;
+        self.bits.count_ones() as i32
+    }
 
-        let mut bb = self.bits;
+    pub fn contains_square(&self, square: usize) -> bool {
+        self.bits & (1 << square) != 0
+    }
 
-        while bb != 0 {
-            out += 1;
-            bb &= bb-1;
-        }
+    /// Lowest set square, if any
+    pub fn lsb(&self) -> Option<usize> {
+        (self.bits != 0).then(|| self.bits.trailing_zeros() as usize)
+    }
+
+    /// Remove and return the lowest set square
+    pub fn pop_lsb(&mut self) -> Option<usize> {
+        let square = self.lsb()?;
+        self.bits &= self.bits - 1;
+        Some(square)
+    }
 
-        out
+    /// Set squares from a1 upwards, without allocating
+    pub fn squares(self) -> Squares {
+        Squares { bits: self }
+    }
+
+    // Directional shifts, dropping whatever would wrap around to the other side of the board
 
+    pub fn north(self) -> Self {
+        Self::from_bits(self.bits << 8)
+    }
+
+    pub fn south(self) -> Self {
+        Self::from_bits(self.bits >> 8)
+    }
+
+    pub fn east(self) -> Self {
+        Self::from_bits((self.bits & !Self::FILE_H.bits) << 1)
+    }
+
+    pub fn west(self) -> Self {
+        Self::from_bits((self.bits & !Self::FILE_A.bits) >> 1)
+    }
+
+    pub fn north_east(self) -> Self {
+        self.east().north()
+    }
+
+    pub fn north_west(self) -> Self {
+        self.west().north()
+    }
+
+    pub fn south_east(self) -> Self {
+        self.east().south()
+    }
+
+    pub fn south_west(self) -> Self {
+        self.west().south()
     }
 
     pub fn get_set_coords(&self) -> Vec<Coords> {
-        let mut result = Vec::new();
-        let mut bb = self.bits; // assuming BitBoard wraps a u64, e.g. struct BitBoard(u64);
-
-        while bb != 0 {
-            // Extract index of least significant set bit
-            let idx = bb.trailing_zeros() as u8;
-
-            // Convert idx (0..63) into rank + file
-            let rank = (idx / 8) + 1; // ranks usually 1–8
-            let file = match idx % 8 {
-                0 => File::A,
-                1 => File::B,
-                2 => File::C,
-                3 => File::D,
-                4 => File::E,
-                5 => File::F,
-                6 => File::G,
-                7 => File::H,
-                _ => unreachable!(),
-            };
-
-            result.push(Coords::new(rank as u8, file));
-
-            // Clear the LSB
-            bb &= bb - 1;
+        self.squares().map(Coords::from_index).collect()
+    }
+}
+
+/// Iterator over the set squares of a `BitBoard`, lowest first
+#[derive(Debug, Clone)]
+pub struct Squares {
+    bits: BitBoard,
+}
+
+impl Iterator for Squares {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.bits.pop_lsb()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let count = self.bits.num_set_bits() as usize;
+        (count, Some(count))
+    }
+}
+
+impl ExactSizeIterator for Squares {}
+
+impl IntoIterator for BitBoard {
+    type Item = usize;
+    type IntoIter = Squares;
+
+    fn into_iter(self) -> Squares {
+        self.squares()
+    }
+}
+
+impl From<u64> for BitBoard {
+    fn from(bits: u64) -> Self {
+        Self::from_bits(bits)
+    }
+}
+
+macro_rules! bitwise_ops {
+    ($($op:ident $method:ident $assign_op:ident $assign_method:ident),*) => {$(
+        impl $op for BitBoard {
+            type Output = BitBoard;
+
+            fn $method(self, rhs: BitBoard) -> BitBoard {
+                BitBoard::from_bits(self.bits.$method(rhs.bits))
+            }
+        }
+
+        impl $assign_op for BitBoard {
+            fn $assign_method(&mut self, rhs: BitBoard) {
+                self.bits.$assign_method(rhs.bits);
+            }
         }
+    )*};
+}
+
+bitwise_ops!(BitAnd bitand BitAndAssign bitand_assign, BitOr bitor BitOrAssign bitor_assign, BitXor bitxor BitXorAssign bitxor_assign);
+
+impl Not for BitBoard {
+    type Output = BitBoard;
+
+    fn not(self) -> BitBoard {
+        BitBoard::from_bits(!self.bits)
+    }
+}
 
-        result
+/// Plain bit shifts, which wrap between files; use the directional shifts to avoid that
+impl Shl<u32> for BitBoard {
+    type Output = BitBoard;
+
+    fn shl(self, rhs: u32) -> BitBoard {
+        BitBoard::from_bits(self.bits << rhs)
+    }
+}
+
+impl Shr<u32> for BitBoard {
+    type Output = BitBoard;
+
+    fn shr(self, rhs: u32) -> BitBoard {
+        BitBoard::from_bits(self.bits >> rhs)
+    }
+}
+
+/// 8x8 grid with rank 8 at the top, for debugging
+impl fmt::Display for BitBoard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for rank in (0..8).rev() {
+            write!(f, "{} ", rank + 1)?;
+            for file in 0..8 {
+                write!(f, " {}", if self.contains_square(rank * 8 + file) { 'x' } else { '.' })?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "   a b c d e f g h")
     }
 }
 
@@ -87,6 +257,7 @@ impl BitBoard {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::enums::File;
 
     #[test]
     fn test_bit_board_initialization() {
@@ -145,4 +316,72 @@ mod tests {
 
         assert_eq!(bb.num_set_bits(), 3);
     }
+
+    #[test]
+    fn test_operators() {
+        let a = BitBoard::from_bits(0b1100);
+        let b = BitBoard::from_bits(0b1010);
+
+        assert_eq!(a & b, BitBoard::from_bits(0b1000));
+        assert_eq!(a | b, BitBoard::from_bits(0b1110));
+        assert_eq!(a ^ b, BitBoard::from_bits(0b0110));
+        assert_eq!(!BitBoard::EMPTY, BitBoard::FULL);
+        assert_eq!(a << 1, BitBoard::from_bits(0b11000));
+        assert_eq!(a >> 2, BitBoard::from_bits(0b11));
+
+        let mut c = a;
+        c |= b;
+        c &= !BitBoard::from_square(1);
+        c ^= BitBoard::from_square(0);
+        assert_eq!(c, BitBoard::from_bits(0b1101));
+    }
+
+    #[test]
+    fn test_squares_and_pop_lsb() {
+        let bb = BitBoard::from_bits((1u64 << 3) | (1u64 << 17) | (1u64 << 63));
+        assert_eq!(bb.squares().len(), 3);
+        assert_eq!(bb.squares().collect::<Vec<_>>(), vec![3, 17, 63]);
+        assert_eq!(bb.into_iter().last(), Some(63));
+
+        let mut popped = bb;
+        assert_eq!(popped.pop_lsb(), Some(3));
+        assert_eq!(popped.lsb(), Some(17));
+        assert_eq!(popped.num_set_bits(), 2);
+        assert_eq!(BitBoard::EMPTY.lsb(), None);
+    }
+
+    #[test]
+    fn test_masks() {
+        assert_eq!(BitBoard::FILES.iter().fold(BitBoard::EMPTY, |all, &file| all | file), BitBoard::FULL);
+        assert_eq!(BitBoard::RANKS[3], BitBoard::from_bits(0xFF00_0000));
+        assert!(BitBoard::FILE_H.contains_square(63) && BitBoard::RANK_8.contains_square(56));
+
+        // a1-h8 and a8-h1
+        assert_eq!(BitBoard::DIAGONALS[7], BitBoard::from_bits(0x8040_2010_0804_0201));
+        assert_eq!(BitBoard::ANTI_DIAGONALS[7], BitBoard::from_bits(0x0102_0408_1020_4080));
+        assert_eq!(BitBoard::DIAGONALS[0], BitBoard::from_square(56));
+        assert_eq!(BitBoard::ANTI_DIAGONALS[14], BitBoard::from_square(63));
+    }
+
+    #[test]
+    fn test_directional_shifts_do_not_wrap() {
+        let a_and_h = BitBoard::from_square(24) | BitBoard::from_square(31); // a4, h4
+
+        assert_eq!(a_and_h.east(), BitBoard::from_square(25));
+        assert_eq!(a_and_h.west(), BitBoard::from_square(30));
+        assert_eq!(a_and_h.north_east(), BitBoard::from_square(33));
+        assert_eq!(a_and_h.south_west(), BitBoard::from_square(22));
+        assert_eq!(BitBoard::RANK_8.north(), BitBoard::EMPTY);
+        assert_eq!(BitBoard::RANK_1.south(), BitBoard::EMPTY);
+    }
+
+    #[test]
+    fn test_display_grid() {
+        let grid = BitBoard::from_square(0).to_string();
+        let lines: Vec<&str> = grid.lines().collect();
+        assert_eq!(lines.len(), 9);
+        assert_eq!(lines[0], "8  . . . . . . . .");
+        assert_eq!(lines[7], "1  x . . . . . . .");
+        assert_eq!(lines[8], "   a b c d e f g h");
+    }
 }
@@ -1,9 +1,10 @@
 use strum::IntoEnumIterator;
 
-use crate::enums::piece_types::PIECE_COUNT;
+use crate::enums::piece_types::{PIECE_COUNT, PIECE_TYPES};
 use crate::enums::{Colour, PieceType};
 use crate::coords::Coords;
 use crate::piece::Piece;
+use crate::square::Square;
 use crate::game_classes::board_classes::bit_board::BitBoard;
 // use crate::coords::Coords;
 // use crate::pieces::Piece;
@@ -15,7 +16,10 @@ pub struct Board {
 
     white_occ: BitBoard,
     black_occ: BitBoard,
-    all_occ: BitBoard
+    all_occ: BitBoard,
+
+    // Piece on each square, kept in sync with the bitboards for O(1) lookups
+    mailbox: [Option<Piece>; 64],
 }
 
 impl Board {
@@ -27,6 +31,8 @@ impl Board {
             white_occ: BitBoard::new(),
             black_occ: BitBoard::new(),
             all_occ: BitBoard::new(),
+
+            mailbox: [None; 64],
         }   
     }
 
@@ -45,6 +51,15 @@ impl Board {
         self.white_occ = BitBoard::from_bits(white_bits);
         self.black_occ = BitBoard::from_bits(black_bits);
         self.all_occ = BitBoard::from_bits(white_bits | black_bits);
+
+        self.recompute_mailbox();
+    }
+
+    fn recompute_mailbox(&mut self) {
+        self.mailbox = [None; 64];
+        for (piece, coords) in self.get_all_pieces() {
+            self.mailbox[coords.to_index()] = Some(piece);
+        }
     }
 
     pub fn setup_startposition() -> Self {
@@ -100,6 +115,11 @@ impl Board {
         out
     }
 
+    /// Every piece on the board and its square, without collecting them
+    pub fn pieces(&self) -> Pieces<'_> {
+        Pieces { board: self, index: 0, bits: 0 }
+    }
+
     pub fn get_all_pieces(&self) -> Vec<(Piece, Coords)> {
         let mut out = Vec::new();
 
@@ -124,6 +144,10 @@ impl Board {
         }
     }
 
+    pub fn get_piece_bit_board(&self, piece: Piece) -> BitBoard {
+        *self.get_bit_board(&piece)
+    }
+
     fn get_bit_board_mut(&mut self, piece: &Piece) -> &mut BitBoard {
         match piece.colour {
             Colour::White => &mut self.white_bit_boards[piece.kind as usize],
@@ -153,15 +177,8 @@ impl Board {
                     file_index += ch.to_digit(10).unwrap() as usize;
                 } else {
                     let colour = if ch.is_uppercase() { Colour::White } else { Colour::Black };
-                    let piece_type = match ch.to_ascii_lowercase() {
-                        'p' => PieceType::Pawn,
-                        'r' => PieceType::Rook,
-                        'n' => PieceType::Knight,
-                        'b' => PieceType::Bishop,
-                        'q' => PieceType::Queen,
-                        'k' => PieceType::King,
-                        _ => panic!("Invalid FEN string: unknown piece {}", ch),
-                    };
+                    let piece_type = PieceType::from_char(ch)
+                        .unwrap_or_else(|| panic!("Invalid FEN string: unknown piece {}", ch));
 
                     let coords = Coords::new(8 - rank_index as u8, crate::enums::File::from_usize(file_index).unwrap());
                     let piece = Piece { kind: piece_type, colour: colour };
@@ -178,39 +195,14 @@ impl Board {
     }
 
     pub fn set_coords(&mut self, coords: &Coords, maybe_piece: Option<Piece>) {
-        let index = coords.to_index();
-        let mask = 1u64 << index;
-
-
-        self.white_occ = BitBoard::from_bits(self.white_occ.bits() & !mask);
-        self.black_occ = BitBoard::from_bits(self.black_occ.bits() & !mask);
+        let square = Square::from(coords);
 
-
-        // Clear all bitboards first
-        for colour in [Colour::White, Colour::Black] {
-            for piece_type in PieceType::iter() {
-                let piece = Piece { kind: piece_type, colour };
-                let bitboard = self.get_bit_board_mut(&piece);
-                bitboard.set_bit(coords, false);
-            }
-        }
-
-        // If we're setting a new piece
+        self.remove_piece(square);
         if let Some(piece) = maybe_piece {
-            self.get_bit_board_mut(&piece).set_bit(coords, true);
-
-            match piece.colour {
-                Colour::White => {
-                    self.white_occ = BitBoard::from_bits(self.white_occ.bits() | mask);
-                }
-                Colour::Black => {
-                    self.black_occ = BitBoard::from_bits(self.black_occ.bits() | mask);
-                }
-            }
+            self.put_piece(square, piece);
         }
 
-        // Always recompute all_occ
-        self.all_occ = BitBoard::from_bits(self.white_occ.bits() | self.black_occ.bits());
+        debug_assert!(self.is_consistent(), "Board out of sync after set_coords({}, {:?})", coords, maybe_piece);
     }
 
     pub fn move_piece(&mut self, piece: &Piece, from: &Coords, to: &Coords) {
@@ -223,32 +215,137 @@ impl Board {
             }
         }
 
-        self.set_coords(from, None);
-        self.set_coords(to, Some(*piece));
+        let (from, to) = (Square::from(from), Square::from(to));
+        self.remove_piece(to);
+        self.toggle_piece(from, piece);
+        self.toggle_piece(to, piece);
+        self.mailbox[from.index()] = None;
+        self.mailbox[to.index()] = Some(*piece);
+
+        debug_assert!(self.is_consistent(), "Board out of sync after moving {:?} to {}", piece, to);
+    }
 
+    /// Place `piece` on an empty square
+    pub fn put_piece(&mut self, square: Square, piece: Piece) {
+        debug_assert!(self.mailbox[square.index()].is_none(), "{} is already occupied", square);
+        self.toggle_piece(square, &piece);
+        self.mailbox[square.index()] = Some(piece);
+    }
+
+    /// Clear a square, returning whatever was on it
+    pub fn remove_piece(&mut self, square: Square) -> Option<Piece> {
+        let removed = self.mailbox[square.index()].take();
+        if let Some(piece) = removed {
+            debug_assert!(self.get_bit_board(&piece).bits() & square.bit() != 0, "Mailbox has {:?} on {} but its bitboard does not", piece, square);
+            self.toggle_piece(square, &piece);
+        }
+        removed
+    }
+
+    // Flip a single square in the piece's bitboard and the occupancy caches
+    fn toggle_piece(&mut self, square: Square, piece: &Piece) {
+        let mask = square.bit();
+        let bitboard = self.get_bit_board_mut(piece);
+        *bitboard = BitBoard::from_bits(bitboard.bits() ^ mask);
+
+        match piece.colour {
+            Colour::White => self.white_occ = BitBoard::from_bits(self.white_occ.bits() ^ mask),
+            Colour::Black => self.black_occ = BitBoard::from_bits(self.black_occ.bits() ^ mask),
+        }
+        self.all_occ = BitBoard::from_bits(self.all_occ.bits() ^ mask);
     }
 
     pub fn get_coords(&self, coords: &Coords) -> Option<Piece> {
+        self.mailbox[coords.to_index()]
+    }
+
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        self.mailbox[square.index()]
+    }
+
+    /// Look a square up by testing every piece bitboard instead of the mailbox
+    pub fn scan_bit_boards(&self, square: Square) -> Option<Piece> {
+        let mask = square.bit();
         for colour in [Colour::White, Colour::Black] {
-            let bitboards = match colour {
-                Colour::White => &self.white_bit_boards,
-                Colour::Black => &self.black_bit_boards,
-            };
-
-            for piece in PieceType::iter() {
-                if bitboards[piece as usize].is_set(coords) {
-                    return Some(
-                        Piece {
-                            kind: piece,
-                            colour: colour
-                        }
-                    );
+            for kind in PieceType::iter() {
+                let piece = Piece { kind, colour };
+                if self.get_bit_board(&piece).bits() & mask != 0 {
+                    return Some(piece);
                 }
             }
         }
         None
     }
 
+    /// Whether the mailbox, piece bitboards and occupancy caches all describe the same position
+    pub fn is_consistent(&self) -> bool {
+        self.structure_violations().is_empty()
+    }
+
+    /// Everything wrong with the position: what `is_consistent` checks, plus a side without
+    /// exactly one king or pawns on the first or last rank
+    pub fn validate(&self) -> Vec<String> {
+        let mut violations = self.structure_violations();
+
+        for colour in [Colour::White, Colour::Black] {
+            let kings = self.get_piece_bit_board(Piece { kind: PieceType::King, colour }).num_set_bits();
+            if kings != 1 {
+                violations.push(format!("{:?} has {} kings", colour, kings));
+            }
+
+            let pawns = self.get_piece_bit_board(Piece { kind: PieceType::Pawn, colour });
+            for square in (pawns & (BitBoard::RANK_1 | BitBoard::RANK_8)).squares() {
+                violations.push(format!("{:?} pawn on {}", colour, Square::from_index(square)));
+            }
+        }
+
+        violations
+    }
+
+    // Disagreements between the piece bitboards, occupancy caches and mailbox
+    pub(crate) fn structure_violations(&self) -> Vec<String> {
+        let mut violations = Vec::new();
+        let mut white_bits = 0u64;
+        let mut black_bits = 0u64;
+        for kind in PieceType::iter() {
+            let (white, black) = (self.white_bit_boards[kind as usize].bits(), self.black_bit_boards[kind as usize].bits());
+            // No square may hold two pieces
+            let shared = ((white_bits | black_bits) & (white | black)) | (white & black);
+            for square in BitBoard::from_bits(shared).squares() {
+                violations.push(format!("{} holds more than one piece", Square::from_index(square)));
+            }
+            white_bits |= white;
+            black_bits |= black;
+        }
+
+        for (name, cached, actual) in [
+            ("White", self.white_occ.bits(), white_bits),
+            ("Black", self.black_occ.bits(), black_bits),
+            ("All", self.all_occ.bits(), white_bits | black_bits),
+        ] {
+            if cached != actual {
+                violations.push(format!("{} occupancy is {:#018x} but the pieces cover {:#018x}", name, cached, actual));
+            }
+        }
+
+        // Scanning every square is slow, so only done to find out where the mailbox is wrong
+        let occupied = white_bits | black_bits;
+        let mut mailbox_matches = self.pieces().all(|(piece, square)| self.mailbox[square.index()] == Some(piece));
+        for square in 0..64 {
+            mailbox_matches &= self.mailbox[square].is_some() == (occupied & (1u64 << square) != 0);
+        }
+        if !mailbox_matches {
+            for square in Square::all() {
+                let (mailbox, scanned) = (self.mailbox[square.index()], self.scan_bit_boards(square));
+                if mailbox != scanned {
+                    violations.push(format!("Mailbox has {:?} on {} but the bitboards have {:?}", mailbox, square, scanned));
+                }
+            }
+        }
+
+        violations
+    }
+
     pub fn get_piece_coords(&self, piece: Piece) -> Vec<Coords> {
         let bitboards = match piece.colour {
             Colour::White => &self.white_bit_boards,
@@ -271,6 +368,36 @@ impl Board {
     pub fn all_occ(&self) -> BitBoard { self.all_occ }
 }
 
+/// Iterator over the pieces on a board, white's and black's of each kind in turn
+pub struct Pieces<'a> {
+    board: &'a Board,
+    // Bitboard being read, white pawns first and black kings last
+    index: usize,
+    bits: u64,
+}
+
+impl Iterator for Pieces<'_> {
+    type Item = (Piece, Square);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.bits == 0 {
+            if self.index == 2 * PIECE_COUNT {
+                return None;
+            }
+            let (colour, kind) = (self.index / PIECE_COUNT, self.index % PIECE_COUNT);
+            let bitboards = if colour == 0 { &self.board.white_bit_boards } else { &self.board.black_bit_boards };
+            self.bits = bitboards[kind].bits();
+            self.index += 1;
+        }
+
+        let square = Square::new(self.bits.trailing_zeros() as u8);
+        self.bits &= self.bits - 1;
+        let index = self.index - 1;
+        let colour = if index < PIECE_COUNT { Colour::White } else { Colour::Black };
+        Some((Piece { kind: PIECE_TYPES[index % PIECE_COUNT], colour }, square))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -334,10 +461,7 @@ mod tests {
         let to = Coords { rank: 3, file: File::A }; // e2
 
         // Place a pawn at "from"
-        {
-            let bitboard = board.get_bit_board_mut(&white_pawn);
-            bitboard.set_bit(&from, true);
-        }
+        board.set_coords(&from, Some(white_pawn));
 
         // Move it
         board.move_piece(&white_pawn, &from, &to);
@@ -365,6 +489,58 @@ mod tests {
         board.move_piece(&pawn, &from, &to);
     }
 
+    #[test]
+    fn test_mailbox_tracks_set_coords_and_move_piece() {
+        let mut board = Board::setup_startposition();
+        assert!(board.is_consistent());
+
+        let knight = Piece { kind: PieceType::Knight, colour: Colour::White };
+        let pawn = Piece { kind: PieceType::Pawn, colour: Colour::Black };
+
+        // Ng1-f3, then drop a black pawn on e4 and capture it with the knight
+        board.move_piece(&knight, &Coords::new(1, File::G), &Coords::new(3, File::F));
+        board.set_coords(&Coords::new(4, File::E), Some(pawn));
+        board.move_piece(&knight, &Coords::new(3, File::F), &Coords::new(4, File::E));
+
+        assert_eq!(board.get_coords(&Coords::new(1, File::G)), None);
+        assert_eq!(board.get_coords(&Coords::new(3, File::F)), None);
+        assert_eq!(board.get_coords(&Coords::new(4, File::E)), Some(knight));
+        assert!(board.get_piece_bit_board(pawn).bits() & (1u64 << Coords::new(4, File::E).to_index()) == 0);
+        assert!(board.is_consistent());
+    }
+
+    #[test]
+    fn test_inconsistent_board_detected() {
+        let mut board = Board::setup_startposition();
+
+        // Writing a bitboard directly leaves the mailbox behind
+        board.white_bit_boards[PieceType::Queen as usize].set_bit(&Coords::new(4, File::D), true);
+        assert!(!board.is_consistent());
+
+        board.recompute_occupancy();
+        assert!(board.is_consistent());
+    }
+
+    #[test]
+    fn test_validate_reports_violations() {
+        let mut board = Board::setup_startposition();
+        assert!(board.validate().is_empty());
+
+        let white_king = Piece { kind: PieceType::King, colour: Colour::White };
+        board.set_coords(&Coords::new(1, File::E), None);
+        board.set_coords(&Coords::new(8, File::A), Some(Piece { kind: PieceType::Pawn, colour: Colour::White }));
+        board.set_coords(&Coords::new(4, File::D), Some(white_king));
+        board.set_coords(&Coords::new(4, File::E), Some(white_king));
+        assert_eq!(board.validate(), vec!["White has 2 kings", "White pawn on a8"]);
+
+        // Two pieces on one square, with the occupancy caches left behind
+        board.black_bit_boards[PieceType::Queen as usize].set_bit(&Coords::new(4, File::D), true);
+        let violations = board.validate();
+        assert!(violations.contains(&"d4 holds more than one piece".to_string()));
+        assert!(violations.iter().any(|violation| violation.starts_with("Black occupancy")));
+        assert!(!board.is_consistent());
+    }
+
     #[test]
     fn test_get_player_pieces_empty_board() {
         let board = Board::new();
@@ -1,6 +1,8 @@
 use once_cell::sync::Lazy;
 use crate::game_classes::board_classes::bit_board::BitBoard;
+use crate::game_classes::board_classes::magic_numbers::{BISHOP_MAGICS, ROOK_MAGICS};
 use std::mem::MaybeUninit;
+use rand::Rng;
 
 pub static MAGIC_TABLES: Lazy<MagicTables> = Lazy::new(|| MagicTables::new());
 
@@ -9,6 +11,7 @@ pub struct MagicBitboard {
     pub magic: u64,            // Magic multiplier for hashing
     pub shift: u8,             // Right-shift for index compression
     pub attacks: Vec<u64>,     // Precomputed attack bitboards
+    pub pext: bool,            // Attacks are indexed by PEXT of the blockers instead of the magic
 }
 
 pub struct MagicTables {
@@ -17,29 +20,55 @@ pub struct MagicTables {
 }
 
 fn get_blocker_index(magic: &MagicBitboard, occ: u64) -> usize {
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    if magic.pext {
+        // only set once BMI2 has been detected
+        return unsafe { pext(occ, magic.mask) } as usize;
+    }
+
     let relevant_blockers = occ & magic.mask;
     ((relevant_blockers.wrapping_mul(magic.magic)) >> magic.shift) as usize
 }
 
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+#[target_feature(enable = "bmi2")]
+unsafe fn pext(value: u64, mask: u64) -> u64 {
+    std::arch::x86_64::_pext_u64(value, mask)
+}
+
+/// Whether to index attacks with PEXT, which needs the `pext` feature and a CPU with BMI2
+fn use_pext() -> bool {
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    return std::arch::is_x86_feature_detected!("bmi2");
+
+    #[cfg(not(all(feature = "pext", target_arch = "x86_64")))]
+    false
+}
+
 
 impl MagicBitboard {
     pub fn get_attacks(&self, occ_all: &BitBoard) -> BitBoard {
-        let blockers = occ_all.bits() & self.mask;
-        let index = ((blockers.wrapping_mul(self.magic)) >> self.shift) as usize;
-        BitBoard::from_bits(self.attacks[index])
+        BitBoard::from_bits(self.attacks[get_blocker_index(self, occ_all.bits())])
     }
 
 }
 
 impl MagicTables {
+    /// Attack tables for the precomputed magics, or for PEXT indexing where it's available
     pub fn new() -> Self {
+        Self::with_pext(use_pext())
+    }
+
+    /// Attack tables indexed by PEXT when `pext` is set, which is only safe to ask for once the
+    /// CPU is known to support BMI2
+    pub fn with_pext(pext: bool) -> Self {
         Self {
-            bishop_magics: Self::init_magic_table(true),
-            rook_magics: Self::init_magic_table(false),
+            bishop_magics: Self::init_magic_table(true, pext),
+            rook_magics: Self::init_magic_table(false, pext),
         }
     }
 
-    fn init_magic_table(is_bishop: bool) -> [MagicBitboard; 64] {
+    fn init_magic_table(is_bishop: bool, pext: bool) -> [MagicBitboard; 64] {
         let mut table: [MaybeUninit<MagicBitboard>; 64] = unsafe { MaybeUninit::uninit().assume_init() };
 
         for sq in 0..64 {
@@ -50,7 +79,7 @@ impl MagicTables {
             };
 
             let bits = mask.count_ones() as usize;
-            let magic = Self::find_magic(sq, bits, is_bishop);
+            let magic = if is_bishop { BISHOP_MAGICS[sq] } else { ROOK_MAGICS[sq] };
             let shift = 64 - bits as u8;
             let num_entries = 1 << bits;
             let mut attacks = vec![0u64; num_entries];
@@ -62,11 +91,12 @@ impl MagicTables {
                 } else {
                     Self::rook_attacks(sq, blockers)
                 };
-                let idx = ((blockers.wrapping_mul(magic)) >> shift) as usize;
+                // PEXT of the blockers on the mask gives back the index they were made from
+                let idx = if pext { index } else { ((blockers.wrapping_mul(magic)) >> shift) as usize };
                 attacks[idx] = attack;
             }
 
-            table[sq] = MaybeUninit::new(MagicBitboard { mask, magic, shift, attacks });
+            table[sq] = MaybeUninit::new(MagicBitboard { mask, magic, shift, attacks, pext });
         }
 
         unsafe { std::mem::transmute::<_, [MagicBitboard; 64]>(table) }
@@ -211,8 +241,10 @@ impl MagicTables {
         attacks
     }
 
-    fn find_magic(square: usize, relevant_bits: usize, bishop: bool) -> u64 {
+    /// Search for a magic by random trial, the way `ROOK_MAGICS` and `BISHOP_MAGICS` were found
+    pub fn find_magic<R: Rng>(square: usize, bishop: bool, rng: &mut R) -> u64 {
         let mask = if bishop { MagicTables::bishop_mask(square) } else { MagicTables::rook_mask(square) };
+        let relevant_bits = mask.count_ones() as usize;
         let occupancy_variations = 1 << relevant_bits;
 
         let mut blockers = vec![0u64; occupancy_variations];
@@ -230,7 +262,8 @@ impl MagicTables {
 
         // Try random magics until one works
         loop {
-            let magic = MagicTables::random_sparse_u64();
+            // sparse candidates are far more likely to work
+            let magic = rng.random::<u64>() & rng.random::<u64>() & rng.random::<u64>();
             if ((mask.wrapping_mul(magic)) & 0xFF00000000000000).count_ones() < 6 {
                 continue; // skip poor magics
             }
@@ -254,11 +287,6 @@ impl MagicTables {
         }
     }
 
-    fn random_sparse_u64() -> u64 {
-        // Keeps only a few bits from random_u64() to make it sparse
-        rand::random::<u64>() & rand::random::<u64>() & rand::random::<u64>()
-    }
-
 }
 
 #[cfg(test)]
@@ -267,6 +295,49 @@ mod tests {
     use crate::game_classes::board_classes::bit_board::BitBoard;
     use crate::coords::Coords;
     use crate::enums::file::File;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha12Rng;
+
+    #[test]
+    fn test_precomputed_magics_are_collision_free() {
+        for sq in 0..64 {
+            for (bishop, magic) in [(false, ROOK_MAGICS[sq]), (true, BISHOP_MAGICS[sq])] {
+                let mask = if bishop { MagicTables::bishop_mask(sq) } else { MagicTables::rook_mask(sq) };
+                let bits = mask.count_ones() as usize;
+                let mut slots = vec![None; 1 << bits];
+
+                for index in 0..(1u64 << bits) {
+                    let blockers = MagicTables::set_occupancy(index, bits, mask);
+                    let attacks = if bishop { MagicTables::bishop_attacks(sq, blockers) } else { MagicTables::rook_attacks(sq, blockers) };
+                    let slot = &mut slots[(blockers.wrapping_mul(magic) >> (64 - bits)) as usize];
+                    assert!(slot.is_none() || *slot == Some(attacks), "Magic for square {} (bishop: {}) clashes", sq, bishop);
+                    *slot = Some(attacks);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_magic_reproduces_precomputed_magics() {
+        // rand_chacha keeps its streams stable across releases, unlike `StdRng`
+        let mut rng = ChaCha12Rng::seed_from_u64(64);
+        assert_eq!(MagicTables::find_magic(0, false, &mut rng), ROOK_MAGICS[0]);
+        assert_eq!(MagicTables::find_magic(1, false, &mut rng), ROOK_MAGICS[1]);
+    }
+
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    #[test]
+    fn test_pext_tables_match_magic_tables() {
+        if !std::arch::is_x86_feature_detected!("bmi2") {
+            return;
+        }
+        let (magic, pext) = (MagicTables::with_pext(false), MagicTables::with_pext(true));
+        for sq in 0..64 {
+            let occ = BitBoard::from_bits(rand::random::<u64>());
+            assert_eq!(magic.get_rook_attacks(sq, &occ).bits(), pext.get_rook_attacks(sq, &occ).bits());
+            assert_eq!(magic.get_bishop_attacks(sq, &occ).bits(), pext.get_bishop_attacks(sq, &occ).bits());
+        }
+    }
 
     #[test]
     fn test_rook_mask_edges() {
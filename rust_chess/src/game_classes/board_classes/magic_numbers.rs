@@ -0,0 +1,42 @@
+// Magic multipliers for the sliding piece attack tables, indexed by square (a1 = 0). They were
+// found once with `MagicTables::find_magic` from a `rand_chacha::ChaCha12Rng` seeded with 64,
+// all rook squares first and then all bishop squares. Each maps every blocker set on its square's mask to a slot
+// without clashing attacks, indexing with exactly as many bits as the mask has.
+
+pub const ROOK_MAGICS: [u64; 64] = [
+    0x1480_0061_8040_0412, 0x0040_4000_2000_1000, 0x6080_1000_2000_0880, 0x0100_0420_1000_0902,
+    0x0200_1002_0020_0804, 0x0280_0200_8004_0021, 0x8400_4100_8208_2410, 0x6100_0040_8210_2100,
+    0x8021_8010_c000_2080, 0x0011_0021_0080_4000, 0x4041_0019_0020_0040, 0x0001_000d_0010_0220,
+    0x0010_8080_0800_0400, 0x3020_8080_0400_0200, 0x4044_0010_0104_0802, 0x0102_0000_4124_0486,
+    0x0400_9180_0060_4003, 0x0040_0020_0028_1001, 0x2020_0080_8020_1000, 0x0000_8080_0800_1002,
+    0x0004_0080_8008_0004, 0x0000_8080_0400_0200, 0x1020_a101_0002_0004, 0x0000_0200_1880_4421,
+    0x0080_4000_8000_2088, 0x0000_8042_0022_0110, 0x0000_2000_8080_1000, 0x1802_1004_8008_0080,
+    0x2008_0800_8080_0400, 0x2801_0003_0024_0008, 0x4041_0001_0004_0200, 0x4088_2302_0000_844c,
+    0x4080_0020_0040_0040, 0x0000_4000_8080_2000, 0x1402_0020_8200_1840, 0x0010_0802_8280_1001,
+    0x0300_8004_0080_0800, 0x0802_0010_0200_0508, 0x2200_1008_0400_01c2, 0x0010_8010_6080_0100,
+    0x0100_4000_8000_8025, 0x0220_1000_2040_4000, 0x0210_0020_0080_8016, 0x0450_0020_1101_0008,
+    0x2000_0400_0800_8080, 0x0002_0010_0402_0008, 0x48b0_1008_4104_0042, 0x0000_0045_008e_0004,
+    0x0400_4080_0900_3100, 0x7120_4000_8020_0080, 0x0a08_4204_1021_8200, 0x0b10_2209_0010_0100,
+    0x0408_0004_0008_8080, 0x1804_0200_8004_0080, 0x0104_3211_1008_0400, 0x0040_408c_0100_4a00,
+    0x0083_0022_4010_800d, 0x1000_4001_0020_1081, 0x1a02_d140_2900_2001, 0x2204_0900_2004_1001,
+    0x000a_0020_0804_1002, 0x2402_0004_0801_1002, 0x0080_1510_40a2_0804, 0x0000_0048_8c00_2902,
+];
+
+pub const BISHOP_MAGICS: [u64; 64] = [
+    0x0220_0110_0220_4042, 0x0008_180b_2400_b004, 0x6610_2102_0c24_0000, 0x0011_0400_8002_0028,
+    0x0044_2420_4400_0800, 0x8041_0160_1001_0281, 0x0004_0201_7009_2031, 0x0004_2101_1090_4008,
+    0x4086_0860_8400_8200, 0x2109_8802_0802_0a28, 0x8000_2806_040a_2010, 0x8002_9090_8200_0400,
+    0x9030_0202_1000_4020, 0x8400_0202_d004_0904, 0x0000_1044_0220_1103, 0x0002_1a02_1a02_0200,
+    0x0088_0010_0348_0802, 0x3020_4008_0801_2041, 0x4068_0004_2224_0010, 0x0408_0620_8200_4004,
+    0x0080_8014_08a0_0100, 0x0464_4008_0808_0481, 0x405c_0002_0252_0240, 0x0000_2408_8221_1000,
+    0x0488_6022_6614_1000, 0x0110_0888_1009_4921, 0x4024_0104_1088_0082, 0x0104_0400_0c40_1280,
+    0x0450_0400_1080_2100, 0x0002_0200_0190_5008, 0xc002_0200_0088_0120, 0x0014_2480_0420_8400,
+    0xd124_0208_0120_a100, 0x0000_8490_0004_102b, 0x0d00_2410_00cd_0901, 0x0000_0808_000a_0a00,
+    0x1011_0501_0010_0440, 0x0010_1000_2088_4400, 0x8042_0084_0402_0204, 0x00b4_0481_280a_0124,
+    0x0002_1004_2140_0400, 0x0020_4c30_040c_0810, 0x0220_0824_8800_1000, 0x8000_0014_1400_0802,
+    0x1400_0841_0040_2400, 0x400e_201c_0820_8300, 0x2009_0108_0201_0080, 0x0201_0811_0508_2044,
+    0x0101_0808_0404_88a0, 0x0206_0201_0442_0014, 0x4e0c_0104_0104_0080, 0x0040_5020_4608_0201,
+    0x0100_0042_0822_0100, 0x0004_89b0_9000_8020, 0x0490_2082_0400_5002, 0x8002_060a_6401_0101,
+    0x4803_0032_1004_2400, 0x0801_8022_0110_0802, 0x8020_0400_4608_1125, 0x0002_8041_4020_8840,
+    0x0241_0180_4005_0108, 0x8012_0005_0408_2200, 0x0020_0448_a801_0413, 0x4120_1204_820c_1241,
+];
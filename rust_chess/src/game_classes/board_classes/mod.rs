@@ -1,4 +1,5 @@
 pub mod bit_board;
 pub mod board;
 pub mod magic_bitboard;
-pub mod piece_attacks;
\ No newline at end of file
+pub mod piece_attacks;
+pub mod magic_numbers;
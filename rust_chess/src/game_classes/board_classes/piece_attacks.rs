@@ -1,72 +1,70 @@
 use once_cell::sync::Lazy;
 
-pub static KNIGHT_ATTACKS: Lazy<[u64; 64]> = Lazy::new(|| init_knight_attacks());
-pub static KING_ATTACKS: Lazy<[u64; 64]> = Lazy::new(|| init_king_attacks());
-pub static WHITE_PAWN_ATTACKS: Lazy<[u64; 64]> = Lazy::new(|| init_pawn_attacks(true));
-pub static BLACK_PAWN_ATTACKS: Lazy<[u64; 64]> = Lazy::new(|| init_pawn_attacks(false));
+use crate::game_classes::board_classes::magic_bitboard::MagicTables;
 
-fn init_knight_attacks() -> [u64; 64] {
+// Leaper attacks are built at compile time
+pub static KNIGHT_ATTACKS: [u64; 64] = leaper_attacks(&[(2, 1), (1, 2), (-1, 2), (-2, 1), (-2, -1), (-1, -2), (1, -2), (2, -1)]);
+pub static KING_ATTACKS: [u64; 64] = leaper_attacks(&[(1, -1), (1, 0), (1, 1), (0, -1), (0, 1), (-1, -1), (-1, 0), (-1, 1)]);
+pub static WHITE_PAWN_ATTACKS: [u64; 64] = leaper_attacks(&[(1, -1), (1, 1)]);
+pub static BLACK_PAWN_ATTACKS: [u64; 64] = leaper_attacks(&[(-1, -1), (-1, 1)]);
+
+/// Squares strictly between two squares sharing a rank, file or diagonal (empty otherwise)
+pub static BETWEEN: Lazy<Box<[[u64; 64]; 64]>> = Lazy::new(init_between);
+/// Full rank, file or diagonal through two aligned squares (empty if they are not aligned)
+pub static LINE: Lazy<Box<[[u64; 64]; 64]>> = Lazy::new(init_line);
+
+/// Squares reached from each square by the given (rank, file) steps, staying on the board
+const fn leaper_attacks(steps: &[(i32, i32)]) -> [u64; 64] {
     let mut attacks = [0u64; 64];
-    for sq in 0..64 {
+    let mut sq = 0;
+    while sq < 64 {
         let rank = (sq / 8) as i32;
         let file = (sq % 8) as i32;
-        let mut moves = 0u64;
-        let knight_moves = [
-            (2, 1), (1, 2), (-1, 2), (-2, 1),
-            (-2, -1), (-1, -2), (1, -2), (2, -1)
-        ];
-
-        for (dr, df) in knight_moves {
-            let r = rank + dr;
-            let f = file + df;
-            if (0..8).contains(&r) && (0..8).contains(&f) {
-                moves |= 1u64 << (r * 8 + f);
+        let mut i = 0;
+        while i < steps.len() {
+            let (r, f) = (rank + steps[i].0, file + steps[i].1);
+            if r >= 0 && r < 8 && f >= 0 && f < 8 {
+                attacks[sq] |= 1u64 << (r * 8 + f);
             }
+            i += 1;
         }
-        attacks[sq] = moves;
+        sq += 1;
     }
     attacks
 }
 
-fn init_king_attacks() -> [u64; 64] {
-    let mut attacks = [0u64; 64];
-    for sq in 0..64 {
-        let rank = (sq / 8) as i32;
-        let file = (sq % 8) as i32;
-        let mut moves = 0u64;
-        for dr in -1..=1 {
-            for df in -1..=1 {
-                if dr == 0 && df == 0 { continue; }
-                let r = rank + dr;
-                let f = file + df;
-                if (0..8).contains(&r) && (0..8).contains(&f) {
-                    moves |= 1u64 << (r * 8 + f);
-                }
+fn init_between() -> Box<[[u64; 64]; 64]> {
+    let mut between = Box::new([[0u64; 64]; 64]);
+    for a in 0..64 {
+        for b in 0..64 {
+            if a == b { continue; }
+            let (a_bit, b_bit) = (1u64 << a, 1u64 << b);
+
+            if MagicTables::rook_attacks(a, 0) & b_bit != 0 {
+                between[a][b] = MagicTables::rook_attacks(a, b_bit) & MagicTables::rook_attacks(b, a_bit);
+            } else if MagicTables::bishop_attacks(a, 0) & b_bit != 0 {
+                between[a][b] = MagicTables::bishop_attacks(a, b_bit) & MagicTables::bishop_attacks(b, a_bit);
             }
         }
-        attacks[sq] = moves;
     }
-    attacks
+    between
 }
 
-fn init_pawn_attacks(white: bool) -> [u64; 64] {
-    let mut attacks = [0u64; 64];
-    for sq in 0..64 {
-        let rank = (sq / 8) as i32;
-        let file = (sq % 8) as i32;
-        let mut moves = 0u64;
-        let directions = if white { [(1, -1), (1, 1)] } else { [(-1, -1), (-1, 1)] };
-
-        for (dr, df) in directions {
-            let r = rank + dr;
-            let f = file + df;
-            if (0..8).contains(&r) && (0..8).contains(&f) {
-                moves |= 1u64 << (r * 8 + f);
+fn init_line() -> Box<[[u64; 64]; 64]> {
+    let mut line = Box::new([[0u64; 64]; 64]);
+    for a in 0..64 {
+        for b in 0..64 {
+            if a == b { continue; }
+            let (a_bit, b_bit) = (1u64 << a, 1u64 << b);
+
+            if MagicTables::rook_attacks(a, 0) & b_bit != 0 {
+                line[a][b] = (MagicTables::rook_attacks(a, 0) & MagicTables::rook_attacks(b, 0)) | a_bit | b_bit;
+            } else if MagicTables::bishop_attacks(a, 0) & b_bit != 0 {
+                line[a][b] = (MagicTables::bishop_attacks(a, 0) & MagicTables::bishop_attacks(b, 0)) | a_bit | b_bit;
             }
         }
-        attacks[sq] = moves;
     }
-    attacks
+    line
 }
 
 #[cfg(test)]
@@ -173,5 +171,31 @@ mod tests {
             assert_eq!(BLACK_PAWN_ATTACKS[sq] & bit(sq), 0);
         }
     }
+
+    #[test]
+    fn test_between_on_file_and_diagonal() {
+        // a1 -> a4 should contain a2 and a3
+        assert_eq!(BETWEEN[0][24], bit(8) | bit(16));
+        // c1 -> f4 should contain d2 and e3
+        assert_eq!(BETWEEN[2][29], bit(11) | bit(20));
+        // Adjacent squares have nothing between them
+        assert_eq!(BETWEEN[0][1], 0);
+        // Unaligned squares (a1, b3) have nothing between them
+        assert_eq!(BETWEEN[0][17], 0);
+    }
+
+    #[test]
+    fn test_line_through_aligned_squares() {
+        // e1 and e4 lie on the e-file
+        let e_file: u64 = 0x1010_1010_1010_1010;
+        assert_eq!(LINE[4][28], e_file);
+        assert_eq!(LINE[28][4], e_file);
+
+        // a1 and h8 lie on the long diagonal
+        assert_eq!(LINE[0][63], 0x8040_2010_0804_0201);
+
+        // Knight-distance squares are not aligned
+        assert_eq!(LINE[0][17], 0);
+    }
 }
 
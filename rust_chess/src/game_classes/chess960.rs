@@ -0,0 +1,74 @@
+// Which two of the five squares left after the bishops and queen the knights take
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [
+    (0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4),
+];
+
+/// FEN of Chess960 start position `index` (0-959) in Scharnagl's numbering, where 518 is the
+/// standard start position
+pub fn start_fenstr(index: u16) -> String {
+    assert!(index < 960, "Chess960 start position index out of range: {}", index);
+
+    let mut back_rank = [None; 8];
+    let mut n = index as usize;
+
+    // One bishop on the light squares b, d, f, h and one on the dark squares a, c, e, g
+    back_rank[2 * (n % 4) + 1] = Some('b');
+    n /= 4;
+    back_rank[2 * (n % 4)] = Some('b');
+    n /= 4;
+
+    // The queen and knights go on the nth empty square counting from the a file, the second
+    // knight first so the first one's count is unaffected
+    place(&mut back_rank, n % 6, 'q');
+    n /= 6;
+    let (first_knight, second_knight) = KNIGHT_PLACEMENTS[n];
+    place(&mut back_rank, second_knight, 'n');
+    place(&mut back_rank, first_knight, 'n');
+
+    // The king always ends up between the rooks
+    for piece in ['r', 'k', 'r'] {
+        place(&mut back_rank, 0, piece);
+    }
+
+    let black: String = back_rank.iter().map(|piece| piece.unwrap()).collect();
+    format!("{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1", black, black.to_uppercase())
+}
+
+fn place(back_rank: &mut [Option<char>; 8], nth_empty: usize, piece: char) {
+    let file = (0..8).filter(|&file| back_rank[file].is_none()).nth(nth_empty).unwrap();
+    back_rank[file] = Some(piece);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_standard_position_is_518() {
+        assert_eq!(start_fenstr(518), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(start_fenstr(0), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+    }
+
+    #[test]
+    fn test_all_start_positions_are_distinct_and_valid() {
+        let mut seen = HashSet::new();
+
+        for index in 0..960 {
+            let fen = start_fenstr(index);
+            let back_rank: Vec<char> = fen.split('/').next().unwrap().chars().collect();
+            let files_of = |piece: char| back_rank.iter().enumerate().filter(|(_, &p)| p == piece).map(|(file, _)| file).collect::<Vec<_>>();
+
+            let (bishops, rooks, king) = (files_of('b'), files_of('r'), files_of('k'));
+            assert_ne!(bishops[0] % 2, bishops[1] % 2, "Bishops on the same colour in {}", fen);
+            assert!(rooks[0] < king[0] && king[0] < rooks[1], "King not between the rooks in {}", fen);
+            assert!(seen.insert(fen));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_index_out_of_range_panics() {
+        start_fenstr(960);
+    }
+}
@@ -1,56 +1,123 @@
+use std::sync::Arc;
+
 use crate::game_classes::board_classes::board::Board;
+use crate::game_classes::board_classes::bit_board::BitBoard;
+use crate::engine::eval_params::EvalParams;
+use crate::engine::evaluator::Evaluator;
+use crate::engine::nnue::{Accumulator, Network};
 use crate::coords::Coords;
 use crate::game_classes::board_classes::magic_bitboard;
 use crate::game_classes::game_state_tracker::GameStateTracker;
 use crate::game_classes::zobrist::Zobrist;
 use crate::moves::move_generator::MoveGenerator;
+use crate::moves::compact_move::Move;
 use crate::piece::Piece;
+use crate::square::Square;
 use crate::enums::{Colour, PieceType, ChessMove, ExecutedMove};
-use crate::game_classes::game_state::{GameState};
+use crate::enums::piece_types::PIECE_TYPES;
+use crate::game_classes::game_state::{CastlingRights, GameState, Pockets};
+use crate::variants::{Standard, Variant};
 
 
 #[derive(Debug)]
 pub enum GameResult {
     Checkmate(Colour),
     Stalemate,
-    Draw
+    Draw,
+    // Lost to a variant's own rule rather than checkmate
+    VariantLoss(Colour),
 }
 
-struct GameStateSnapshot {
-    state: GameState,
+/// The part of the position a move destroys and that can't be recomputed from the move.
+/// The captured piece is kept on the matching `ExecutedMove`.
+#[derive(Clone)]
+struct UndoRecord {
+    castling_rights: CastlingRights,
+    en_passant_target: Option<Coords>,
+    halfmove_clock: u16,
+    checks_given: [u8; 2],
+    pockets: Pockets,
+    promoted: BitBoard,
     hash: u64,
+    pawn_hash: u64,
 }
 
 pub struct Game {
     board: Board,
     game_state: GameState,
     move_history: Vec<ExecutedMove>,
-    history: Vec<GameStateSnapshot>,
-    zobrist: Zobrist,
+    history: Vec<UndoRecord>,
+    // What was already wrong before each move in `history`, for the debug checks
+    #[cfg(debug_assertions)]
+    violation_history: Vec<Vec<String>>,
+    zobrist: Arc<Zobrist>,
     state_tracker: GameStateTracker,
     hash: u64,
+    // Keyed on pawns alone, for caching pawn structure evaluation
+    pawn_hash: u64,
+    // Material plus piece-square (middlegame, endgame) score of each side, indexed by `Colour`
+    material_pst: [(i32, i32); 2],
+    eval_params: Arc<EvalParams>,
+    // Hidden layer inputs of the network, when one is loaded
+    accumulator: Option<Accumulator>,
+    variant: Arc<dyn Variant>,
     // ended: Option<GameResult>,
 }
 
 impl Game {
     pub fn new() -> Self {
+        Self::with_zobrist(Zobrist::shared())
+    }
+
+    /// A game hashed with its own Zobrist keys instead of the shared default set
+    pub fn with_zobrist_seed(seed: u64) -> Self {
+        Self::with_zobrist(Arc::new(Zobrist::with_seed(seed)))
+    }
+
+    fn with_zobrist(zobrist: Arc<Zobrist>) -> Self {
         let mut game = Self {
             board: Board::setup_startposition(),
             game_state: GameState::new(),
             move_history: Vec::new(),
             history: Vec::new(),
-            zobrist: Zobrist::new(),
+            #[cfg(debug_assertions)]
+            violation_history: Vec::new(),
+            zobrist,
             state_tracker: GameStateTracker::new(),
             hash: 0,
+            pawn_hash: 0,
+            material_pst: [(0, 0); 2],
+            eval_params: EvalParams::shared(),
+            accumulator: None,
+            variant: Arc::new(Standard),
             // ended: None,
         };
 
         game.hash_position();
+        game.compute_material_pst();
         game.state_tracker.record_position(game.hash);
 
         game
     }
 
+    /// A game of `variant` from its start position
+    pub fn with_variant(variant: Arc<dyn Variant>) -> Self {
+        let mut game = Self::new();
+        game.set_variant(variant);
+        game
+    }
+
+    /// Switch to `variant` and set up its start position
+    pub fn set_variant(&mut self, variant: Arc<dyn Variant>) {
+        let start_fenstr = variant.start_fenstr();
+        self.variant = variant;
+        self.set_fenstr(start_fenstr);
+    }
+
+    pub fn variant(&self) -> &Arc<dyn Variant> {
+        &self.variant
+    }
+
     pub fn clear_board(&mut self) {
         self.board = Board::new();
     }
@@ -67,8 +134,12 @@ impl Game {
             panic!("Invalid FEN string: expected at least 4 fields, got {}", fenstr_parts.len());
         }
 
-        // 1. Board setup
-        self.board.set_board_from_fenstr(fenstr_parts[0]);
+        // 1. Board setup, with Crazyhouse pieces in hand as `[...]` or a ninth rank and
+        // promoted pieces marked with a `~`
+        let (board_fenstr, pockets_fenstr) = Self::split_pockets_fenstr(fenstr_parts[0]);
+        self.board.set_board_from_fenstr(&board_fenstr.replace('~', ""));
+        self.game_state.set_pockets_from_fenstr(pockets_fenstr);
+        self.game_state.set_promoted(Self::parse_promoted_fenstr(board_fenstr));
 
         // 2. Active colour
         self.game_state.set_turn(match fenstr_parts[1] {
@@ -78,7 +149,7 @@ impl Game {
         });
 
         // 3. Castling rights
-        self.game_state.set_castling_rights_from_fenstr(fenstr_parts[2]);
+        self.game_state.set_castling_rights_from_fenstr(fenstr_parts[2], &self.board);
 
 
         // 4. En passant target
@@ -94,16 +165,72 @@ impl Game {
             self.game_state.set_en_passant_target(None);
         }
 
+        // Three-check counters, as checks remaining (`3+3` before the clocks) or as checks
+        // given (`+0+0` after them)
+        let mut clock_parts = fenstr_parts[4..].to_vec();
+        let checks_given = match clock_parts.iter().position(|part| part.contains('+')) {
+            Some(i) => Self::parse_checks_fenstr(clock_parts.remove(i)),
+            None => [0, 0],
+        };
+        self.game_state.set_checks_given(checks_given);
+
+        // 5-6. Halfmove clock and fullmove number (optional)
+        self.game_state.set_halfmove_clock(clock_parts.first().and_then(|s| s.parse().ok()).unwrap_or(0));
+        self.game_state.set_fullmove_number(clock_parts.get(1).and_then(|s| s.parse().ok()).unwrap_or(1));
+
         self.hash_position();
+        self.compute_material_pst();
+        self.refresh_accumulator();
         self.state_tracker.record_position(self.hash);
+    }
+
+    fn split_pockets_fenstr(board_fenstr: &str) -> (&str, &str) {
+        if let Some((board, pockets)) = board_fenstr.split_once('[') {
+            return (board, pockets.trim_end_matches(']'));
+        }
+        match board_fenstr.rsplit_once('/') {
+            Some((board, pockets)) if board.matches('/').count() == 7 => (board, pockets),
+            _ => (board_fenstr, ""),
+        }
+    }
+
+    // Squares of the pieces followed by a `~`
+    fn parse_promoted_fenstr(board_fenstr: &str) -> BitBoard {
+        let mut promoted = BitBoard::EMPTY;
+        for (rank_index, rank_str) in board_fenstr.split('/').enumerate() {
+            let mut file_index = 0;
+            for ch in rank_str.chars() {
+                match ch {
+                    '~' => promoted |= BitBoard::from_bits(Square::new(((7 - rank_index) * 8 + file_index - 1) as u8).bit()),
+                    '1'..='8' => file_index += ch.to_digit(10).unwrap() as usize,
+                    _ => file_index += 1,
+                }
+            }
+        }
+        promoted
+    }
 
-        // Halfmove clock and fullmove number not yet implemented
-        
+    fn parse_checks_fenstr(checks_fenstr: &str) -> [u8; 2] {
+        let counts: Vec<u8> = checks_fenstr.trim_start_matches('+').split('+')
+            .map(|count| count.parse().ok().filter(|&count| count <= 3))
+            .collect::<Option<_>>()
+            .filter(|counts: &Vec<u8>| counts.len() == 2)
+            .unwrap_or_else(|| panic!("Invalid three-check field in fenstring: {}", checks_fenstr));
+
+        if checks_fenstr.starts_with('+') {
+            [counts[0], counts[1]]
+        } else {
+            [3 - counts[0], 3 - counts[1]]
+        }
     }
 
-    pub fn is_game_over_with_moves(&mut self, moves: &Vec<ChessMove>, magic_bitboard: bool) -> Option<GameResult> {
+    pub fn is_game_over_with_moves(&mut self, moves: &[Move], magic_bitboard: bool) -> Option<GameResult> {
         let player = self.get_game_state().get_turn();
 
+        if let Some(result) = self.variant.game_result(self) {
+            return Some(result);
+        }
+
         if self.state_tracker.is_threefold_repetition(self.hash) {
             return Some(GameResult::Draw);
         }
@@ -112,12 +239,8 @@ impl Game {
             return None;
         }
 
-        if self.is_player_in_check(player, magic_bitboard) {
-            return Some(GameResult::Checkmate(player))
-        }
-        else {
-            return Some(GameResult::Stalemate)
-        }
+        let variant = Arc::clone(&self.variant);
+        Some(variant.no_moves_result(self, player, magic_bitboard))
     }
 
 
@@ -141,113 +264,152 @@ impl Game {
         self.game_state.set_turn(colour);
     }
 
+    /// Read and write castling as king takes rook, as Chess960 GUIs do
+    pub fn set_chess960(&mut self, chess960: bool) {
+        self.game_state.set_chess960(chess960);
+    }
+
     pub fn get_current_hash(&self) -> u64 {
         self.hash
     }
 
+    pub fn get_pawn_hash(&self) -> u64 {
+        self.pawn_hash
+    }
+
+    /// Material plus piece-square score of each side, as kept up to date by make/undo
+    pub fn get_material_pst(&self) -> [(i32, i32); 2] {
+        self.material_pst
+    }
+
+    /// Recompute the material and piece-square scores from the board
+    pub fn compute_material_pst(&mut self) {
+        self.material_pst = Evaluator::material_pst_from_scratch(&self.board, &self.eval_params);
+    }
+
+    pub fn get_eval_params(&self) -> &EvalParams {
+        &self.eval_params
+    }
+
+    /// Evaluate with different parameters from now on, including after undoing moves made
+    /// before the change. Pawn tables filled with the old ones need clearing by their owner.
+    pub fn set_eval_params(&mut self, params: Arc<EvalParams>) {
+        self.eval_params = params;
+        self.compute_material_pst();
+    }
+
+    /// Evaluate with `network` when asked to, `None` drops the accumulator
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.accumulator = network.map(|network| Accumulator::new(network, &self.board));
+    }
+
+    pub fn get_accumulator(&self) -> Option<&Accumulator> {
+        self.accumulator.as_ref()
+    }
+
+    fn refresh_accumulator(&mut self) {
+        if let Some(accumulator) = &self.accumulator {
+            self.accumulator = Some(Accumulator::new(Arc::clone(accumulator.network()), &self.board));
+        }
+    }
+
+    /// Network evaluation from the side to move's point of view, if a network is loaded
+    pub fn nnue_evaluate(&self) -> Option<i32> {
+        let accumulator = self.accumulator.as_ref()?;
+        debug_assert!(
+            *accumulator == Accumulator::new(Arc::clone(accumulator.network()), &self.board),
+            "Incremental accumulator out of step with the board"
+        );
+        Some(accumulator.evaluate(self.game_state.get_turn()))
+    }
+
     pub fn get_player_pieces(&self, player: Colour) -> Vec<(Piece, Coords)> {
         self.board.get_player_pieces(player)
     }
 
     pub fn make_move(&mut self, chess_move: &ChessMove) {
-        self.history.push(GameStateSnapshot { 
-            state: self.game_state.clone(), 
-            hash: self.hash 
+        #[cfg(debug_assertions)]
+        self.violation_history.push(self.move_violations());
+
+        self.history.push(UndoRecord {
+            castling_rights: self.game_state.get_castling_rights(),
+            en_passant_target: self.game_state.get_en_passant_target(),
+            halfmove_clock: self.game_state.get_halfmove_clock(),
+            checks_given: [Colour::White, Colour::Black].map(|colour| self.game_state.get_checks_given(colour)),
+            pockets: self.game_state.get_pockets(),
+            promoted: self.game_state.get_promoted(),
+            hash: self.hash,
+            pawn_hash: self.pawn_hash,
         });
 
         self.game_state.update(chess_move, &mut self.hash, &self.zobrist);
 
-
-        match chess_move {
+        let executed_move = match chess_move {
             ChessMove::Normal(ref mv) => {
-                let piece = Piece { kind: mv.piece_type, colour: mv.colour};
-
-                // Update hash
-
-                // Remove moving piece from original square
-                self.zobrist.toggle_piece(&mut self.hash, &mv.from, &piece);
-                // Remove captured piece if any
-                if let Some(captured) = self.board.get_coords(&mv.to) {
-                    self.zobrist.toggle_piece(&mut self.hash, &mv.to, &captured);
-                }
-                // Add moving piece to destination
-                self.zobrist.toggle_piece(&mut self.hash, &mv.to, &piece);
+                let piece = Piece { kind: mv.piece_type, colour: mv.colour };
+                let (from, to) = (Square::from(mv.from), Square::from(mv.to));
 
+                let captured_piece = self.remove_captured_piece(to);
+                self.move_piece_hashed(piece, from, to);
 
-                // Update Board
-                let executed_move = ExecutedMove::Normal { mv: *mv, captured_piece: self.board.get_coords(&mv.to)};
-                self.move_history.push(executed_move);
-
-                self.board.move_piece(&piece, &mv.from, &mv.to);
-                
+                ExecutedMove::Normal { mv: *mv, captured_piece }
             }
             ChessMove::Castling(ref mv) => {
-                let king = Piece { kind: PieceType::King, colour: mv.colour};
-                let rook = Piece { kind: PieceType::Rook, colour: mv.colour};
-                // Update hash
-
-                // Remove king and rook from original squares
-                self.zobrist.toggle_piece(&mut self.hash, &mv.king_from, &king);
-                self.zobrist.toggle_piece(&mut self.hash, &mv.rook_from, &rook);
-                // Add king and rook to destination squares
-                self.zobrist.toggle_piece(&mut self.hash, &mv.king_to, &king);
-                self.zobrist.toggle_piece(&mut self.hash, &mv.rook_to, &rook);
-
+                let king = Piece { kind: PieceType::King, colour: mv.colour };
+                let rook = Piece { kind: PieceType::Rook, colour: mv.colour };
 
-                // Update Board
-                let executed_move = ExecutedMove::Castling { mv: *mv };
-                self.move_history.push(executed_move);
-                self.board.move_piece(&king, &mv.king_from, &mv.king_to);
-                self.board.move_piece(&rook, &mv.rook_from, &mv.rook_to);
+                // Both come off first, in Chess960 either may land where the other started
+                self.remove_piece_hashed(king, Square::from(mv.king_from));
+                self.remove_piece_hashed(rook, Square::from(mv.rook_from));
+                self.put_piece_hashed(king, Square::from(mv.king_to));
+                self.put_piece_hashed(rook, Square::from(mv.rook_to));
 
+                ExecutedMove::Castling { mv: *mv }
             }
             ChessMove::Promotion(ref mv) => {
                 let pawn = Piece { kind: PieceType::Pawn, colour: mv.colour };
-                let promotion_piece = Piece {kind: mv.promotion_piece_type, colour: mv.colour};
-
-                // Update hash
-                // Remove pawn from original square
-                self.zobrist.toggle_piece(&mut self.hash, &mv.from, &pawn);
-                // Remove captured piece if any
-                if let Some(captured) = self.board.get_coords(&mv.to) {
-                    self.zobrist.toggle_piece(&mut self.hash, &mv.to, &captured);
-                }
-                // Add promoted piece to destination
-                self.zobrist.toggle_piece(&mut self.hash, &mv.to, &promotion_piece);
+                let promotion_piece = Piece { kind: mv.promotion_piece_type, colour: mv.colour };
+                let (from, to) = (Square::from(mv.from), Square::from(mv.to));
 
-                // Update board
-                let executed_move = ExecutedMove::Promotion { mv: *mv , captured_piece: self.board.get_coords(&mv.to)};
-                self.move_history.push(executed_move);
-
-                self.board.set_coords(&mv.from, None);
-                self.board.set_coords(&mv.to, Some(promotion_piece));
+                let captured_piece = self.remove_captured_piece(to);
+                self.remove_piece_hashed(pawn, from);
+                self.put_piece_hashed(promotion_piece, to);
 
+                ExecutedMove::Promotion { mv: *mv, captured_piece }
             }
             ChessMove::EnPassant(ref mv) => {
                 let pawn = Piece { kind: PieceType::Pawn, colour: mv.colour };
                 let captured = Piece { kind: PieceType::Pawn, colour: mv.colour.other() };
 
-                // update hash
-
-                // Remove moving pawn
-                self.zobrist.toggle_piece(&mut self.hash, &mv.from, &pawn);
-                // Remove captured pawn
-                self.zobrist.toggle_piece(&mut self.hash, &mv.captured_coords, &captured);
-                // Add moving pawn to destination
-                self.zobrist.toggle_piece(&mut self.hash, &mv.to, &pawn);
+                self.remove_piece_hashed(captured, Square::from(mv.captured_coords));
+                self.move_piece_hashed(pawn, Square::from(mv.from), Square::from(mv.to));
 
+                ExecutedMove::EnPassant { mv: *mv }
+            }
+            ChessMove::Drop(ref mv) => {
+                let piece = Piece { kind: mv.piece_type, colour: mv.colour };
+                self.game_state.take_from_pocket(mv.colour, mv.piece_type, &mut self.hash, &self.zobrist);
+                self.put_piece_hashed(piece, Square::from(mv.to));
 
-                // Update board
-                let executed_move = ExecutedMove::EnPassant { mv:*mv };
-                self.move_history.push(executed_move);
-
-                self.board.move_piece(&pawn, &mv.from, &mv.to);
-                self.board.set_coords(&mv.captured_coords, None);
-
+                ExecutedMove::Drop { mv: *mv }
             }
-        }
+        };
+        let variant = Arc::clone(&self.variant);
+        let executed_move = variant.after_move(self, chess_move, executed_move);
+
+        let irreversible = chess_move.piece() == PieceType::Pawn || executed_move.is_capture();
+        self.game_state.advance_clocks(irreversible);
+        self.move_history.push(executed_move);
 
         self.state_tracker.record_position(self.hash);
+        #[cfg(debug_assertions)]
+        self.assert_no_new_violations(self.violation_history.last().unwrap(), "make_move");
+    }
+
+    /// Make a packed move by expanding it against the current board
+    pub fn make_compact_move(&mut self, mv: Move) {
+        let chess_move = mv.to_chess_move(self);
+        self.make_move(&chess_move);
     }
 
     pub fn undo_last_move(&mut self) {
@@ -255,41 +417,154 @@ impl Game {
             panic!("No move to undo.");
         }
 
-        if let Some(snapshot) = self.history.pop() {
-            self.state_tracker.unrecord_position(self.hash);
-
-            self.game_state = snapshot.state;
-            self.hash = snapshot.hash;
-        }
+        let record = self.history.pop().unwrap();
+        self.state_tracker.unrecord_position(self.hash);
+        self.game_state.restore(record.castling_rights, record.en_passant_target, record.halfmove_clock);
+        self.game_state.set_checks_given(record.checks_given);
+        self.game_state.set_pockets(record.pockets);
+        self.game_state.set_promoted(record.promoted);
+        self.hash = record.hash;
+        self.pawn_hash = record.pawn_hash;
 
         let executed_move = self.move_history.pop().unwrap();
+        let variant = Arc::clone(&self.variant);
+        let executed_move = variant.undo_move(self, executed_move);
+        self.undo_executed_move(executed_move);
+        #[cfg(debug_assertions)]
+        {
+            let violations_before = self.violation_history.pop().unwrap();
+            self.assert_no_new_violations(&violations_before, "undo_last_move");
+        }
+    }
 
+    // Board side of undoing a move, the game state and hashes are restored from the record
+    fn undo_executed_move(&mut self, executed_move: ExecutedMove) {
         match executed_move {
-            ExecutedMove::Normal {mv, captured_piece} => {
-                let piece = Piece { kind: mv.piece_type, colour: mv.colour};
-
-                self.board.move_piece(&piece, &mv.to, &mv.from);
-                self.board.set_coords(&mv.to, captured_piece);
+            ExecutedMove::Normal { mv, captured_piece } => {
+                let piece = Piece { kind: mv.piece_type, colour: mv.colour };
+                let (from, to) = (Square::from(mv.from), Square::from(mv.to));
+
+                self.remove_piece_scored(to);
+                self.put_piece_scored(from, piece);
+                if let Some(captured) = captured_piece {
+                    self.put_piece_scored(to, captured);
+                }
             }
-            ExecutedMove::Castling {mv} => {
+            ExecutedMove::Castling { mv } => {
                 let king = Piece { kind: PieceType::King, colour: mv.colour };
                 let rook = Piece { kind: PieceType::Rook, colour: mv.colour };
-                self.board.move_piece(&king, &mv.king_to, &mv.king_from);
-                self.board.move_piece(&rook, &mv.rook_to, &mv.rook_from);
+
+                self.remove_piece_scored(Square::from(mv.king_to));
+                self.remove_piece_scored(Square::from(mv.rook_to));
+                self.put_piece_scored(Square::from(mv.king_from), king);
+                self.put_piece_scored(Square::from(mv.rook_from), rook);
             }
-            ExecutedMove::Promotion {mv, captured_piece} => {
+            ExecutedMove::Promotion { mv, captured_piece } => {
                 let pawn = Piece { kind: PieceType::Pawn, colour: mv.colour };
+                let to = Square::from(mv.to);
 
-                self.board.set_coords(&mv.to, captured_piece);
-                self.board.set_coords(&mv.from, Some(pawn));
+                self.remove_piece_scored(to);
+                self.put_piece_scored(Square::from(mv.from), pawn);
+                if let Some(captured) = captured_piece {
+                    self.put_piece_scored(to, captured);
+                }
             }
-            ExecutedMove::EnPassant {mv} => {
+            ExecutedMove::EnPassant { mv } => {
                 let pawn = Piece { kind: PieceType::Pawn, colour: mv.colour };
-                self.board.move_piece(&pawn, &mv.to, &mv.from);
-
                 let captured_pawn = Piece { kind: PieceType::Pawn, colour: mv.colour.other() };
-                self.board.set_coords(&mv.captured_coords, Some(captured_pawn));
+
+                self.remove_piece_scored(Square::from(mv.to));
+                self.put_piece_scored(Square::from(mv.from), pawn);
+                self.put_piece_scored(Square::from(mv.captured_coords), captured_pawn);
             }
+            ExecutedMove::Drop { mv } => {
+                self.remove_piece_scored(Square::from(mv.to));
+            }
+            ExecutedMove::Explosion { .. } => unreachable!("Explosions are taken back by the variant that made them"),
+        }
+    }
+
+    // Edits for the variant move hooks
+
+    /// Take whatever is on `square` off the board while making a move, keeping the hashes and scores in step
+    pub(crate) fn remove_piece_at(&mut self, square: Square) -> Option<Piece> {
+        self.remove_captured_piece(square)
+    }
+
+    /// Put `piece` back on `square` while undoing a move. The hashes come back from the undo record.
+    pub(crate) fn restore_piece_at(&mut self, square: Square, piece: Piece) {
+        self.put_piece_scored(square, piece);
+    }
+
+    /// The game state along with the hash and keys its hashed setters update
+    pub(crate) fn game_state_with_hash(&mut self) -> (&mut GameState, &mut u64, &Zobrist) {
+        (&mut self.game_state, &mut self.hash, &self.zobrist)
+    }
+
+    // Board edits that also keep the Zobrist hashes and material/PST scores in step
+
+    fn move_piece_hashed(&mut self, piece: Piece, from: Square, to: Square) {
+        self.remove_piece_hashed(piece, from);
+        self.put_piece_hashed(piece, to);
+    }
+
+    fn put_piece_hashed(&mut self, piece: Piece, square: Square) {
+        self.board.put_piece(square, piece);
+        self.toggle_piece_hashes(piece, square);
+        self.update_incremental_eval(piece, square, 1);
+    }
+
+    fn remove_piece_hashed(&mut self, piece: Piece, square: Square) {
+        match self.board.remove_piece(square) {
+            Some(removed) if removed == piece => {}
+            removed => panic!("Expected {:?} on {}, found {:?}", piece, square, removed),
+        }
+        self.toggle_piece_hashes(piece, square);
+        self.update_incremental_eval(piece, square, -1);
+    }
+
+    /// Remove whatever is on the square a move lands on
+    fn remove_captured_piece(&mut self, square: Square) -> Option<Piece> {
+        let captured = self.board.remove_piece(square);
+        if let Some(piece) = captured {
+            self.toggle_piece_hashes(piece, square);
+            self.update_incremental_eval(piece, square, -1);
+        }
+        captured
+    }
+
+    // Undo restores the hashes from its record, but the scores are updated as pieces go back,
+    // so they stay right if the evaluation parameters changed since the move was made
+
+    fn put_piece_scored(&mut self, square: Square, piece: Piece) {
+        self.board.put_piece(square, piece);
+        self.update_incremental_eval(piece, square, 1);
+    }
+
+    fn remove_piece_scored(&mut self, square: Square) {
+        if let Some(piece) = self.board.remove_piece(square) {
+            self.update_incremental_eval(piece, square, -1);
+        }
+    }
+
+    fn update_incremental_eval(&mut self, piece: Piece, square: Square, sign: i32) {
+        self.update_material_pst(piece, square, sign);
+        if let Some(accumulator) = &mut self.accumulator {
+            accumulator.update(piece, square.index(), sign);
+        }
+    }
+
+    fn update_material_pst(&mut self, piece: Piece, square: Square, sign: i32) {
+        let (mg, eg) = Evaluator::evaluate_piece(&self.eval_params, piece, Coords::from(square));
+        let score = &mut self.material_pst[piece.colour as usize];
+        score.0 += sign * mg;
+        score.1 += sign * eg;
+    }
+
+    fn toggle_piece_hashes(&mut self, piece: Piece, square: Square) {
+        self.zobrist.toggle_piece(&mut self.hash, square, &piece);
+        if piece.kind == PieceType::Pawn {
+            self.zobrist.toggle_piece(&mut self.pawn_hash, square, &piece);
         }
     }
 
@@ -301,6 +576,10 @@ impl Game {
         let player_king = Piece {kind: PieceType::King, colour: player };
         let player_king_coords = self.board.get_piece_coords(player_king);
 
+        // Variants such as atomic and antichess play on without a king
+        if player_king_coords.is_empty() {
+            return false;
+        }
         if player_king_coords.len() != 1 {
             panic!("Multiple king coords found: {:?}", player_king_coords);
         }
@@ -310,6 +589,38 @@ impl Game {
         MoveGenerator::is_square_under_attack(self, &player.other(), &king_coords, magic_bitboard)
     }
 
+    fn king_square(&self, colour: Colour) -> Option<Square> {
+        let king = self.board.get_piece_bit_board(Piece { kind: PieceType::King, colour });
+        match king.num_set_bits() {
+            1 => king.lsb().map(Square::from_index),
+            _ => None,
+        }
+    }
+
+    /// `colour`'s pieces that attack `square`
+    pub fn attackers_of(&self, square: Square, colour: Colour) -> BitBoard {
+        BitBoard::from_bits(MoveGenerator::attackers_to(self, square, colour, self.board.all_occ().bits()))
+    }
+
+    /// `colour`'s pieces pinned to their king, none without exactly one king
+    pub fn pinned_pieces(&self, colour: Colour) -> BitBoard {
+        let pinned = self.king_square(colour).map_or(0, |king_sq| MoveGenerator::pinned_pieces(self, king_sq, colour));
+        BitBoard::from_bits(pinned)
+    }
+
+    /// The pieces giving check to the side to move
+    pub fn checkers(&self) -> BitBoard {
+        let turn = self.game_state.get_turn();
+        self.king_square(turn).map_or(BitBoard::EMPTY, |king_sq| self.attackers_of(king_sq, turn.other()))
+    }
+
+    /// The squares the piece on `square` attacks, own pieces included, empty if there is no piece
+    pub fn attacks_from(&self, square: Square) -> BitBoard {
+        let attacks = self.board.piece_at(square)
+            .map_or(0, |piece| MoveGenerator::attacks_from(piece, square, self.board.all_occ().bits()));
+        BitBoard::from_bits(attacks)
+    }
+
     pub fn is_check(&mut self, chess_move: &ChessMove, magic_bitboard: bool) -> bool {
         self.make_move(chess_move);
 
@@ -322,33 +633,140 @@ impl Game {
     }
 
     pub fn hash_position(&mut self) {
-        self.hash = 0u64;
+        (self.hash, self.pawn_hash) = self.hashes_from_scratch();
+    }
 
-        for (piece, coords) in self.board.get_all_pieces() {
-            self.zobrist.toggle_piece(&mut self.hash, &coords, &piece)
+    /// Full and pawn hashes of the position, computed without the incremental updates
+    fn hashes_from_scratch(&self) -> (u64, u64) {
+        let mut hash = 0u64;
+        let mut pawn_hash = 0u64;
+
+        for (piece, square) in self.board.pieces() {
+            self.zobrist.toggle_piece(&mut hash, square, &piece);
+            if piece.kind == PieceType::Pawn {
+                self.zobrist.toggle_piece(&mut pawn_hash, square, &piece);
+            }
         }
 
         // Add castling rights
-        self.zobrist.toggle_castle(&mut self.hash, &self.game_state.get_castling_rights());
+        self.zobrist.toggle_castle(&mut hash, &self.game_state.get_castling_rights());
 
         // En passant
         if let Some(coords) = self.game_state.get_en_passant_piece_coords() {
-            self.zobrist.toggle_en_passant(&mut self.hash, &coords.file);
+            self.zobrist.toggle_en_passant(&mut hash, &coords.file);
         }
 
         // Side to move
         if self.game_state.get_turn() == Colour::Black {
-            self.zobrist.toggle_side_to_move(&mut self.hash);
+            self.zobrist.toggle_side_to_move(&mut hash);
+        }
+
+        // Three-check counters
+        for colour in [Colour::White, Colour::Black] {
+            self.zobrist.toggle_checks(&mut hash, colour, self.game_state.get_checks_given(colour));
+        }
+
+        // Crazyhouse pieces in hand
+        for colour in [Colour::White, Colour::Black] {
+            for kind in &PIECE_TYPES[..5] {
+                self.zobrist.toggle_pocket(&mut hash, colour, *kind, self.game_state.pocket_count(colour, *kind));
+            }
         }
+
+        (hash, pawn_hash)
     }
-}
 
+    /// Everything wrong with the position and the state kept alongside it: the board's own
+    /// violations, castling rights without their king and rook, a bad en passant target, stale
+    /// hashes or scores, and the side not to move being in check
+    pub fn validate(&self) -> Vec<String> {
+        let mut violations = self.board.validate();
+        violations.extend(self.state_violations());
+
+        let waiting = self.game_state.get_turn().other();
+        if let Some(king_sq) = self.king_square(waiting) {
+            if !self.attackers_of(king_sq, waiting.other()).is_empty() {
+                violations.push(format!("{:?} is in check but it is {:?}'s turn", waiting, waiting.other()));
+            }
+        }
+
+        violations
+    }
+
+    // What making and undoing moves has to keep right. Missing kings and checks are left to
+    // `validate`, test positions are allowed those and search makes moves that leave the king
+    // in check before rejecting them.
+    fn state_violations(&self) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        let rights = self.game_state.get_castling_rights();
+        for right in CastlingRights::SINGLE {
+            let colour = right.colour();
+            let king_sq = Square::from(self.game_state.castling_king_coords(colour));
+            let rook_sq = Square::from(self.game_state.castling_rook_coords(right));
+            let in_place = self.board.piece_at(king_sq) == Some(Piece { kind: PieceType::King, colour })
+                && self.board.piece_at(rook_sq) == Some(Piece { kind: PieceType::Rook, colour });
+            if rights.contains(right) && !in_place {
+                violations.push(format!("{:?} castling right without a king on {} and rook on {}", right, king_sq, rook_sq));
+            }
+        }
+
+        if let (Some(target), Some(captured)) = (self.game_state.get_en_passant_target(), self.game_state.get_en_passant_piece_coords()) {
+            let mover = self.game_state.get_turn();
+            let expected_rank = if mover == Colour::White { 6 } else { 3 };
+            if target.rank != expected_rank {
+                violations.push(format!("En passant target {} with {:?} to move", target, mover));
+            } else if self.board.get_coords(&target).is_some() {
+                violations.push(format!("En passant target {} is occupied", target));
+            } else if self.board.get_coords(&captured) != Some(Piece { kind: PieceType::Pawn, colour: mover.other() }) {
+                violations.push(format!("No pawn to capture en passant on {}", captured));
+            }
+        }
+
+        for square in self.game_state.get_promoted().squares().map(Square::from_index) {
+            if !matches!(self.board.piece_at(square), Some(piece) if piece.kind != PieceType::Pawn && piece.kind != PieceType::King) {
+                violations.push(format!("Promoted piece marked on {} where there is none", square));
+            }
+        }
+
+        if (self.hash, self.pawn_hash) != self.hashes_from_scratch() {
+            violations.push("Zobrist hashes out of date".to_string());
+        }
+        if self.material_pst != Evaluator::material_pst_from_scratch(&self.board, &self.eval_params) {
+            violations.push("Material and piece-square scores out of date".to_string());
+        }
+        if let Some(accumulator) = &self.accumulator {
+            if *accumulator != Accumulator::new(Arc::clone(accumulator.network()), &self.board) {
+                violations.push("NNUE accumulator out of date".to_string());
+            }
+        }
+
+        violations
+    }
+
+    // Checked around every move in debug builds
+    #[cfg(debug_assertions)]
+    fn move_violations(&self) -> Vec<String> {
+        let mut violations = self.board.structure_violations();
+        violations.extend(self.state_violations());
+        violations
+    }
+
+    // Positions set up by hand may already be off (stale hashes, castling rights without
+    // rooks), so only what the move itself broke is reported
+    #[cfg(debug_assertions)]
+    fn assert_no_new_violations(&self, before: &[String], after: &str) {
+        let new: Vec<String> = self.move_violations().into_iter().filter(|violation| !before.contains(violation)).collect();
+        assert!(new.is_empty(), "Invalid state after {}: {}", after, new.join("; "));
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::enums::moves::{NormalMove, CastlingMove, PromotionMove, EnPassantMove};
     use crate::enums::{Colour, PieceType, File, ChessMove};
+    use crate::moves::move_parser::MoveParser;
 
     // Helper to create a normal move
     fn make_normal_move(colour: Colour, piece: PieceType, from: Coords, to: Coords) -> ChessMove {
@@ -1012,6 +1430,94 @@ mod tests {
         assert_eq!(game.hash, hash_after_moves, "Recalculating hash from scratch should result in the same value.");
     }
 
+    #[test]
+    fn test_material_pst_kept_in_step() {
+        // Captures, promotions with capture, en passant and castling are all available
+        let mut game = Game::new();
+        game.set_fenstr("r3k2r/1P6/8/3pP3/8/8/6p1/R3K2R w KQkq d6 0 1");
+        let initial = game.material_pst;
+
+        let mut moves = Vec::new();
+        MoveGenerator::generate_legal_moves_into(&mut game, Colour::White, false, &mut moves);
+        for mv in moves {
+            game.make_compact_move(mv);
+            assert_eq!(game.material_pst, Evaluator::material_pst_from_scratch(&game.board, &game.eval_params), "After {}", mv);
+
+            let mut replies = Vec::new();
+            MoveGenerator::generate_legal_moves_into(&mut game, Colour::Black, false, &mut replies);
+            for reply in replies {
+                game.make_compact_move(reply);
+                assert_eq!(game.material_pst, Evaluator::material_pst_from_scratch(&game.board, &game.eval_params), "After {} {}", mv, reply);
+                game.undo_last_move();
+            }
+
+            game.undo_last_move();
+            assert_eq!(game.material_pst, initial);
+        }
+    }
+
+    #[test]
+    fn test_material_pst_follows_new_eval_params() {
+        let mut game = Game::new();
+        game.set_fenstr("r3k2r/1P6/8/3pP3/8/8/6p1/R3K2R w KQkq d6 0 1");
+        for mv in ["e5d6", "g2g1q", "b7a8n"] {
+            game.make_move(&MoveParser::parse_str(mv, &game).unwrap());
+        }
+
+        let mut params = EvalParams { piece_values: [90, 300, 310, 480, 950, 0], ..Default::default() };
+        params.mg_pst[0][2][3] = 77;
+        game.set_eval_params(Arc::new(params));
+        assert_eq!(game.material_pst, Evaluator::material_pst_from_scratch(&game.board, &game.eval_params));
+
+        // Undoing moves made under the old parameters scores with the new ones
+        for _ in 0..3 {
+            game.undo_last_move();
+            assert_eq!(game.material_pst, Evaluator::material_pst_from_scratch(&game.board, &game.eval_params));
+        }
+    }
+
+    #[test]
+    fn test_pawn_hash_tracks_pawns_only() {
+        let mut game = Game::new();
+        let initial_pawn_hash = game.pawn_hash;
+
+        // Knight moves leave the pawn hash alone
+        let knight_move = ChessMove::Normal(NormalMove {
+            colour: Colour::White,
+            piece_type: PieceType::Knight,
+            from: Coords::new(1, File::G),
+            to: Coords::new(3, File::F),
+        });
+        game.make_move(&knight_move);
+        assert_eq!(game.pawn_hash, initial_pawn_hash);
+
+        let pawn_move = ChessMove::Normal(NormalMove {
+            colour: Colour::Black,
+            piece_type: PieceType::Pawn,
+            from: Coords::new(7, File::E),
+            to: Coords::new(5, File::E),
+        });
+        game.make_move(&pawn_move);
+        assert_ne!(game.pawn_hash, initial_pawn_hash);
+
+        // Capturing a pawn with a piece changes it too
+        let capture = ChessMove::Normal(NormalMove {
+            colour: Colour::White,
+            piece_type: PieceType::Knight,
+            from: Coords::new(3, File::F),
+            to: Coords::new(5, File::E),
+        });
+        game.make_move(&capture);
+        let pawn_hash_after_moves = game.pawn_hash;
+        game.hash_position();
+        assert_eq!(game.pawn_hash, pawn_hash_after_moves, "Recalculating the pawn hash from scratch should match");
+
+        game.undo_last_move();
+        game.undo_last_move();
+        game.undo_last_move();
+        assert_eq!(game.pawn_hash, initial_pawn_hash, "Undoing moves should restore the pawn hash");
+    }
+
     #[test]
     fn test_threefold_repetition_draw() {
         let mut game = Game::new();
@@ -1145,5 +1651,83 @@ mod tests {
         let result = game.is_game_over_with_moves(&moves, true);
         assert!(result.is_none(), "Draw should not trigger before third repetition");
     }
-}
 
+    #[test]
+    fn test_validate_accepts_legal_positions() {
+        let mut game = Game::new();
+        assert!(game.validate().is_empty());
+
+        game.set_fenstr("r3k2r/1P6/8/3pP3/8/8/6p1/R3K2R w KQkq d6 0 1");
+        assert_eq!(game.validate(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_validate_reports_game_state_violations() {
+        let mut game = Game::new();
+        // White may castle kingside without a rook on h1, black is in check with white to
+        // move, and the en passant target has no pawn behind it
+        game.set_fenstr("4k3/8/8/3P4/8/8/8/4K2R w Kk e6 0 1");
+        game.board.set_coords(&Coords::new(1, File::H), None);
+        game.board.set_coords(&Coords::new(8, File::D), Some(Piece { kind: PieceType::Rook, colour: Colour::White }));
+
+        let violations = game.validate();
+        assert!(violations.contains(&"CastlingRights(WHITE_KINGSIDE) castling right without a king on e1 and rook on h1".to_string()));
+        assert!(violations.contains(&"CastlingRights(BLACK_KINGSIDE) castling right without a king on e8 and rook on h8".to_string()));
+        assert!(violations.contains(&"No pawn to capture en passant on e5".to_string()));
+        assert!(violations.contains(&"Black is in check but it is White's turn".to_string()));
+        // The board was changed behind the game's back
+        assert!(violations.contains(&"Zobrist hashes out of date".to_string()));
+        assert!(violations.contains(&"Material and piece-square scores out of date".to_string()));
+    }
+
+    fn bitboard_of(squares: &[Square]) -> BitBoard {
+        squares.iter().fold(BitBoard::EMPTY, |bits, square| bits | BitBoard::from_square(square.index()))
+    }
+
+    #[test]
+    fn test_chess960_castling_notation() {
+        // Standard chess reads the king moving two files and writes it back the same way
+        let mut game = Game::new();
+        game.set_fenstr("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        let castling = MoveParser::parse_str("e1g1", &game).unwrap();
+        assert!(matches!(castling, ChessMove::Castling(mv) if mv.rook_from == Coords::new(1, File::H)));
+        assert_eq!(castling.to_uci(false), "e1g1");
+        assert_eq!(castling.to_uci(true), "e1h1");
+
+        // Chess960 writes castling as king takes rook, and there a two-file king move is just a king move
+        game.set_chess960(true);
+        game.set_fenstr("1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1");
+        let castling = MoveParser::parse_str("e1b1", &game).unwrap();
+        assert!(matches!(castling, ChessMove::Castling(mv) if mv.king_to == Coords::new(1, File::C) && mv.rook_to == Coords::new(1, File::D)));
+        assert_eq!(castling.to_uci(true), "e1b1");
+        assert!(matches!(MoveParser::parse_str("e1c1", &game), Some(ChessMove::Normal(_))));
+
+        game.make_move(&castling);
+        assert!(game.validate().is_empty(), "{:?}", game.validate());
+        assert_eq!(game.get_board().get_coords(&Coords::new(1, File::C)), Some(Piece { kind: PieceType::King, colour: Colour::White }));
+        assert_eq!(game.get_board().get_coords(&Coords::new(1, File::D)), Some(Piece { kind: PieceType::Rook, colour: Colour::White }));
+    }
+
+    #[test]
+    fn test_attack_queries() {
+        let mut game = Game::new();
+        // The black rook pins the bishop on e2, which the black bishop also attacks
+        game.set_fenstr("4k3/8/8/1b2r3/8/2N5/4B3/4K3 w - - 0 1");
+
+        assert_eq!(game.attackers_of(Square::E2, Colour::Black), bitboard_of(&[Square::B5, Square::E5]));
+        assert_eq!(game.attackers_of(Square::E2, Colour::White), bitboard_of(&[Square::E1, Square::C3]));
+        assert_eq!(game.pinned_pieces(Colour::White), bitboard_of(&[Square::E2]));
+        assert_eq!(game.pinned_pieces(Colour::Black), BitBoard::EMPTY);
+        assert_eq!(game.checkers(), BitBoard::EMPTY);
+
+        let rook_attacks = [Square::E6, Square::E7, Square::E8, Square::E4, Square::E3, Square::E2, Square::D5, Square::C5, Square::B5, Square::F5, Square::G5, Square::H5];
+        assert_eq!(game.attacks_from(Square::E5), bitboard_of(&rook_attacks));
+        assert_eq!(game.attacks_from(Square::C3).num_set_bits(), 8);
+        assert_eq!(game.attacks_from(Square::A1), BitBoard::EMPTY);
+
+        // Moving the bishop away leaves the king in check from the rook
+        game.set_fenstr("4k3/8/8/1b2r3/8/2N2B2/8/4K3 w - - 0 1");
+        assert_eq!(game.checkers(), bitboard_of(&[Square::E5]));
+        assert_eq!(game.pinned_pieces(Colour::White), BitBoard::EMPTY);
+    }
+}
@@ -1,6 +1,12 @@
+use strum::IntoEnumIterator;
+
+use crate::enums::moves::CastlingMove;
 use crate::enums::{ChessMove, Colour, File, PieceType};
 use crate::coords::Coords;
-use crate::game_classes::zobrist::Zobrist;
+use crate::game_classes::board_classes::bit_board::BitBoard;
+use crate::game_classes::board_classes::board::Board;
+use crate::game_classes::zobrist::{Zobrist, MAX_POCKET_COUNT};
+use crate::piece::Piece;
 
 bitflags::bitflags! {
     #[derive(Debug, Clone, Copy)]
@@ -12,12 +18,73 @@ bitflags::bitflags! {
     }
 }
 
+impl CastlingRights {
+    pub const SINGLE: [CastlingRights; 4] = [
+        CastlingRights::WHITE_KINGSIDE,
+        CastlingRights::WHITE_QUEENSIDE,
+        CastlingRights::BLACK_KINGSIDE,
+        CastlingRights::BLACK_QUEENSIDE,
+    ];
+
+    /// The kingside or queenside right of `colour`
+    pub fn side(colour: Colour, kingside: bool) -> Self {
+        match (colour, kingside) {
+            (Colour::White, true) => CastlingRights::WHITE_KINGSIDE,
+            (Colour::White, false) => CastlingRights::WHITE_QUEENSIDE,
+            (Colour::Black, true) => CastlingRights::BLACK_KINGSIDE,
+            (Colour::Black, false) => CastlingRights::BLACK_QUEENSIDE,
+        }
+    }
+
+    /// Colour of a single right
+    pub fn colour(self) -> Colour {
+        if self.intersects(CastlingRights::WHITE_KINGSIDE | CastlingRights::WHITE_QUEENSIDE) {
+            Colour::White
+        } else {
+            Colour::Black
+        }
+    }
+
+    pub fn is_kingside(self) -> bool {
+        self.intersects(CastlingRights::WHITE_KINGSIDE | CastlingRights::BLACK_KINGSIDE)
+    }
+
+    // Position of a single right in `SINGLE`
+    fn index(self) -> usize {
+        self.bits().trailing_zeros() as usize
+    }
+}
+
+/// Pieces in hand for Crazyhouse drops, indexed by `Colour` and `PieceType`, kings aside
+pub type Pockets = [[u8; 5]; 2];
+
+fn back_rank(colour: Colour) -> u8 {
+    match colour {
+        Colour::White => 1,
+        Colour::Black => 8,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct GameState {
     turn: Colour,
     castling_rights: CastlingRights,
     en_passant_target: Option<Coords>,
-    en_passant_piece_coords: Option<Coords>
+    en_passant_piece_coords: Option<Coords>,
+    halfmove_clock: u16,
+    fullmove_number: u16,
+    // Start files of each side's king and of the rook behind each right, indexed by `Colour`
+    // and `CastlingRights::SINGLE`. Only Chess960 moves them off e, h and a.
+    castling_king_files: [File; 2],
+    castling_rook_files: [File; 4],
+    // Castling is written as king takes rook
+    chess960: bool,
+    // Checks each side has given, indexed by `Colour`. Only counted in three-check.
+    checks_given: [u8; 2],
+    // Only filled in Crazyhouse
+    pockets: Pockets,
+    // Pieces that were promoted from pawns, which go back into a pocket as pawns when taken
+    promoted: BitBoard,
 }
 
 impl GameState {
@@ -26,25 +93,166 @@ impl GameState {
             turn: Colour::White,
             castling_rights: CastlingRights::all(),
             en_passant_target: None,
-            en_passant_piece_coords: None
+            en_passant_piece_coords: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            castling_king_files: [File::E; 2],
+            castling_rook_files: [File::H, File::A, File::H, File::A],
+            chess960: false,
+            checks_given: [0; 2],
+            pockets: [[0; 5]; 2],
+            promoted: BitBoard::EMPTY,
         }
     }
 
-    pub fn set_castling_rights_from_fenstr(&mut self, castling_rights_fenstr: &str) {
+    /// Read the castling field of a FEN, as `KQkq`, X-FEN or Shredder-FEN. `K`/`Q` name the
+    /// outermost rook on that side of the king, a file letter names the rook itself.
+    pub fn set_castling_rights_from_fenstr(&mut self, castling_rights_fenstr: &str, board: &Board) {
         self.castling_rights = CastlingRights::empty();
+        self.castling_king_files = [File::E; 2];
+        self.castling_rook_files = [File::H, File::A, File::H, File::A];
 
         if castling_rights_fenstr == "-" {
             return;
         }
 
         for ch in castling_rights_fenstr.chars() {
-            match ch {
-                'K' => self.castling_rights.insert(CastlingRights::WHITE_KINGSIDE),
-                'Q' => self.castling_rights.insert(CastlingRights::WHITE_QUEENSIDE),
-                'k' => self.castling_rights.insert(CastlingRights::BLACK_KINGSIDE),
-                'q' => self.castling_rights.insert(CastlingRights::BLACK_QUEENSIDE),
+            let colour = if ch.is_ascii_uppercase() { Colour::White } else { Colour::Black };
+            let rank = back_rank(colour);
+            let king = Some(Piece { kind: PieceType::King, colour });
+            let rook = Some(Piece { kind: PieceType::Rook, colour });
+            let holds = |file: &File, piece: Option<Piece>| board.get_coords(&Coords::new(rank, *file)) == piece;
+
+            // Without a king on the back rank the right can't be used anyway, assume e
+            let king_file = File::iter().find(|file| holds(file, king)).unwrap_or(File::E);
+
+            let (right, rook_file) = match ch.to_ascii_lowercase() {
+                'k' => {
+                    let outermost = File::iter().rev().take_while(|file| *file - king_file > 0).find(|file| holds(file, rook));
+                    (CastlingRights::side(colour, true), outermost.unwrap_or(File::H))
+                }
+                'q' => {
+                    let outermost = File::iter().take_while(|file| *file - king_file < 0).find(|file| holds(file, rook));
+                    (CastlingRights::side(colour, false), outermost.unwrap_or(File::A))
+                }
+                'a'..='h' => {
+                    let file = File::from_char(ch).unwrap();
+                    (CastlingRights::side(colour, file - king_file > 0), file)
+                }
                 _ => panic!("Invalid castling rights character in FEN: {}", ch),
+            };
+
+            self.castling_rights.insert(right);
+            self.castling_king_files[colour as usize] = king_file;
+            self.castling_rook_files[right.index()] = rook_file;
+        }
+    }
+
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    pub fn set_chess960(&mut self, chess960: bool) {
+        self.chess960 = chess960;
+    }
+
+    pub fn get_checks_given(&self, colour: Colour) -> u8 {
+        self.checks_given[colour as usize]
+    }
+
+    pub fn set_checks_given(&mut self, checks_given: [u8; 2]) {
+        self.checks_given = checks_given;
+    }
+
+    /// Count a check given by `colour`, up to the third that ends a game of three-check
+    pub fn add_check(&mut self, colour: Colour, hash: &mut u64, zobrist: &Zobrist) {
+        let count = &mut self.checks_given[colour as usize];
+        if *count >= 3 {
+            return;
+        }
+        zobrist.toggle_checks(hash, colour, *count);
+        *count += 1;
+        zobrist.toggle_checks(hash, colour, *count);
+    }
+
+    pub fn get_pockets(&self) -> Pockets {
+        self.pockets
+    }
+
+    pub fn set_pockets(&mut self, pockets: Pockets) {
+        self.pockets = pockets;
+    }
+
+    pub fn pocket_count(&self, colour: Colour, kind: PieceType) -> u8 {
+        self.pockets[colour as usize][kind as usize]
+    }
+
+    /// Put a piece captured by `colour` into their pocket
+    pub fn add_to_pocket(&mut self, colour: Colour, kind: PieceType, hash: &mut u64, zobrist: &Zobrist) {
+        let count = &mut self.pockets[colour as usize][kind as usize];
+        zobrist.toggle_pocket(hash, colour, kind, *count);
+        *count += 1;
+        zobrist.toggle_pocket(hash, colour, kind, *count);
+    }
+
+    /// Take a piece out of `colour`'s pocket to drop it. Panics if there is none.
+    pub fn take_from_pocket(&mut self, colour: Colour, kind: PieceType, hash: &mut u64, zobrist: &Zobrist) {
+        let count = &mut self.pockets[colour as usize][kind as usize];
+        if *count == 0 {
+            panic!("No {:?} {:?} in hand to drop", colour, kind);
+        }
+        zobrist.toggle_pocket(hash, colour, kind, *count);
+        *count -= 1;
+        zobrist.toggle_pocket(hash, colour, kind, *count);
+    }
+
+    /// Read the pieces in hand of a Crazyhouse FEN, e.g. `QNpp`, white's in upper case
+    pub fn set_pockets_from_fenstr(&mut self, pockets_fenstr: &str) {
+        self.pockets = [[0; 5]; 2];
+        for ch in pockets_fenstr.chars().filter(|&ch| ch != '-') {
+            let colour = if ch.is_ascii_uppercase() { Colour::White } else { Colour::Black };
+            let count = PieceType::from_char(ch)
+                .filter(|&kind| kind != PieceType::King)
+                .map(|kind| &mut self.pockets[colour as usize][kind as usize])
+                .unwrap_or_else(|| panic!("Invalid piece in hand in fenstring: {}", ch));
+            if *count as usize >= MAX_POCKET_COUNT {
+                panic!("Too many pieces in hand in fenstring: {}", pockets_fenstr);
             }
+            *count += 1;
+        }
+    }
+
+    pub fn get_promoted(&self) -> BitBoard {
+        self.promoted
+    }
+
+    pub fn set_promoted(&mut self, promoted: BitBoard) {
+        self.promoted = promoted;
+    }
+
+    /// Square the king has to stand on to castle
+    pub fn castling_king_coords(&self, colour: Colour) -> Coords {
+        Coords::new(back_rank(colour), self.castling_king_files[colour as usize])
+    }
+
+    /// Square the rook has to stand on to castle with the single right `right`
+    pub fn castling_rook_coords(&self, right: CastlingRights) -> Coords {
+        Coords::new(back_rank(right.colour()), self.castling_rook_files[right.index()])
+    }
+
+    /// Castling with the single right `right`, whether or not it is currently legal.
+    /// The king always ends on the g or c file and the rook next to it on f or d.
+    pub fn castling_move(&self, right: CastlingRights) -> CastlingMove {
+        let colour = right.colour();
+        let rank = back_rank(colour);
+        let (king_to, rook_to) = if right.is_kingside() { (File::G, File::F) } else { (File::C, File::D) };
+
+        CastlingMove {
+            colour,
+            king_from: self.castling_king_coords(colour),
+            king_to: Coords::new(rank, king_to),
+            rook_from: self.castling_rook_coords(right),
+            rook_to: Coords::new(rank, rook_to),
         }
     }
 
@@ -72,8 +280,65 @@ impl GameState {
         self.en_passant_piece_coords
     }
 
+    /// A target off the third and sixth ranks can't come from a double step, so it is ignored
     pub fn set_en_passant_target(&mut self, target: Option<Coords>) {
-        self.en_passant_target = target;
+        // The pawn that can be captured sits one rank past the target, away from its start
+        self.en_passant_piece_coords = target.and_then(|coords| match coords.rank {
+            3 => Some(Coords::new(4, coords.file)),
+            6 => Some(Coords::new(5, coords.file)),
+            _ => None,
+        });
+        self.en_passant_target = target.filter(|_| self.en_passant_piece_coords.is_some());
+    }
+
+    pub fn get_halfmove_clock(&self) -> u16 {
+        self.halfmove_clock
+    }
+
+    pub fn set_halfmove_clock(&mut self, halfmove_clock: u16) {
+        self.halfmove_clock = halfmove_clock;
+    }
+
+    pub fn get_fullmove_number(&self) -> u16 {
+        self.fullmove_number
+    }
+
+    pub fn set_fullmove_number(&mut self, fullmove_number: u16) {
+        self.fullmove_number = fullmove_number;
+    }
+
+    /// Advance the clocks after `update` has passed the turn on.
+    /// Pawn moves and captures reset the fifty-move counter.
+    pub fn advance_clocks(&mut self, irreversible: bool) {
+        self.halfmove_clock = if irreversible { 0 } else { self.halfmove_clock + 1 };
+        if self.turn == Colour::White {
+            self.fullmove_number += 1;
+        }
+    }
+
+    /// Reverse `update` and `advance_clocks` from the values saved before the move.
+    /// The hash is restored separately by the caller.
+    pub fn restore(&mut self, castling_rights: CastlingRights, en_passant_target: Option<Coords>, halfmove_clock: u16) {
+        if self.turn == Colour::White {
+            self.fullmove_number -= 1;
+        }
+        self.turn = self.turn.other();
+        self.castling_rights = castling_rights;
+        self.set_en_passant_target(en_passant_target);
+        self.halfmove_clock = halfmove_clock;
+    }
+
+    /// Take away the rights that need a king or rook starting on `coords`, for pieces removed
+    /// other than by a move, such as in an explosion
+    pub fn revoke_castling_rights_at(&mut self, coords: Coords, hash: &mut u64, zobrist: &Zobrist) {
+        let old_castling = self.castling_rights;
+        for right in CastlingRights::SINGLE {
+            if coords == self.castling_king_coords(right.colour()) || coords == self.castling_rook_coords(right) {
+                self.castling_rights.remove(right);
+            }
+        }
+        zobrist.toggle_castle(hash, &old_castling);
+        zobrist.toggle_castle(hash, &self.castling_rights);
     }
 
     pub fn update(&mut self, mv: &ChessMove, hash: &mut u64, zobrist: &Zobrist) {
@@ -98,19 +363,11 @@ impl GameState {
                 self.castling_rights.remove(CastlingRights::BLACK_KINGSIDE | CastlingRights::BLACK_QUEENSIDE);
             }
         }
-        if mv.piece() == PieceType::Rook {
-            if mv.colour() == Colour::White {
-                if mv.from() == Coords::new(1, File::A) {
-                    self.castling_rights.remove(CastlingRights::WHITE_QUEENSIDE);
-                } else if mv.from() == Coords::new(1, File::H) {
-                    self.castling_rights.remove(CastlingRights::WHITE_KINGSIDE);
-                }
-            } else {
-                if mv.from() == Coords::new(8, File::A) {
-                    self.castling_rights.remove(CastlingRights::BLACK_QUEENSIDE);
-                } else if mv.from() == Coords::new(8, File::H) {
-                    self.castling_rights.remove(CastlingRights::BLACK_KINGSIDE);
-                }
+        // A rook leaving its start square or being captured there takes that right away
+        for right in CastlingRights::SINGLE {
+            let rook_coords = self.castling_rook_coords(right);
+            if mv.from() == rook_coords || mv.to() == rook_coords {
+                self.castling_rights.remove(right);
             }
         }
 
@@ -144,6 +401,7 @@ mod tests {
     use crate::enums::moves::NormalMove;
     use crate::enums::{ChessMove, Colour, File, PieceType};
     use crate::coords::Coords;
+    use crate::game_classes::game::Game;
     use crate::game_classes::zobrist::Zobrist;
 
     fn create_move(piece: PieceType, colour: Colour, from: Coords, to: Coords) -> ChessMove {
@@ -227,6 +485,86 @@ mod tests {
         assert!(gs.en_passant_target.is_none());
     }
 
+    #[test]
+    fn test_invalid_en_passant_target_is_ignored() {
+        let mut game = Game::new();
+        game.set_fenstr("4k3/8/8/8/4P3/8/8/4K3 b - e4 0 1");
+        assert!(game.get_game_state().get_en_passant_target().is_none());
+        assert!(game.get_game_state().get_en_passant_piece_coords().is_none());
+    }
+
+    #[test]
+    fn test_clocks_advance_and_restore() {
+        let (mut gs, zobrist, mut hash) = new_game_state_with_hash();
+        gs.set_halfmove_clock(7);
+
+        // Black's knight move increments the halfmove clock and completes the full move
+        gs.turn = Colour::Black;
+        let mv = create_move(PieceType::Knight, Colour::Black, Coords::new(8, File::G), Coords::new(6, File::F));
+        let (rights, ep) = (gs.castling_rights, gs.en_passant_target);
+        gs.update(&mv, &mut hash, &zobrist);
+        gs.advance_clocks(false);
+        assert_eq!(gs.get_halfmove_clock(), 8);
+        assert_eq!(gs.get_fullmove_number(), 2);
+
+        // A pawn move resets the halfmove clock
+        let mv = create_move(PieceType::Pawn, Colour::White, Coords::new(2, File::D), Coords::new(4, File::D));
+        gs.update(&mv, &mut hash, &zobrist);
+        gs.advance_clocks(true);
+        assert_eq!(gs.get_halfmove_clock(), 0);
+        assert_eq!(gs.get_fullmove_number(), 2);
+        assert_eq!(gs.get_en_passant_piece_coords(), Some(Coords::new(4, File::D)));
+
+        gs.restore(CastlingRights::all(), None, 8);
+        gs.restore(rights, ep, 7);
+        assert_eq!(gs.turn, Colour::Black);
+        assert_eq!(gs.get_halfmove_clock(), 7);
+        assert_eq!(gs.get_fullmove_number(), 1);
+        assert!(gs.get_en_passant_piece_coords().is_none());
+    }
+
+    #[test]
+    fn test_castling_fields_find_king_and_rook_files() {
+        let mut board = Board::new();
+        board.set_board_from_fenstr("1r2k1r1/8/8/8/8/8/8/RK4R1");
+        let mut gs = GameState::new();
+
+        // Shredder-FEN names the rook files outright
+        gs.set_castling_rights_from_fenstr("GAgb", &board);
+        assert_eq!(gs.castling_king_coords(Colour::White), Coords::new(1, File::B));
+        assert_eq!(gs.castling_king_coords(Colour::Black), Coords::new(8, File::E));
+        assert_eq!(gs.castling_rook_coords(CastlingRights::WHITE_KINGSIDE), Coords::new(1, File::G));
+        assert_eq!(gs.castling_rook_coords(CastlingRights::WHITE_QUEENSIDE), Coords::new(1, File::A));
+        assert_eq!(gs.castling_rook_coords(CastlingRights::BLACK_KINGSIDE), Coords::new(8, File::G));
+        assert_eq!(gs.castling_rook_coords(CastlingRights::BLACK_QUEENSIDE), Coords::new(8, File::B));
+        assert!(gs.can_castle(CastlingRights::all()));
+
+        // X-FEN's KQkq stand for the outermost rook on each side
+        gs.set_castling_rights_from_fenstr("Kq", &board);
+        assert_eq!(gs.castling_rook_coords(CastlingRights::WHITE_KINGSIDE), Coords::new(1, File::G));
+        assert_eq!(gs.castling_rook_coords(CastlingRights::BLACK_QUEENSIDE), Coords::new(8, File::B));
+        assert!(!gs.can_castle(CastlingRights::WHITE_QUEENSIDE));
+
+        // Castling always lands the king on g or c and the rook beside it
+        let mv = gs.castling_move(CastlingRights::WHITE_KINGSIDE);
+        assert_eq!((mv.king_from, mv.king_to), (Coords::new(1, File::B), Coords::new(1, File::G)));
+        assert_eq!((mv.rook_from, mv.rook_to), (Coords::new(1, File::G), Coords::new(1, File::F)));
+    }
+
+    #[test]
+    fn test_castling_right_follows_chess960_rook() {
+        let mut board = Board::new();
+        board.set_board_from_fenstr("4k3/8/8/8/8/8/8/1RK3R1");
+        let (mut gs, zobrist, mut hash) = new_game_state_with_hash();
+        gs.set_castling_rights_from_fenstr("GB", &board);
+
+        // Moving the g1 rook only loses the kingside right
+        let mv = create_move(PieceType::Rook, Colour::White, Coords::new(1, File::G), Coords::new(5, File::G));
+        gs.update(&mv, &mut hash, &zobrist);
+        assert!(!gs.can_castle(CastlingRights::WHITE_KINGSIDE));
+        assert!(gs.can_castle(CastlingRights::WHITE_QUEENSIDE));
+    }
+
     #[test]
     #[should_panic(expected = "It's White's turn")]
     fn test_wrong_turn_panics() {
@@ -1,5 +1,7 @@
 pub mod board_classes;
+pub mod chess960;
 pub mod game;
 pub mod game_state;
 pub mod game_state_tracker;
-pub mod zobrist;
\ No newline at end of file
+pub mod zobrist;
+pub mod polyglot_zobrist;
@@ -1,53 +1,82 @@
-use rand::Rng;
+use std::sync::Arc;
 
-use crate::{coords::Coords, enums::File, piece::Piece};
+use once_cell::sync::Lazy;
+
+use crate::{enums::{Colour, File, PieceType}, piece::Piece, square::Square};
 use crate::game_classes::game_state::CastlingRights;
 
+/// Seed for the keys every `Game` shares unless it is given its own
+pub const DEFAULT_SEED: u64 = 0x5EED_C0DE_2B7E_1516;
+
+/// Default keys, generated once and shared so hashes match across games and runs
+pub static ZOBRIST: Lazy<Arc<Zobrist>> = Lazy::new(|| Arc::new(Zobrist::with_seed(DEFAULT_SEED)));
+
 pub struct Zobrist {
     pub piece_square: [[[u64; 64]; 6]; 2], // [color][piece][square]
     pub castling: [u64; 4],                // 4 castling rights 
     pub en_passant: [u64; 8],              // en passant files
     pub side_to_move: u64,                 // white or black to move
+    pub checks: [[u64; 3]; 2],             // [color][checks given - 1], for three-check
+    pub pockets: [[[u64; MAX_POCKET_COUNT]; 5]; 2], // [color][piece][count - 1], for crazyhouse
 }
 
+/// Most pieces of one type a pocket can hold, every pawn of both sides
+pub const MAX_POCKET_COUNT: usize = 16;
+
 impl Zobrist {
     pub fn new() -> Self {
-        let mut rng = rand::rng();
+        Self::with_seed(DEFAULT_SEED)
+    }
+
+    /// The shared default key set
+    pub fn shared() -> Arc<Zobrist> {
+        Arc::clone(&ZOBRIST)
+    }
+
+    /// Keys from a specific seed, e.g. to check results don't depend on a lucky key set
+    pub fn with_seed(seed: u64) -> Self {
+        let mut state = seed;
+        let mut next = || splitmix64(&mut state);
 
         let mut piece_square = [[[0u64; 64]; 6]; 2];
         for color in 0..2 {
             for piece in 0..6 {
                 for sq in 0..64 {
-                    piece_square[color][piece][sq] = rng.random();
+                    piece_square[color][piece][sq] = next();
                 }
             }
         }
 
         let mut castling = [0u64; 4];
         for i in 0..4 {
-            castling[i] = rng.random();
+            castling[i] = next();
         }
 
         let mut en_passant = [0u64; 8];
         for i in 0..8 {
-            en_passant[i] = rng.random();
+            en_passant[i] = next();
         }
 
-        let side_to_move = rng.random();
+        let side_to_move = next();
+
+        let checks = [(); 2].map(|_| [(); 3].map(|_| next()));
+
+        let pockets = [(); 2].map(|_| [(); 5].map(|_| [(); MAX_POCKET_COUNT].map(|_| next())));
 
         Zobrist {
             piece_square,
             castling,
             en_passant,
             side_to_move,
+            checks,
+            pockets,
         }
     }
 
-    pub fn toggle_piece(&self, hash: &mut u64, coords: &Coords, piece: &Piece) {
+    pub fn toggle_piece(&self, hash: &mut u64, square: Square, piece: &Piece) {
         let colour_idx = piece.colour as usize;
         let kind_idx = piece.kind as usize;
-        let coords_idx = coords.to_index();
-        *hash ^= self.piece_square[colour_idx][kind_idx][coords_idx];
+        *hash ^= self.piece_square[colour_idx][kind_idx][square.index()];
     }
 
     // Toggle a specific castling right
@@ -75,12 +104,34 @@ impl Zobrist {
     pub fn toggle_side_to_move(&self, hash: &mut u64) {
         *hash ^= self.side_to_move;
     }
+
+    // Toggle the number of checks `colour` has given, no checks having no key
+    pub fn toggle_checks(&self, hash: &mut u64, colour: Colour, count: u8) {
+        if count > 0 {
+            *hash ^= self.checks[colour as usize][count as usize - 1];
+        }
+    }
+
+    // Toggle how many pieces of `kind` are in `colour`'s pocket, an empty pocket having no key
+    pub fn toggle_pocket(&self, hash: &mut u64, colour: Colour, kind: PieceType, count: u8) {
+        if count > 0 {
+            *hash ^= self.pockets[colour as usize][kind as usize][count as usize - 1];
+        }
+    }
+}
+
+// SplitMix64: small, fast and fully reproducible, which is all hash keys need
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::coords::Coords;
     use crate::piece::{Piece};
     use crate::enums::{Colour, PieceType};
 
@@ -90,14 +141,13 @@ mod tests {
         let mut hash: u64 = 0;
 
         let piece = Piece { colour: Colour::White, kind: PieceType::Pawn };
-        let coords = Coords::new(2, crate::enums::File::E);
 
         let original_hash = hash;
-        zob.toggle_piece(&mut hash, &coords, &piece);
+        zob.toggle_piece(&mut hash, Square::E2, &piece);
         assert_ne!(hash, original_hash, "Hash should change after toggling piece");
 
         // Toggling again should revert
-        zob.toggle_piece(&mut hash, &coords, &piece);
+        zob.toggle_piece(&mut hash, Square::E2, &piece);
         assert_eq!(hash, original_hash, "Hash should revert after toggling piece twice");
     }
 
@@ -145,19 +195,51 @@ mod tests {
         assert_eq!(hash, original_hash, "Hash should revert after toggling side to move twice");
     }
 
+    #[test]
+    fn test_keys_are_deterministic() {
+        // Same seed gives the same keys every time
+        let a = Zobrist::new();
+        let b = Zobrist::new();
+        assert_eq!(a.piece_square, b.piece_square);
+        assert_eq!(a.castling, b.castling);
+        assert_eq!(a.en_passant, b.en_passant);
+        assert_eq!(a.side_to_move, b.side_to_move);
+        assert_eq!(a.piece_square, Zobrist::shared().piece_square);
+
+        // A different seed gives a different key set
+        let c = Zobrist::with_seed(DEFAULT_SEED + 1);
+        assert_ne!(a.piece_square, c.piece_square);
+        assert_ne!(a.side_to_move, c.side_to_move);
+    }
+
+    #[test]
+    fn test_keys_are_distinct() {
+        let zob = Zobrist::new();
+        let mut keys: Vec<u64> = zob.piece_square.iter().flatten().flatten().copied().collect();
+        keys.extend(zob.castling);
+        keys.extend(zob.en_passant);
+        keys.push(zob.side_to_move);
+        keys.extend(zob.checks.iter().flatten());
+        keys.extend(zob.pockets.iter().flatten().flatten());
+
+        let count = keys.len();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), count, "Zobrist keys should not repeat");
+    }
+
     #[test]
     fn test_combined_toggles() {
         let zob = Zobrist::new();
         let mut hash: u64 = 0;
 
         let piece = Piece { colour: Colour::Black, kind: PieceType::Knight };
-        let coords = Coords::new(8, crate::enums::File::G);
         let rights = CastlingRights::WHITE_QUEENSIDE | CastlingRights::BLACK_KINGSIDE;
         let file = File::D; // D file
 
         let original_hash = hash;
 
-        zob.toggle_piece(&mut hash, &coords, &piece);
+        zob.toggle_piece(&mut hash, Square::G8, &piece);
         zob.toggle_castle(&mut hash, &rights);
         zob.toggle_en_passant(&mut hash, &file);
         zob.toggle_side_to_move(&mut hash);
@@ -168,7 +250,7 @@ mod tests {
         zob.toggle_side_to_move(&mut hash);
         zob.toggle_en_passant(&mut hash, &file);
         zob.toggle_castle(&mut hash, &rights);
-        zob.toggle_piece(&mut hash, &coords, &piece);
+        zob.toggle_piece(&mut hash, Square::G8, &piece);
 
         assert_eq!(hash, original_hash, "Hash should revert after toggling all features twice");
     }
@@ -1,4 +1,8 @@
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use std::sync::Arc;
 
 use crate::enums::ChessMove;
 use crate::game_classes::board_classes::magic_bitboard;
@@ -6,14 +10,25 @@ use crate::moves::move_generator;
 use crate::game_classes::game::Game;
 use crate::moves::move_parser::MoveParser;
 use crate::engine::minimax::Minimax;
+use crate::engine::eval_params::EvalParams;
+use crate::engine::evaluator::{EvalTrace, Evaluator};
+use crate::engine::nnue::Network;
+use crate::book::{BookPick, OpeningBook};
+use crate::syzygy::tablebase::Tablebase;
+use crate::game_classes::board_classes::bit_board::BitBoard;
+use crate::square::Square;
 
 pub mod coords;
+pub mod square;
 pub mod piece;
 pub mod enums;
 pub mod moves;
 pub mod game_classes;
 pub mod engine;
 pub mod move_ordering;
+pub mod book;
+pub mod syzygy;
+pub mod variants;
 
 #[pymodule]
 fn rust_chess(m: &Bound<'_, PyModule>) -> PyResult<()> {
@@ -24,6 +39,36 @@ fn rust_chess(m: &Bound<'_, PyModule>) -> PyResult<()> {
     Ok(())
 }
 
+fn parse_square(square: &str) -> PyResult<Square> {
+    square.parse().map_err(PyValueError::new_err)
+}
+
+fn parse_colour(colour: &str) -> PyResult<enums::Colour> {
+    match colour.to_lowercase().as_str() {
+        "white" => Ok(enums::Colour::White),
+        "black" => Ok(enums::Colour::Black),
+        _ => Err(PyValueError::new_err(format!("Invalid colour: {}", colour))),
+    }
+}
+
+fn square_names(squares: BitBoard) -> Vec<String> {
+    squares.squares().map(|square| Square::from_index(square).to_string()).collect()
+}
+
+/// `{term: {"white": (mg, eg), "black": (mg, eg)}, "phase": int, "total": int}`, total from white's view
+fn eval_breakdown_dict<'py>(py: Python<'py>, trace: &EvalTrace) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    for (name, [white, black]) in trace.terms() {
+        let sides = PyDict::new(py);
+        sides.set_item("white", white)?;
+        sides.set_item("black", black)?;
+        dict.set_item(name, sides)?;
+    }
+    dict.set_item("phase", trace.phase)?;
+    dict.set_item("total", trace.total)?;
+    Ok(dict)
+}
+
 #[pyclass]
 pub struct PyGame {
     inner: Game,
@@ -58,7 +103,37 @@ impl PyGame {
 
         let mut moves = Vec::new();
         move_generator::MoveGenerator::generate_legal_moves_into(&mut self.inner, colour, false, &mut moves);
-        moves.iter().map(|m| m.to_string()).collect()
+        moves.iter().map(|m| m.to_chess_move(&self.inner).to_string()).collect()
+    }
+
+    /// Per-term, per-side breakdown of the static evaluation
+    fn eval_breakdown<'py>(&mut self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        eval_breakdown_dict(py, &Evaluator::eval_trace(&mut self.inner))
+    }
+
+    /// Everything wrong with the position, empty when it is valid
+    fn validate(&self) -> Vec<String> {
+        self.inner.validate()
+    }
+
+    /// Squares of `colour`'s pieces attacking `square`
+    fn attackers_of(&self, square: &str, colour: &str) -> PyResult<Vec<String>> {
+        Ok(square_names(self.inner.attackers_of(parse_square(square)?, parse_colour(colour)?)))
+    }
+
+    /// Squares of `colour`'s pieces pinned to their king
+    fn pinned_pieces(&self, colour: &str) -> PyResult<Vec<String>> {
+        Ok(square_names(self.inner.pinned_pieces(parse_colour(colour)?)))
+    }
+
+    /// Squares of the pieces checking the side to move
+    fn checkers(&self) -> Vec<String> {
+        square_names(self.inner.checkers())
+    }
+
+    /// Squares attacked by the piece on `square`
+    fn attacks_from(&self, square: &str) -> PyResult<Vec<String>> {
+        Ok(square_names(self.inner.attacks_from(parse_square(square)?)))
     }
 }
 
@@ -77,27 +152,33 @@ impl PyMinimax {
 
     pub fn go(&mut self) -> String {
         let colour = self.game.get_game_state().get_turn();
+        let chess960 = self.game.get_game_state().is_chess960();
         // // println!("Current board eval: {}", self.inner.evaluate(&self.game, colour));
         // let best = self.inner.find_best_move(&mut self.game, colour);
         // return best.unwrap().to_string();
 
+        if let Some(book_move) = self.inner.probe_book(&mut self.game) {
+            return book_move.to_uci(chess960);
+        }
+
         let moves = self.inner.find_sorted_moves(&mut self.game, colour);
         for (mv, eval) in moves.iter().take(100) {
             println!("{mv}: {eval}");
         }
 
-        return moves[0].0.to_string();
+        return moves[0].0.to_uci(chess960);
     }
 
     pub fn evaluate_moves(&mut self) -> Vec<(String, i32)> {
         let colour = self.game.get_game_state().get_turn();
+        let chess960 = self.game.get_game_state().is_chess960();
         // // println!("Current board eval: {}", self.inner.evaluate(&self.game, colour));
         // let best = self.inner.find_best_move(&mut self.game, colour);
         // return best.unwrap().to_string();
 
         self.inner.find_sorted_moves(&mut self.game, colour)
             .iter()                          // iterate over & (ChessMove, i32)
-            .map(|(mv, score)| (mv.to_string(), *score))  // convert ChessMove -> String, copy the i32
+            .map(|(mv, score)| (mv.to_uci(chess960), *score))  // convert ChessMove -> String, copy the i32
             .collect()
     }
 
@@ -119,6 +200,11 @@ impl PyMinimax {
         panic!("Invalid move");
     }
 
+    /// Static evaluation breakdown of the current position
+    pub fn eval_breakdown<'py>(&mut self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        eval_breakdown_dict(py, &Evaluator::eval_trace(&mut self.game))
+    }
+
     /// Engine option setters
     pub fn set_max_depth(&mut self, max_depth: usize) {
         // self.inner.update_max_depth(max_depth);
@@ -136,6 +222,83 @@ impl PyMinimax {
         self.inner.engine_options.magic_bitboards = use_magic_bitboards;
     }
 
+    /// Read and write castling as king takes rook, for Chess960 GUIs
+    pub fn set_chess960(&mut self, chess960: bool) {
+        self.game.set_chess960(chess960);
+    }
+
+    /// Play the variant called `name`, e.g. `3check`, `kingofthehill` or `atomic`, from its start position
+    pub fn set_variant(&mut self, name: &str) -> PyResult<()> {
+        let variant = variants::from_name(name)
+            .ok_or_else(|| PyValueError::new_err(format!("Unknown variant: {}", name)))?;
+        self.game.set_variant(variant);
+        self.inner.tt.clear();
+        Ok(())
+    }
+
+    /// Load a Polyglot opening book, used by `go` until the game leaves it
+    pub fn set_book(&mut self, path: &str) -> PyResult<()> {
+        self.inner.book = Some(OpeningBook::open(path)?);
+        Ok(())
+    }
+
+    pub fn clear_book(&mut self) {
+        self.inner.book = None;
+    }
+
+    /// Play the highest weighted book move instead of a weighted random one
+    pub fn set_book_best_move(&mut self, best: bool) {
+        self.inner.engine_options.book_pick = if best { BookPick::Best } else { BookPick::WeightedRandom };
+    }
+
+    /// Load Syzygy tables from a `:` separated list of directories, an empty path unloads them.
+    /// Returns the largest number of pieces covered.
+    pub fn set_syzygy_path(&mut self, path: &str) -> PyResult<usize> {
+        if path.is_empty() {
+            self.inner.tablebase = None;
+            return Ok(0);
+        }
+
+        let tablebase = Tablebase::from_path_list(path)?;
+        let max_pieces = tablebase.max_pieces();
+        self.inner.tablebase = Some(tablebase);
+        Ok(max_pieces)
+    }
+
+    pub fn set_syzygy_probe_limit(&mut self, limit: usize) {
+        self.inner.engine_options.syzygy_probe_limit = limit;
+    }
+
+    /// Load evaluation parameters written by `save_eval_file`, an empty path restores the defaults
+    pub fn set_eval_file(&mut self, path: &str) -> PyResult<()> {
+        let params = if path.is_empty() { EvalParams::shared() } else { Arc::new(EvalParams::load(path)?) };
+        self.game.set_eval_params(params);
+        self.inner.pawn_table.clear();
+        self.inner.tt.clear();
+        Ok(())
+    }
+
+    /// Load a network to evaluate with, an empty path unloads it and goes back to the
+    /// hand-crafted evaluation
+    pub fn set_nnue_file(&mut self, path: &str) -> PyResult<()> {
+        let network = if path.is_empty() { None } else { Some(Arc::new(Network::load(path)?)) };
+        self.inner.engine_options.use_nnue = network.is_some();
+        self.game.set_network(network);
+        self.inner.tt.clear();
+        Ok(())
+    }
+
+    /// Switch between a loaded network and the hand-crafted evaluation
+    pub fn set_use_nnue(&mut self, use_nnue: bool) {
+        self.inner.engine_options.use_nnue = use_nnue;
+        self.inner.tt.clear();
+    }
+
+    /// Write the evaluation parameters in use, e.g. as a starting point for editing
+    pub fn save_eval_file(&self, path: &str) -> PyResult<()> {
+        Ok(self.game.get_eval_params().save(path)?)
+    }
+
     /// Engine option getters
     pub fn get_max_depth(&self) -> usize {
         self.inner.engine_options.max_depth
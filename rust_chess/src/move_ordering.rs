@@ -1,29 +1,60 @@
-use crate::enums::{ChessMove, PieceType};
+use crate::enums::PieceType;
 use crate::game_classes::game::Game;
 use crate::engine::evaluator::Evaluator;
+use crate::moves::compact_move::{Move, MoveKind};
+
+const HASH_MOVE_SCORE: i32 = 1_000_000;
+const CAPTURE_SCORE: i32 = 100_000;
+const PROMOTION_SCORE: i32 = 50_000;
+const KILLER_SCORES: [i32; 2] = [40_000, 39_000];
 
 fn mvv_lva_score(attacker: PieceType, victim: PieceType) -> i32 {
     Evaluator::get_piece_value(victim) - Evaluator::get_piece_value(attacker)
 }
 
+/// Captures and promotions, i.e. the moves that are never stored as killers
+pub fn is_noisy_move(mv: Move, game: &Game) -> bool {
+    match mv.kind() {
+        MoveKind::Normal => game.get_board().all_occ().bits() & mv.to_sq().bit() != 0,
+        MoveKind::Promotion | MoveKind::EnPassant => true,
+        MoveKind::Castling | MoveKind::Drop => false,
+    }
+}
+
+fn move_order_score(mv: Move, game: &Game, hash_move: Move, killers: &[Move; 2]) -> i32 {
+    // The best move found for this position by an earlier search goes first
+    if mv == hash_move {
+        return HASH_MOVE_SCORE;
+    }
 
-fn move_order_score(mv: &ChessMove, game: &Game) -> i32 {
     // Captures: use MVV-LVA
-    if let Some(captured) = game.get_board().get_coords(&mv.to()) {
-        let attacker = game.get_board().get_coords(&mv.from()).unwrap();
-        return 100_000 + mvv_lva_score(attacker.kind, captured.kind);
+    if mv.kind() != MoveKind::Castling {
+        if let Some(captured) = game.get_board().piece_at(mv.to_sq()) {
+            let attacker = game.get_board().piece_at(mv.from_sq()).unwrap();
+            return CAPTURE_SCORE + mvv_lva_score(attacker.kind, captured.kind);
+        }
     }
 
     // Promotions (if included in quiescence)
-    if matches!(mv, ChessMove::Promotion(_)) {
-        return 50_000;
+    if mv.kind() == MoveKind::Promotion {
+        return PROMOTION_SCORE;
+    }
+
+    // Quiet moves that caused a cutoff at the same ply in a sibling node
+    if let Some(slot) = killers.iter().position(|&killer| killer == mv) {
+        return KILLER_SCORES[slot];
     }
 
     // Quiet moves (not usually searched in quiescence)
     0
 }
 
-pub fn order_moves(moves: &mut Vec<ChessMove>, game: &Game) {
-    moves.sort_unstable_by_key(|mv| move_order_score(mv, game));
+pub fn order_moves(moves: &mut Vec<Move>, game: &Game) {
+    order_moves_with_hints(moves, game, Move::NULL, &[Move::NULL; 2]);
+}
+
+/// Order moves with the transposition table move and this ply's killer moves ahead of the rest
+pub fn order_moves_with_hints(moves: &mut Vec<Move>, game: &Game, hash_move: Move, killers: &[Move; 2]) {
+    moves.sort_unstable_by_key(|&mv| move_order_score(mv, game, hash_move, killers));
     moves.reverse(); // highest score first
-}
\ No newline at end of file
+}
@@ -0,0 +1,340 @@
+use std::fmt;
+
+use crate::coords::Coords;
+use crate::enums::moves::{DropMove, EnPassantMove, NormalMove, PromotionMove};
+use crate::enums::piece_types::PIECE_TYPES;
+use crate::enums::{ChessMove, ExecutedMove, File, PieceType};
+use crate::game_classes::game::Game;
+use crate::game_classes::game_state::CastlingRights;
+use crate::square::Square;
+
+// Layout: bits 0-5 from square, bits 6-11 to square, bits 12-13 kind, bits 14-15 promotion piece.
+// Only a Chess960 castle with the king standing still goes from a square to itself, so a drop
+// is written as one, with the dropped piece type plus four in bits 12-15 to keep clear of the
+// castling kind.
+const SQUARE_MASK: u16 = 0x3F;
+const TO_SHIFT: u16 = 6;
+const KIND_SHIFT: u16 = 12;
+const PROMOTION_SHIFT: u16 = 14;
+const DROP_PIECE_OFFSET: u16 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveKind {
+    Normal = 0,
+    Promotion = 1,
+    EnPassant = 2,
+    Castling = 3,
+    Drop = 4,
+}
+
+/// A move packed into 16 bits. Colour and moving piece are not stored, they are read
+/// from the board when the move is expanded back into a `ChessMove`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Move(u16);
+
+impl Move {
+    /// Placeholder for "no move" (a1a1 can never be a real move)
+    pub const NULL: Move = Move(0);
+
+    const fn pack(from: Square, to: Square, kind: MoveKind, promotion_bits: u16) -> Self {
+        Move(
+            (from.index() as u16 & SQUARE_MASK)
+                | ((to.index() as u16 & SQUARE_MASK) << TO_SHIFT)
+                | ((kind as u16) << KIND_SHIFT)
+                | (promotion_bits << PROMOTION_SHIFT),
+        )
+    }
+
+    pub const fn normal(from: Square, to: Square) -> Self {
+        Self::pack(from, to, MoveKind::Normal, 0)
+    }
+
+    pub const fn en_passant(from: Square, to: Square) -> Self {
+        Self::pack(from, to, MoveKind::EnPassant, 0)
+    }
+
+    /// Castling is encoded by the king's from and to squares
+    pub const fn castling(king_from: Square, king_to: Square) -> Self {
+        Self::pack(king_from, king_to, MoveKind::Castling, 0)
+    }
+
+    pub const fn drop(piece_type: PieceType, to: Square) -> Self {
+        Self::pack(to, to, MoveKind::Normal, 0).with_drop_piece(piece_type)
+    }
+
+    const fn with_drop_piece(self, piece_type: PieceType) -> Self {
+        Move(self.0 | ((piece_type as u16 + DROP_PIECE_OFFSET) << KIND_SHIFT))
+    }
+
+    pub fn promotion(from: Square, to: Square, piece_type: PieceType) -> Self {
+        let promotion_bits = match piece_type {
+            PieceType::Knight => 0,
+            PieceType::Bishop => 1,
+            PieceType::Rook => 2,
+            PieceType::Queen => 3,
+            _ => panic!("Invalid promotion piece: {:?}", piece_type),
+        };
+        Self::pack(from, to, MoveKind::Promotion, promotion_bits)
+    }
+
+    pub const fn from_raw(raw: u16) -> Self {
+        Move(raw)
+    }
+
+    pub const fn raw(self) -> u16 {
+        self.0
+    }
+
+    pub const fn is_null(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn from_sq(self) -> Square {
+        Square::new((self.0 & SQUARE_MASK) as u8)
+    }
+
+    pub const fn to_sq(self) -> Square {
+        Square::new(((self.0 >> TO_SHIFT) & SQUARE_MASK) as u8)
+    }
+
+    pub const fn kind(self) -> MoveKind {
+        if self.is_drop() {
+            return MoveKind::Drop;
+        }
+        match (self.0 >> KIND_SHIFT) & 0b11 {
+            0 => MoveKind::Normal,
+            1 => MoveKind::Promotion,
+            2 => MoveKind::EnPassant,
+            _ => MoveKind::Castling,
+        }
+    }
+
+    const fn is_drop(self) -> bool {
+        (self.0 & SQUARE_MASK) == ((self.0 >> TO_SHIFT) & SQUARE_MASK) && self.0 >> KIND_SHIFT >= DROP_PIECE_OFFSET
+    }
+
+    /// The piece type put down by a drop
+    pub fn drop_piece(self) -> Option<PieceType> {
+        if !self.is_drop() {
+            return None;
+        }
+        PIECE_TYPES.get(((self.0 >> KIND_SHIFT) - DROP_PIECE_OFFSET) as usize).copied()
+    }
+
+    pub fn promotion_piece(self) -> Option<PieceType> {
+        if self.kind() != MoveKind::Promotion {
+            return None;
+        }
+
+        Some(match self.0 >> PROMOTION_SHIFT {
+            0 => PieceType::Knight,
+            1 => PieceType::Bishop,
+            2 => PieceType::Rook,
+            _ => PieceType::Queen,
+        })
+    }
+
+    pub fn from_coords(self) -> Coords {
+        Coords::from(self.from_sq())
+    }
+
+    pub fn to_coords(self) -> Coords {
+        Coords::from(self.to_sq())
+    }
+
+    /// Expand into a `ChessMove`, reading the colour and moving piece from the board.
+    /// Panics if the from square is empty.
+    pub fn to_chess_move(self, game: &Game) -> ChessMove {
+        let from = self.from_coords();
+        let to = self.to_coords();
+        if let Some(piece_type) = self.drop_piece() {
+            let colour = game.get_game_state().get_turn();
+            return ChessMove::Drop(DropMove { colour, piece_type, to });
+        }
+
+        let piece = game.get_board().get_coords(&from)
+            .unwrap_or_else(|| panic!("No piece on {} for move {}", from, self));
+
+        match self.kind() {
+            MoveKind::Normal => ChessMove::Normal(NormalMove {
+                colour: piece.colour,
+                piece_type: piece.kind,
+                from,
+                to,
+            }),
+            MoveKind::Promotion => ChessMove::Promotion(PromotionMove {
+                colour: piece.colour,
+                from,
+                to,
+                promotion_piece_type: self.promotion_piece().unwrap(),
+            }),
+            MoveKind::EnPassant => ChessMove::EnPassant(EnPassantMove {
+                colour: piece.colour,
+                from,
+                to,
+                captured_coords: Coords::new(from.rank, to.file),
+            }),
+            MoveKind::Castling => {
+                // The king lands on the g file castling kingside, the rooks come from the game state
+                let right = CastlingRights::side(piece.colour, to.file == File::G);
+                ChessMove::Castling(game.get_game_state().castling_move(right))
+            }
+            MoveKind::Drop => unreachable!("Drops are expanded above"),
+        }
+    }
+}
+
+impl From<&ChessMove> for Move {
+    fn from(chess_move: &ChessMove) -> Self {
+        match chess_move {
+            ChessMove::Normal(mv) => Move::normal(Square::from(mv.from), Square::from(mv.to)),
+            ChessMove::Castling(mv) => Move::castling(Square::from(mv.king_from), Square::from(mv.king_to)),
+            ChessMove::Promotion(mv) => Move::promotion(Square::from(mv.from), Square::from(mv.to), mv.promotion_piece_type),
+            ChessMove::EnPassant(mv) => Move::en_passant(Square::from(mv.from), Square::from(mv.to)),
+            ChessMove::Drop(mv) => Move::drop(mv.piece_type, Square::from(mv.to)),
+        }
+    }
+}
+
+impl From<ChessMove> for Move {
+    fn from(chess_move: ChessMove) -> Self {
+        Move::from(&chess_move)
+    }
+}
+
+impl From<&ExecutedMove> for Move {
+    fn from(executed_move: &ExecutedMove) -> Self {
+        match executed_move {
+            ExecutedMove::Normal { mv, .. } => Move::normal(Square::from(mv.from), Square::from(mv.to)),
+            ExecutedMove::Castling { mv } => Move::castling(Square::from(mv.king_from), Square::from(mv.king_to)),
+            ExecutedMove::Promotion { mv, .. } => Move::promotion(Square::from(mv.from), Square::from(mv.to), mv.promotion_piece_type),
+            ExecutedMove::EnPassant { mv } => Move::en_passant(Square::from(mv.from), Square::from(mv.to)),
+            ExecutedMove::Drop { mv } => Move::drop(mv.piece_type, Square::from(mv.to)),
+            ExecutedMove::Explosion { capture, .. } => Move::from(capture.as_ref()),
+        }
+    }
+}
+
+/// UCI notation, e.g. `e2e4`, `e7e8q` or `P@e4`
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(piece_type) = self.drop_piece() {
+            return write!(f, "{}@{}", piece_type.to_string().to_uppercase(), self.to_coords());
+        }
+        write!(f, "{}{}", self.from_coords(), self.to_coords())?;
+        if let Some(piece_type) = self.promotion_piece() {
+            write!(f, "{}", piece_type)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::Colour;
+    use crate::enums::moves::CastlingMove;
+
+    #[test]
+    fn test_move_fits_in_two_bytes() {
+        assert_eq!(std::mem::size_of::<Move>(), 2);
+    }
+
+    #[test]
+    fn test_pack_and_unpack_fields() {
+        // e7 (52) -> e8 (60), promoting to a knight
+        let mv = Move::promotion(Square::E7, Square::E8, PieceType::Knight);
+        assert_eq!(mv.from_sq().index(), 52);
+        assert_eq!(mv.to_sq().index(), 60);
+        assert_eq!(mv.kind(), MoveKind::Promotion);
+        assert_eq!(mv.promotion_piece(), Some(PieceType::Knight));
+        assert_eq!(mv.to_string(), "e7e8n");
+
+        // h8 -> h1 uses every square bit
+        let mv = Move::normal(Square::H8, Square::H1);
+        assert_eq!((mv.from_sq(), mv.to_sq()), (Square::H8, Square::H1));
+        assert_eq!(mv.kind(), MoveKind::Normal);
+        assert_eq!(mv.promotion_piece(), None);
+
+        assert_eq!(Move::from_raw(mv.raw()), mv);
+        assert!(Move::NULL.is_null());
+        assert!(!mv.is_null());
+    }
+
+    #[test]
+    fn test_drop_encoding() {
+        let mv = Move::drop(PieceType::Pawn, Square::A1);
+        assert!(!mv.is_null());
+        assert_eq!(mv.kind(), MoveKind::Drop);
+        assert_eq!((mv.drop_piece(), mv.to_sq()), (Some(PieceType::Pawn), Square::A1));
+        assert_eq!(mv.promotion_piece(), None);
+        assert_eq!(Move::drop(PieceType::Queen, Square::H8).to_string(), "Q@h8");
+
+        assert_eq!(Move::NULL.kind(), MoveKind::Normal);
+        assert_eq!(Move::NULL.drop_piece(), None);
+        assert_eq!(Move::normal(Square::E2, Square::E4).drop_piece(), None);
+        // A Chess960 king can castle without moving, which must not read as a drop
+        assert_eq!(Move::castling(Square::G1, Square::G1).kind(), MoveKind::Castling);
+    }
+
+    #[test]
+    fn test_round_trip_through_chess_move() {
+        // Position with castling, en passant and promotion all available to white
+        let mut game = Game::new();
+        game.set_fenstr("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1");
+
+        let moves = [
+            ChessMove::Normal(NormalMove {
+                colour: Colour::White,
+                piece_type: PieceType::Rook,
+                from: Coords::new(1, File::A),
+                to: Coords::new(5, File::A),
+            }),
+            ChessMove::Castling(CastlingMove {
+                colour: Colour::White,
+                king_from: Coords::new(1, File::E),
+                king_to: Coords::new(1, File::G),
+                rook_from: Coords::new(1, File::H),
+                rook_to: Coords::new(1, File::F),
+            }),
+            ChessMove::Castling(CastlingMove {
+                colour: Colour::White,
+                king_from: Coords::new(1, File::E),
+                king_to: Coords::new(1, File::C),
+                rook_from: Coords::new(1, File::A),
+                rook_to: Coords::new(1, File::D),
+            }),
+            ChessMove::EnPassant(EnPassantMove {
+                colour: Colour::White,
+                from: Coords::new(5, File::E),
+                to: Coords::new(6, File::D),
+                captured_coords: Coords::new(5, File::D),
+            }),
+            ChessMove::Promotion(PromotionMove {
+                colour: Colour::White,
+                from: Coords::new(7, File::B),
+                to: Coords::new(8, File::A),
+                promotion_piece_type: PieceType::Queen,
+            }),
+        ];
+
+        for chess_move in moves {
+            let mv = Move::from(&chess_move);
+            assert_eq!(mv.to_chess_move(&game), chess_move);
+        }
+    }
+
+    #[test]
+    fn test_from_executed_move() {
+        let mut game = Game::new();
+        let chess_move = ChessMove::Normal(NormalMove {
+            colour: Colour::White,
+            piece_type: PieceType::Pawn,
+            from: Coords::new(2, File::E),
+            to: Coords::new(4, File::E),
+        });
+        game.make_move(&chess_move);
+
+        assert_eq!(Move::from(game.get_last_move().unwrap()), Move::from(&chess_move));
+    }
+}
@@ -1,3 +1,5 @@
 pub mod move_ray;
 pub mod move_generator;
-pub mod move_parser;
\ No newline at end of file
+pub mod move_parser;
+pub mod compact_move;
+pub mod perft;
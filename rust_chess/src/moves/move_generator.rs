@@ -1,30 +1,73 @@
+use std::sync::Arc;
+
 use crate::game_classes::board_classes::magic_bitboard::{self, MAGIC_TABLES};
 use crate::game_classes::board_classes::bit_board::BitBoard;
-use crate::game_classes::board_classes::piece_attacks::{WHITE_PAWN_ATTACKS, BLACK_PAWN_ATTACKS, KNIGHT_ATTACKS, KING_ATTACKS};
+use crate::game_classes::board_classes::piece_attacks::{WHITE_PAWN_ATTACKS, BLACK_PAWN_ATTACKS, KNIGHT_ATTACKS, KING_ATTACKS, BETWEEN, LINE};
 use crate::enums::moves::{EnPassantMove, NormalMove, PromotionMove, CastlingMove};
 use crate::enums::{ChessMove, PieceType, Colour, File};
 use crate::game_classes::game::Game;
 use crate::game_classes::game_state::CastlingRights;
+use crate::moves::compact_move::{Move, MoveKind};
 use crate::moves::move_ray::MoveRay;
 use crate::piece::Piece;
 use crate::coords::Coords;
+use crate::square::Square;
 
 const MAX_MOVES: usize = 218;
 const MAX_CASTLING_MOVES: usize = 4;
 
+fn lowest_square(bits: u64) -> Square {
+    Square::new(bits.trailing_zeros() as u8)
+}
+
+// Take the lowest set square off `bits`
+fn pop_square(bits: &mut u64) -> Square {
+    let square = lowest_square(*bits);
+    *bits &= *bits - 1;
+    square
+}
+
 pub struct MoveGenerator;
 
 impl MoveGenerator {
     pub fn init() {
         let _ = &*MAGIC_TABLES;
-        let _ = &*WHITE_PAWN_ATTACKS;
-        let _ = &*BLACK_PAWN_ATTACKS;
-        let _ = &*KNIGHT_ATTACKS;
-        let _ = &*KING_ATTACKS;
+        let _ = &*BETWEEN;
+        let _ = &*LINE;
     }
 
-    /// Generate legal moves into a preallocated buffer
+    /// Generate legal moves into a preallocated buffer, under the rules of the game's variant
     pub fn generate_legal_moves_into(
+        game: &mut Game,
+        player: Colour,
+        magic_bitboard: bool,
+        out_moves: &mut Vec<Move>
+    ) {
+        let variant = Arc::clone(game.variant());
+        variant.generate_legal_moves_into(game, player, magic_bitboard, out_moves);
+    }
+
+    /// Legal moves under the orthodox rules, which variants build on
+    pub fn generate_standard_legal_moves_into(
+        game: &mut Game,
+        player: Colour,
+        magic_bitboard: bool,
+        out_moves: &mut Vec<Move>
+    ) {
+        if magic_bitboard {
+            Self::generate_legal_moves_magic_bitboards_into(game, player, out_moves);
+        } else {
+            let mut chess_moves = Vec::with_capacity(MAX_MOVES);
+            Self::generate_legal_moves_by_filtering_into(game, player, false, &mut chess_moves);
+
+            out_moves.clear();
+            out_moves.extend(chess_moves.iter().map(Move::from));
+        }
+    }
+
+    /// Generate legal moves by making and undoing every pseudo-legal move.
+    /// Slow, but kept as a reference implementation for perft comparisons.
+    pub fn generate_legal_moves_by_filtering_into(
         game: &mut Game,
         player: Colour,
         magic_bitboard: bool,
@@ -53,6 +96,63 @@ impl MoveGenerator {
         Self::generate_castling_moves_into(game, player, magic_bitboard, out_moves);
     }
 
+    /// Every move `player`'s pieces could make, whether or not it leaves their king attacked,
+    /// for variants that decide legality their own way. Castling still has to pass the orthodox
+    /// checks and is only added when `with_castling` is set.
+    pub fn generate_pseudo_legal_compact_moves_into(
+        game: &Game,
+        player: Colour,
+        with_castling: bool,
+        out_moves: &mut Vec<Move>
+    ) {
+        let mut chess_moves = Vec::with_capacity(MAX_MOVES);
+        Self::generate_pseudo_legal_moves_magic_bitboards_into(game, player, &mut chess_moves);
+
+        out_moves.clear();
+        out_moves.extend(chess_moves.iter().map(Move::from));
+
+        if with_castling {
+            for right in [CastlingRights::side(player, true), CastlingRights::side(player, false)] {
+                if let Some(mv) = Self::castling_move(game, right, true) {
+                    out_moves.push(Move::castling(Square::from(mv.king_from), Square::from(mv.king_to)));
+                }
+            }
+        }
+    }
+
+    /// Append `player`'s legal drops of pieces in hand onto empty squares. Pawns can't be dropped
+    /// on the first or last rank, and in check only a drop between the king and a single
+    /// sliding checker is legal.
+    pub fn generate_drop_moves_into(game: &Game, player: Colour, out_moves: &mut Vec<Move>) {
+        let board = game.get_board();
+        let all_occ = board.all_occ().bits();
+
+        let mut targets = !all_occ;
+        let king_bb = board.get_piece_bit_board(Piece { kind: PieceType::King, colour: player }).bits();
+        if king_bb != 0 {
+            let king_sq = lowest_square(king_bb);
+            let checkers = Self::attackers_to(game, king_sq, player.other(), all_occ);
+            targets &= match checkers.count_ones() {
+                0 => u64::MAX,
+                1 => BETWEEN[king_sq.index()][lowest_square(checkers).index()],
+                _ => 0,
+            };
+        }
+
+        let back_ranks = (BitBoard::RANK_1 | BitBoard::RANK_8).bits();
+        let game_state = game.get_game_state();
+        for kind in [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+            if game_state.pocket_count(player, kind) == 0 {
+                continue;
+            }
+
+            let mut kind_targets = if kind == PieceType::Pawn { targets & !back_ranks } else { targets };
+            while kind_targets != 0 {
+                out_moves.push(Move::drop(kind, pop_square(&mut kind_targets)));
+            }
+        }
+    }
+
     pub fn generate_tactical_moves_into(
         game: &mut Game,
         to_move: Colour,
@@ -72,7 +172,7 @@ impl MoveGenerator {
 
         // Filter tactical moves: captures, promotions, en passant, checks
         for mv in pseudo_moves {
-            let is_tactical = Self::is_tactical_move(game, &mv, use_magic);
+            let is_tactical = Self::is_tactical_move(game, Move::from(&mv), use_magic);
 
             if is_tactical {
                 // Optionally, skip moves that leave the king in check
@@ -83,20 +183,252 @@ impl MoveGenerator {
         }
     }
 
-    pub fn is_tactical_move(game: &mut Game, mv: &ChessMove, use_magic: bool) -> bool {
-        let is_tactical = match mv {
-            ChessMove::Normal(nm) => {
-                // Captures
-                game.get_board().get_coords(&nm.to).is_some()
-            }
-            ChessMove::Promotion(_)
-            | ChessMove::EnPassant(_)
-            | ChessMove::Castling(_) => true,
-        } || game.is_check(mv, use_magic);
+    pub fn is_tactical_move(game: &mut Game, mv: Move, use_magic: bool) -> bool {
+        let is_tactical = match mv.kind() {
+            // Captures
+            MoveKind::Normal => game.get_board().all_occ().bits() & mv.to_sq().bit() != 0,
+            MoveKind::Promotion
+            | MoveKind::EnPassant
+            | MoveKind::Castling => true,
+            MoveKind::Drop => false,
+        } || game.is_check(&mv.to_chess_move(game), use_magic);
 
         // Only include moves that don't leave player in check
         is_tactical
     }
+    /// Generate only legal moves using check and pin masks computed up front,
+    /// so no move has to be made and undone to test for self-check.
+    fn generate_legal_moves_magic_bitboards_into(
+        game: &mut Game,
+        player: Colour,
+        out_moves: &mut Vec<Move>
+    ) {
+        out_moves.clear();
+        let board = game.get_board();
+        let opponent = player.other();
+        let all_occ = board.all_occ().bits();
+        let own_occ = board.get_colour_occ(player).bits();
+
+        // Without a king (test positions) there is nothing to keep out of check
+        let king_bb = board.get_piece_bit_board(Piece { kind: PieceType::King, colour: player }).bits();
+        let (king_sq, checkers, pinned) = if king_bb != 0 {
+            let king_sq = lowest_square(king_bb);
+            (Some(king_sq), Self::attackers_to(game, king_sq, opponent, all_occ), Self::pinned_pieces(game, king_sq, player))
+        } else {
+            (None, 0, 0)
+        };
+
+        // King moves: the king itself is removed from the occupancy so it cannot hide behind itself
+        if let Some(king_sq) = king_sq {
+            let occ_without_king = all_occ & !king_sq.bit();
+            let mut targets = KING_ATTACKS[king_sq.index()] & !own_occ;
+
+            while targets != 0 {
+                let to_sq = pop_square(&mut targets);
+
+                if Self::attackers_to(game, to_sq, opponent, occ_without_king) == 0 {
+                    out_moves.push(Move::normal(king_sq, to_sq));
+                }
+            }
+        }
+
+        // In double check only the king can move
+        if checkers.count_ones() > 1 {
+            return;
+        }
+
+        // In single check a move must capture the checker or block the checking ray
+        let check_mask = match king_sq {
+            Some(king_sq) if checkers != 0 => checkers | BETWEEN[king_sq.index()][lowest_square(checkers).index()],
+            _ => !0u64,
+        };
+
+        for piece_type in [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+            let mut pieces = board.get_piece_bit_board(Piece { kind: piece_type, colour: player }).bits();
+
+            while pieces != 0 {
+                let from_sq = pop_square(&mut pieces);
+
+                // A pinned piece may only move along the line through its king and pinner
+                let pin_mask = match king_sq {
+                    Some(king_sq) if pinned & from_sq.bit() != 0 => LINE[king_sq.index()][from_sq.index()],
+                    _ => !0u64,
+                };
+
+                if piece_type == PieceType::Pawn {
+                    Self::generate_legal_pawn_moves_into(game, player, from_sq, king_sq, check_mask & pin_mask, out_moves);
+                    continue;
+                }
+
+                let all_occ_bb = BitBoard::from_bits(all_occ);
+                let attacks = match piece_type {
+                    PieceType::Knight => KNIGHT_ATTACKS[from_sq.index()],
+                    PieceType::Bishop => MAGIC_TABLES.get_bishop_attacks(from_sq.index(), &all_occ_bb).bits(),
+                    PieceType::Rook => MAGIC_TABLES.get_rook_attacks(from_sq.index(), &all_occ_bb).bits(),
+                    PieceType::Queen => {
+                        MAGIC_TABLES.get_bishop_attacks(from_sq.index(), &all_occ_bb).bits()
+                            | MAGIC_TABLES.get_rook_attacks(from_sq.index(), &all_occ_bb).bits()
+                    }
+                    _ => 0,
+                };
+
+                let mut targets = attacks & !own_occ & check_mask & pin_mask;
+                while targets != 0 {
+                    out_moves.push(Move::normal(from_sq, pop_square(&mut targets)));
+                }
+            }
+        }
+
+        if king_sq.is_some() && checkers == 0 {
+            Self::generate_legal_castling_moves_into(game, player, out_moves);
+        }
+    }
+
+    fn generate_legal_pawn_moves_into(
+        game: &Game,
+        player: Colour,
+        from_sq: Square,
+        king_sq: Option<Square>,
+        target_mask: u64,
+        out_moves: &mut Vec<Move>
+    ) {
+        let all_occ = game.get_board().all_occ().bits();
+        let opp_occ = game.get_board().get_colour_occ(player.other()).bits();
+
+        // Ranks are 0-based here
+        let (forward, start_rank, promotion_rank, attacks) = match player {
+            Colour::White => (1, 1, 7, WHITE_PAWN_ATTACKS[from_sq.index()]),
+            Colour::Black => (-1, 6, 0, BLACK_PAWN_ATTACKS[from_sq.index()]),
+        };
+
+        let mut targets = attacks & opp_occ;
+
+        if let Some(single_sq) = from_sq.offset(forward, 0) {
+            if all_occ & single_sq.bit() == 0 {
+                targets |= single_sq.bit();
+
+                if let Some(double_sq) = single_sq.offset(forward, 0) {
+                    if from_sq.rank() == start_rank && all_occ & double_sq.bit() == 0 {
+                        targets |= double_sq.bit();
+                    }
+                }
+            }
+        }
+
+        targets &= target_mask;
+        while targets != 0 {
+            let to_sq = pop_square(&mut targets);
+
+            if to_sq.rank() == promotion_rank {
+                for promotion_type in [PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight] {
+                    out_moves.push(Move::promotion(from_sq, to_sq, promotion_type));
+                }
+            } else {
+                out_moves.push(Move::normal(from_sq, to_sq));
+            }
+        }
+
+        // En passant removes two pawns from the same rank at once, which can expose the king
+        // along that rank, so it is verified against the resulting occupancy instead of the masks
+        let game_state = game.get_game_state();
+        if let (Some(ep_target), Some(captured_coords)) = (game_state.get_en_passant_target(), game_state.get_en_passant_piece_coords()) {
+            let ep_sq = Square::from(ep_target);
+            if attacks & ep_sq.bit() == 0 {
+                return;
+            }
+
+            let captured_sq = Square::from(captured_coords);
+            if let Some(king_sq) = king_sq {
+                let occ_after = (all_occ & !from_sq.bit() & !captured_sq.bit()) | ep_sq.bit();
+                let attackers = Self::attackers_to(game, king_sq, player.other(), occ_after) & !captured_sq.bit();
+                if attackers != 0 {
+                    return;
+                }
+            }
+
+            out_moves.push(Move::en_passant(from_sq, ep_sq));
+        }
+    }
+
+    fn generate_legal_castling_moves_into(game: &mut Game, player: Colour, out_moves: &mut Vec<Move>) {
+        let all_occ = game.get_board().all_occ().bits();
+
+        for right in [CastlingRights::side(player, true), CastlingRights::side(player, false)] {
+            if let Some(mv) = Self::castling_move(game, right, true) {
+                // In Chess960 the rook can be what shields the king's destination from an attack along the back rank
+                let (king_to, rook_from) = (Square::from(mv.king_to), Square::from(mv.rook_from));
+                if Self::attackers_to(game, king_to, player.other(), all_occ & !rook_from.bit()) == 0 {
+                    out_moves.push(Move::castling(Square::from(mv.king_from), king_to));
+                }
+            }
+        }
+    }
+
+    /// Bitboard of the `attacker` pieces that attack `square` given the occupancy `occ`
+    pub fn attackers_to(game: &Game, square: Square, attacker: Colour, occ: u64) -> u64 {
+        let board = game.get_board();
+        let pieces = |kind: PieceType| board.get_piece_bit_board(Piece { kind, colour: attacker }).bits();
+        let square = square.index();
+
+        // A square is attacked by an enemy pawn wherever a friendly pawn on it would attack
+        let pawn_attacks = match attacker {
+            Colour::White => BLACK_PAWN_ATTACKS[square],
+            Colour::Black => WHITE_PAWN_ATTACKS[square],
+        };
+
+        let occ_bb = BitBoard::from_bits(occ);
+        let diagonal = MAGIC_TABLES.get_bishop_attacks(square, &occ_bb).bits();
+        let orthogonal = MAGIC_TABLES.get_rook_attacks(square, &occ_bb).bits();
+
+        (pawn_attacks & pieces(PieceType::Pawn))
+            | (KNIGHT_ATTACKS[square] & pieces(PieceType::Knight))
+            | (KING_ATTACKS[square] & pieces(PieceType::King))
+            | (diagonal & (pieces(PieceType::Bishop) | pieces(PieceType::Queen)))
+            | (orthogonal & (pieces(PieceType::Rook) | pieces(PieceType::Queen)))
+    }
+
+    /// Squares `piece` on `square` attacks given the occupancy `occ`, whatever stands on them
+    pub fn attacks_from(piece: Piece, square: Square, occ: u64) -> u64 {
+        let sq = square.index();
+        let occ_bb = BitBoard::from_bits(occ);
+        match piece.kind {
+            PieceType::Pawn => match piece.colour {
+                Colour::White => WHITE_PAWN_ATTACKS[sq],
+                Colour::Black => BLACK_PAWN_ATTACKS[sq],
+            },
+            PieceType::Knight => KNIGHT_ATTACKS[sq],
+            PieceType::Bishop => MAGIC_TABLES.get_bishop_attacks(sq, &occ_bb).bits(),
+            PieceType::Rook => MAGIC_TABLES.get_rook_attacks(sq, &occ_bb).bits(),
+            PieceType::Queen => MAGIC_TABLES.get_bishop_attacks(sq, &occ_bb).bits() | MAGIC_TABLES.get_rook_attacks(sq, &occ_bb).bits(),
+            PieceType::King => KING_ATTACKS[sq],
+        }
+    }
+
+    /// Bitboard of `player`'s pieces that are pinned to the king on `king_sq`
+    pub fn pinned_pieces(game: &Game, king_sq: Square, player: Colour) -> u64 {
+        let board = game.get_board();
+        let opponent = player.other();
+        let all_occ = board.all_occ().bits();
+        let own_occ = board.get_colour_occ(player).bits();
+        let enemy = |kind: PieceType| board.get_piece_bit_board(Piece { kind, colour: opponent }).bits();
+
+        let empty = BitBoard::new();
+        let mut snipers = (MAGIC_TABLES.get_rook_attacks(king_sq.index(), &empty).bits() & (enemy(PieceType::Rook) | enemy(PieceType::Queen)))
+            | (MAGIC_TABLES.get_bishop_attacks(king_sq.index(), &empty).bits() & (enemy(PieceType::Bishop) | enemy(PieceType::Queen)));
+
+        let mut pinned = 0u64;
+        while snipers != 0 {
+            let sniper_sq = pop_square(&mut snipers);
+
+            let blockers = BETWEEN[king_sq.index()][sniper_sq.index()] & all_occ;
+            if blockers.count_ones() == 1 && blockers & own_occ != 0 {
+                pinned |= blockers;
+            }
+        }
+
+        pinned
+    }
+
     fn generate_pseudo_legal_moves_magic_bitboards_into(
         game: &Game,
         player: Colour,
@@ -310,78 +642,51 @@ impl MoveGenerator {
     }
 
     fn generate_castling_moves_into(game: &mut Game, colour: Colour, magic_bitboard: bool, out_moves: &mut Vec<ChessMove>) {
-        let rank = if colour == Colour::White { 1 } else { 8 };
-        let king_start = Coords::new(rank, File::E);
-        let king = Piece { kind: PieceType::King, colour };
-
-        if let Some(piece) = game.get_board().get_coords(&king_start) {
-            if piece != king { return; }
-        } else { return; }
-
-        if Self::can_castle_kingside(game, colour, &king_start, magic_bitboard) {
-            out_moves.push(ChessMove::Castling(CastlingMove {
-                colour,
-                king_from: king_start,
-                king_to: Coords::new(rank, File::G),
-                rook_from: Coords::new(rank, File::H),
-                rook_to: Coords::new(rank, File::F),
-            }));
-        }
-
-        if Self::can_castle_queenside(game, colour, &king_start, magic_bitboard) {
-            out_moves.push(ChessMove::Castling(CastlingMove {
-                colour,
-                king_from: king_start,
-                king_to: Coords::new(rank, File::C),
-                rook_from: Coords::new(rank, File::A),
-                rook_to: Coords::new(rank, File::D),
-            }));
+        for right in [CastlingRights::side(colour, true), CastlingRights::side(colour, false)] {
+            if let Some(mv) = Self::castling_move(game, right, magic_bitboard) {
+                // In Chess960 the rook can be what shields the king's destination from an attack along the back rank
+                let chess_move = ChessMove::Castling(mv);
+                if !Self::does_leave_player_in_check(game, &chess_move, magic_bitboard) {
+                    out_moves.push(chess_move);
+                }
+            }
         }
     }
 
-    fn can_castle_kingside(game: &mut Game, colour: Colour, king_start: &Coords, magic_bitboard: bool) -> bool {
-        let rank = king_start.rank;
-        let rights_ok = match colour {
-            Colour::White => game.get_game_state().can_castle(CastlingRights::WHITE_KINGSIDE),
-            Colour::Black => game.get_game_state().can_castle(CastlingRights::BLACK_KINGSIDE),
-        };
-        if !rights_ok { return false; }
-
-        let rook_coords = Coords::new(rank, File::H);
-        let rook = Piece { kind: PieceType::Rook, colour };
-        if game.get_board().get_coords(&rook_coords) != Some(rook) { return false; }
-
-        if game.get_board().get_coords(&Coords::new(rank, File::F)).is_some() ||
-           game.get_board().get_coords(&Coords::new(rank, File::G)).is_some() { return false; }
-
-        if Self::is_square_under_attack(game, &colour.other(), king_start, magic_bitboard) ||
-           Self::is_square_under_attack(game, &colour.other(), &Coords::new(rank, File::F), magic_bitboard) ||
-           Self::is_square_under_attack(game, &colour.other(), &Coords::new(rank, File::G), magic_bitboard) { return false; }
+    /// Castling with the single right `right`, if the right is held, the king and rook are on
+    /// their start squares, nothing else stands between them and their destinations, and the
+    /// king does not start on, pass over or land on an attacked square
+    fn castling_move(game: &Game, right: CastlingRights, magic_bitboard: bool) -> Option<CastlingMove> {
+        let game_state = game.get_game_state();
+        if !game_state.can_castle(right) {
+            return None;
+        }
 
-        true
-    }
+        let mv = game_state.castling_move(right);
+        let board = game.get_board();
+        if board.get_coords(&mv.king_from) != Some(Piece { kind: PieceType::King, colour: mv.colour })
+            || board.get_coords(&mv.rook_from) != Some(Piece { kind: PieceType::Rook, colour: mv.colour }) {
+            return None;
+        }
 
-    fn can_castle_queenside(game: &mut Game, colour: Colour, king_start: &Coords, magic_bitboard: bool) -> bool {
-        let rank = king_start.rank;
-        let rights_ok = match colour {
-            Colour::White => game.get_game_state().can_castle(CastlingRights::WHITE_QUEENSIDE),
-            Colour::Black => game.get_game_state().can_castle(CastlingRights::BLACK_QUEENSIDE),
+        let span = |a: Coords, b: Coords| {
+            let (low, high) = (a.file.value().min(b.file.value()), a.file.value().max(b.file.value()));
+            (low..=high).map(move |file| Coords::new(a.rank, File::from_usize(file).unwrap()))
         };
-        if !rights_ok { return false; }
 
-        let rook_coords = Coords::new(rank, File::A);
-        let rook = Piece { kind: PieceType::Rook, colour };
-        if game.get_board().get_coords(&rook_coords) != Some(rook) { return false; }
-
-        if game.get_board().get_coords(&Coords::new(rank, File::B)).is_some() ||
-           game.get_board().get_coords(&Coords::new(rank, File::C)).is_some() ||
-           game.get_board().get_coords(&Coords::new(rank, File::D)).is_some() { return false; }
+        let blocked = span(mv.king_from, mv.king_to).chain(span(mv.rook_from, mv.rook_to))
+            .any(|coords| coords != mv.king_from && coords != mv.rook_from && board.get_coords(&coords).is_some());
+        if blocked {
+            return None;
+        }
 
-        if Self::is_square_under_attack(game, &colour.other(), king_start, magic_bitboard) ||
-           Self::is_square_under_attack(game, &colour.other(), &Coords::new(rank, File::D), magic_bitboard) ||
-           Self::is_square_under_attack(game, &colour.other(), &Coords::new(rank, File::C), magic_bitboard) { return false; }
+        let attacked = span(mv.king_from, mv.king_to)
+            .any(|coords| Self::is_square_under_attack(game, &mv.colour.other(), &coords, magic_bitboard));
+        if attacked {
+            return None;
+        }
 
-        true
+        Some(mv)
     }
 
     pub fn get_attacked_squares(game: &Game, attacker: Colour) -> Vec<Coords> {
@@ -473,14 +778,12 @@ impl MoveGenerator {
     }
 
     pub fn is_square_under_attack(game: &Game, attacker: &Colour, coords: &Coords, magic_bitboard: bool) -> bool {
-        let moves = if magic_bitboard {
-            Self::get_attacked_squares_magic_bitboard(game, *attacker)
+        if magic_bitboard {
+            let all_occ = game.get_board().all_occ().bits();
+            return Self::attackers_to(game, Square::from(coords), *attacker, all_occ) != 0;
         }
-        else {
-            Self::get_attacked_squares(game, *attacker)
-        };
 
-        moves.iter().any(|m| *m == *coords)
+        Self::get_attacked_squares(game, *attacker).contains(coords)
     }
 }
 
@@ -1126,12 +1429,12 @@ mod tests {
         }
 
         assert!(
-            !legal_moves.iter().any(|m| m.to() == Coords::new(3, File::F)),
+            !legal_moves.iter().any(|m| m.to_coords() == Coords::new(3, File::F)),
             "Pawn move to f3 should be illegal because it leaves the king in check"
         );
 
         assert!(
-            !legal_moves.iter().any(|m| m.to() == Coords::new(4, File::F)),
+            !legal_moves.iter().any(|m| m.to_coords() == Coords::new(4, File::F)),
             "Pawn move to f4 should be illegal because it leaves the king in check"
         );
     }
@@ -1147,12 +1450,12 @@ mod tests {
         MoveGenerator::generate_legal_moves_into(&mut game, Colour::White, true, &mut legal_moves);
 
         assert!(
-            !legal_moves.iter().any(|m| m.to() == Coords::new(3, File::F)),
+            !legal_moves.iter().any(|m| m.to_coords() == Coords::new(3, File::F)),
             "Pawn move to f3 should be illegal because it leaves the king in check"
         );
 
         assert!(
-            !legal_moves.iter().any(|m| m.to() == Coords::new(4, File::F)),
+            !legal_moves.iter().any(|m| m.to_coords() == Coords::new(4, File::F)),
             "Pawn move to f4 should be illegal because it leaves the king in check"
         );
     }
@@ -1169,7 +1472,7 @@ mod tests {
         MoveGenerator::generate_legal_moves_into(&mut game, Colour::White, false, &mut legal_moves);
 
         assert!(
-            !legal_moves.iter().any(|m| m.to() == Coords::new(3, File::E)),
+            !legal_moves.iter().any(|m| m.to_coords() == Coords::new(3, File::E)),
             "Pawn move to e3 should be illegal because it leaves the king in check"
         );
     }
@@ -1189,7 +1492,7 @@ mod tests {
             println!("{}", mv);
         }
         assert!(
-            !legal_moves.iter().any(|m| m.to() == Coords::new(3, File::E)),
+            !legal_moves.iter().any(|m| m.to_coords() == Coords::new(3, File::E)),
             "Pawn move to e3 should be illegal because it leaves the king in check"
         );
     }
@@ -1231,12 +1534,12 @@ mod tests {
             println!("{}", mv);
         }
 
-        assert!(!moves.contains(&ChessMove::Castling(CastlingMove { 
+        assert!(!moves.contains(&Move::from(ChessMove::Castling(CastlingMove { 
             colour: Colour::White, 
             king_from: Coords { rank: 1, file: File::E },
             king_to: Coords { rank: 1, file: File::C },
             rook_from: Coords { rank: 1, file: File::A },
-            rook_to: Coords { rank: 1, file: File::D } })),
+            rook_to: Coords { rank: 1, file: File::D } }))),
             "Should not be able to castle queenside"
         )
     }
@@ -1258,13 +1561,56 @@ mod tests {
             println!("{}", mv);
         }
 
-        assert!(!moves.contains(&ChessMove::Castling(CastlingMove { 
+        assert!(!moves.contains(&Move::from(ChessMove::Castling(CastlingMove { 
             colour: Colour::White, 
             king_from: Coords { rank: 1, file: File::E },
             king_to: Coords { rank: 1, file: File::C },
             rook_from: Coords { rank: 1, file: File::A },
-            rook_to: Coords { rank: 1, file: File::D } })),
+            rook_to: Coords { rank: 1, file: File::D } }))),
             "Should not be able to castle queenside"
         )
     }
+
+    #[test]
+    fn test_chess960_castling_legality() {
+        // King f1, queenside rook b1: castling ends with the king on c1 and the rook on d1
+        let queenside = Move::castling(Square::F1, Square::C1);
+
+        for (fen, legal) in [
+            ("4k3/8/8/8/8/8/8/1R3K2 w B - 0 1", true),
+            // The rook on b1 is all that stops the black rook reaching c1
+            ("4k3/8/8/8/8/8/8/rR3K2 w B - 0 1", false),
+            // d1 is between the king and its destination
+            ("4k3/8/8/8/8/8/8/1R1n1K2 w B - 0 1", false),
+            ("4k3/8/8/8/8/8/3r4/1R3K2 w B - 0 1", false),
+        ] {
+            let mut game = Game::new();
+            game.set_fenstr(fen);
+
+            for magic_bitboard in [false, true] {
+                let mut moves = Vec::new();
+                MoveGenerator::generate_legal_moves_into(&mut game, Colour::White, magic_bitboard, &mut moves);
+                assert_eq!(moves.contains(&queenside), legal, "{} (magic bitboards: {})", fen, magic_bitboard);
+            }
+        }
+    }
+
+    #[test]
+    fn test_chess960_castling_onto_own_squares() {
+        // King f1 and rook g1 swap places castling kingside
+        let mut game = Game::new();
+        game.set_fenstr("4k3/8/8/8/8/8/8/5KR1 w G - 0 1");
+
+        let mut moves = Vec::new();
+        MoveGenerator::generate_legal_moves_into(&mut game, Colour::White, true, &mut moves);
+        let castling = Move::castling(Square::F1, Square::G1);
+        assert!(moves.contains(&castling));
+
+        game.make_compact_move(castling);
+        assert_eq!(game.get_board().piece_at(Square::G1), Some(Piece { kind: PieceType::King, colour: Colour::White }));
+        assert_eq!(game.get_board().piece_at(Square::F1), Some(Piece { kind: PieceType::Rook, colour: Colour::White }));
+        assert!(!game.get_game_state().can_castle(CastlingRights::WHITE_KINGSIDE));
+        game.undo_last_move();
+        assert!(game.get_game_state().can_castle(CastlingRights::WHITE_KINGSIDE));
+    }
 }
@@ -1,7 +1,9 @@
-use crate::enums::{File, ChessMove, Colour, PieceType};
+use crate::enums::{ChessMove, PieceType};
 use crate::coords::Coords;
 use crate::game_classes::game::Game;
-use crate::enums::moves::{CastlingMove, NormalMove, PromotionMove, EnPassantMove};
+use crate::game_classes::game_state::CastlingRights;
+use crate::enums::moves::{NormalMove, PromotionMove, EnPassantMove, DropMove};
+use crate::piece::Piece;
 
 pub struct MoveParser;
 
@@ -9,6 +11,20 @@ impl MoveParser {
     pub fn parse_str(move_str: &str, game: &Game) -> Option<ChessMove> {
         let mv = move_str.trim();
 
+        // Drop of a piece in hand, e.g. `P@e4`
+        if let Some((piece_str, to_str)) = mv.split_once('@') {
+            let mut piece_chars = piece_str.chars();
+            let piece_type = match (piece_chars.next(), piece_chars.next()) {
+                (Some(ch), None) => PieceType::from_char(ch).filter(|&kind| kind != PieceType::King)?,
+                _ => return None,
+            };
+            return Some(ChessMove::Drop(DropMove {
+                colour: game.get_game_state().get_turn(),
+                piece_type,
+                to: Coords::from_str(to_str)?,
+            }));
+        }
+
         if mv.len() < 4 {
             return None;
         }
@@ -18,29 +34,17 @@ impl MoveParser {
         let colour = game.get_game_state().get_turn();
         let piece = game.get_board().get_coords(&from)?;
 
-        // Castling
-        if piece.kind == PieceType::King && (to.file - from.file).abs() == 2 {
-            let (rook_from, rook_to) = if to.file.value() > from.file.value() {
-                // Kingside
-                match colour {
-                    Colour::White => (Coords::new(1, File::H), Coords::new(1, File::F)),
-                    Colour::Black => (Coords::new(8, File::H), Coords::new(8, File::F)),
-                }
-            } else {
-                // Queenside
-                match colour {
-                    Colour::White => (Coords::new(1, File::A), Coords::new(1, File::D)),
-                    Colour::Black => (Coords::new(8, File::A), Coords::new(8, File::D)),
-                }
-            };
+        // Castling, written as the king taking its own rook or, outside Chess960, as the king
+        // moving two files
+        let game_state = game.get_game_state();
+        if piece.kind == PieceType::King {
+            let takes_own_rook = game.get_board().get_coords(&to) == Some(Piece { kind: PieceType::Rook, colour });
+            let two_files = !game_state.is_chess960() && (to.file - from.file).abs() == 2;
 
-            return Some(ChessMove::Castling(CastlingMove {
-                colour,
-                king_from: from,
-                king_to: to,
-                rook_from,
-                rook_to,
-            }));
+            if takes_own_rook || two_files {
+                let right = CastlingRights::side(colour, to.file - from.file > 0);
+                return Some(ChessMove::Castling(game_state.castling_move(right)));
+            }
         }
 
         // Promotion
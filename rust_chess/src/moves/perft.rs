@@ -0,0 +1,43 @@
+use crate::game_classes::game::Game;
+use crate::moves::compact_move::Move;
+use crate::moves::move_generator::MoveGenerator;
+
+/// Count the leaf nodes of the legal move tree to the given depth
+pub fn perft(game: &mut Game, depth: usize, magic_bitboard: bool) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let colour = game.get_game_state().get_turn();
+    let mut moves: Vec<Move> = Vec::with_capacity(256);
+    MoveGenerator::generate_legal_moves_into(game, colour, magic_bitboard, &mut moves);
+
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for &mv in &moves {
+        game.make_compact_move(mv);
+        nodes += perft(game, depth - 1, magic_bitboard);
+        game.undo_last_move();
+    }
+
+    nodes
+}
+
+/// Perft split by root move, useful for tracking down move generation bugs
+pub fn perft_divide(game: &mut Game, depth: usize, magic_bitboard: bool) -> Vec<(String, u64)> {
+    let colour = game.get_game_state().get_turn();
+    let mut moves: Vec<Move> = Vec::with_capacity(256);
+    MoveGenerator::generate_legal_moves_into(game, colour, magic_bitboard, &mut moves);
+
+    let mut out = Vec::with_capacity(moves.len());
+    for &mv in &moves {
+        game.make_compact_move(mv);
+        out.push((mv.to_string(), perft(game, depth.saturating_sub(1), magic_bitboard)));
+        game.undo_last_move();
+    }
+
+    out
+}
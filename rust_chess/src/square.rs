@@ -0,0 +1,198 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::coords::Coords;
+use crate::enums::File;
+
+/// A board square as its index, a1 is 0, h1 7 and h8 63
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Square(u8);
+
+const fn distance_table() -> [[u8; 64]; 64] {
+    let mut table = [[0; 64]; 64];
+    let mut a: usize = 0;
+    while a < 64 {
+        let mut b: usize = 0;
+        while b < 64 {
+            let rank_distance = (a / 8).abs_diff(b / 8);
+            let file_distance = (a % 8).abs_diff(b % 8);
+            table[a][b] = if rank_distance > file_distance { rank_distance } else { file_distance } as u8;
+            b += 1;
+        }
+        a += 1;
+    }
+    table
+}
+
+/// King move distance between every pair of squares
+pub static DISTANCE: [[u8; 64]; 64] = distance_table();
+
+impl Square {
+    pub const A1: Square = Square(0); pub const B1: Square = Square(1); pub const C1: Square = Square(2); pub const D1: Square = Square(3); pub const E1: Square = Square(4); pub const F1: Square = Square(5); pub const G1: Square = Square(6); pub const H1: Square = Square(7);
+    pub const A2: Square = Square(8); pub const B2: Square = Square(9); pub const C2: Square = Square(10); pub const D2: Square = Square(11); pub const E2: Square = Square(12); pub const F2: Square = Square(13); pub const G2: Square = Square(14); pub const H2: Square = Square(15);
+    pub const A3: Square = Square(16); pub const B3: Square = Square(17); pub const C3: Square = Square(18); pub const D3: Square = Square(19); pub const E3: Square = Square(20); pub const F3: Square = Square(21); pub const G3: Square = Square(22); pub const H3: Square = Square(23);
+    pub const A4: Square = Square(24); pub const B4: Square = Square(25); pub const C4: Square = Square(26); pub const D4: Square = Square(27); pub const E4: Square = Square(28); pub const F4: Square = Square(29); pub const G4: Square = Square(30); pub const H4: Square = Square(31);
+    pub const A5: Square = Square(32); pub const B5: Square = Square(33); pub const C5: Square = Square(34); pub const D5: Square = Square(35); pub const E5: Square = Square(36); pub const F5: Square = Square(37); pub const G5: Square = Square(38); pub const H5: Square = Square(39);
+    pub const A6: Square = Square(40); pub const B6: Square = Square(41); pub const C6: Square = Square(42); pub const D6: Square = Square(43); pub const E6: Square = Square(44); pub const F6: Square = Square(45); pub const G6: Square = Square(46); pub const H6: Square = Square(47);
+    pub const A7: Square = Square(48); pub const B7: Square = Square(49); pub const C7: Square = Square(50); pub const D7: Square = Square(51); pub const E7: Square = Square(52); pub const F7: Square = Square(53); pub const G7: Square = Square(54); pub const H7: Square = Square(55);
+    pub const A8: Square = Square(56); pub const B8: Square = Square(57); pub const C8: Square = Square(58); pub const D8: Square = Square(59); pub const E8: Square = Square(60); pub const F8: Square = Square(61); pub const G8: Square = Square(62); pub const H8: Square = Square(63);
+
+    /// Panics if `index` is not below 64
+    pub const fn new(index: u8) -> Self {
+        assert!(index < 64, "Square index out of range");
+        Square(index)
+    }
+
+    /// `rank` and `file` both 0-based
+    pub const fn from_rank_file(rank: u8, file: u8) -> Self {
+        assert!(rank < 8 && file < 8, "Rank or file out of range");
+        Square(rank * 8 + file)
+    }
+
+    pub const fn from_index(index: usize) -> Self {
+        Self::new(index as u8)
+    }
+
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// 0-based rank
+    pub const fn rank(self) -> u8 {
+        self.0 >> 3
+    }
+
+    /// 0-based file
+    pub const fn file(self) -> u8 {
+        self.0 & 7
+    }
+
+    pub const fn bit(self) -> u64 {
+        1u64 << self.0
+    }
+
+    /// The same square seen from the other side, a1 becomes a8
+    pub const fn flip(self) -> Self {
+        Square(self.0 ^ 56)
+    }
+
+    /// Mirrored across the middle files, a1 becomes h1
+    pub const fn mirror(self) -> Self {
+        Square(self.0 ^ 7)
+    }
+
+    pub const fn rank_distance(self, other: Square) -> u8 {
+        self.rank().abs_diff(other.rank())
+    }
+
+    pub const fn file_distance(self, other: Square) -> u8 {
+        self.file().abs_diff(other.file())
+    }
+
+    /// Number of king moves between the two squares
+    pub fn distance(self, other: Square) -> u8 {
+        DISTANCE[self.index()][other.index()]
+    }
+
+    /// The square `rank_diff` ranks and `file_diff` files away, if it is on the board
+    pub const fn offset(self, rank_diff: i8, file_diff: i8) -> Option<Square> {
+        let rank = self.rank() as i8 + rank_diff;
+        let file = self.file() as i8 + file_diff;
+        if rank >= 0 && rank < 8 && file >= 0 && file < 8 {
+            Some(Square((rank * 8 + file) as u8))
+        } else {
+            None
+        }
+    }
+
+    /// Iterate over all squares from a1 to h8
+    pub fn all() -> impl Iterator<Item = Square> {
+        (0..64).map(Square)
+    }
+
+    pub fn to_coords(self) -> Coords {
+        Coords::from(self)
+    }
+}
+
+impl From<Coords> for Square {
+    fn from(coords: Coords) -> Self {
+        Square::from_rank_file(coords.rank - 1, coords.file.value() as u8)
+    }
+}
+
+impl From<&Coords> for Square {
+    fn from(coords: &Coords) -> Self {
+        Square::from(*coords)
+    }
+}
+
+impl From<Square> for Coords {
+    fn from(square: Square) -> Self {
+        Coords { rank: square.rank() + 1, file: File::from_usize(square.file() as usize).unwrap() }
+    }
+}
+
+impl FromStr for Square {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Coords::from_str(s).map(Square::from).ok_or_else(|| format!("Invalid square: {}", s))
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", (b'a' + self.file()) as char, self.rank() + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_and_file() {
+        assert_eq!(Square::E4.index(), 28);
+        assert_eq!((Square::E4.rank(), Square::E4.file()), (3, 4));
+        assert_eq!(Square::from_rank_file(7, 0), Square::A8);
+        assert_eq!(Square::E4.to_string(), "e4");
+        assert_eq!("h7".parse::<Square>(), Ok(Square::H7));
+        assert!("i1".parse::<Square>().is_err());
+    }
+
+    #[test]
+    fn test_flip_and_mirror() {
+        assert_eq!(Square::A1.flip(), Square::A8);
+        assert_eq!(Square::E2.flip(), Square::E7);
+        assert_eq!(Square::A1.mirror(), Square::H1);
+        assert_eq!(Square::C6.mirror(), Square::F6);
+        assert!(Square::all().all(|square| square.flip().flip() == square && square.mirror().mirror() == square));
+    }
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(Square::A1.distance(Square::H8), 7);
+        assert_eq!(Square::E4.distance(Square::F6), 2);
+        assert_eq!(Square::E4.distance(Square::E4), 0);
+        assert_eq!(Square::B2.rank_distance(Square::G4), 2);
+        assert_eq!(Square::B2.file_distance(Square::G4), 5);
+    }
+
+    #[test]
+    fn test_offset() {
+        assert_eq!(Square::E4.offset(1, -1), Some(Square::D5));
+        assert_eq!(Square::H4.offset(0, 1), None);
+        assert_eq!(Square::A1.offset(-1, 0), None);
+    }
+
+    #[test]
+    fn test_coords_round_trip() {
+        for square in Square::all() {
+            let coords = Coords::from(square);
+            assert_eq!(coords.to_index(), square.index());
+            assert_eq!(Square::from(coords), square);
+            assert_eq!(coords.to_string(), square.to_string());
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use strum::IntoEnumIterator;
+
+use crate::enums::{Colour, PieceType};
+use crate::game_classes::game::Game;
+use crate::piece::Piece;
+use crate::syzygy::table::{BLACK_PIECE, PAWN_CODE};
+
+// Table names list pieces from most to least valuable
+const NAME_ORDER: [(char, u8); 6] = [('K', 6), ('Q', 5), ('R', 4), ('B', 3), ('N', 2), ('P', 1)];
+
+/// Piece counts of both sides, indexed by table piece code (1 = pawn .. 6 = king)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Material {
+    counts: [[u8; 7]; 2],
+}
+
+impl Material {
+    /// Parse a table name such as `KQvKR`, white's pieces first
+    pub fn from_name(name: &str) -> Option<Self> {
+        let (white, black) = name.split_once('v')?;
+        let mut counts = [[0u8; 7]; 2];
+
+        for (side, pieces) in [white, black].iter().enumerate() {
+            for c in pieces.chars() {
+                let &(_, code) = NAME_ORDER.iter().find(|(letter, _)| *letter == c)?;
+                counts[side][code as usize] += 1;
+            }
+            if counts[side][6] != 1 {
+                return None;
+            }
+        }
+
+        Some(Self { counts })
+    }
+
+    /// Count pieces straight from the board's bitboards
+    pub fn from_game(game: &Game) -> Self {
+        let board = game.get_board();
+        let mut counts = [[0u8; 7]; 2];
+
+        for (side, colour) in [Colour::White, Colour::Black].into_iter().enumerate() {
+            for kind in PieceType::iter() {
+                let bits = board.get_piece_bit_board(Piece { kind, colour }).bits();
+                counts[side][piece_code(kind) as usize] = bits.count_ones() as u8;
+            }
+        }
+
+        Self { counts }
+    }
+
+    pub fn name(&self) -> String {
+        let side_name = |side: usize| -> String {
+            NAME_ORDER.iter()
+                .flat_map(|&(letter, code)| std::iter::repeat_n(letter, self.counts[side][code as usize] as usize))
+                .collect()
+        };
+        format!("{}v{}", side_name(0), side_name(1))
+    }
+
+    /// The same material with colours swapped
+    pub fn flipped(&self) -> Self {
+        Self { counts: [self.counts[1], self.counts[0]] }
+    }
+
+    pub fn count(&self, black: bool, code: u8) -> u8 {
+        self.counts[black as usize][code as usize]
+    }
+
+    pub fn piece_count(&self) -> usize {
+        self.counts.iter().flatten().map(|&count| count as usize).sum()
+    }
+
+    pub fn has_pawns(&self) -> bool {
+        self.count(false, PAWN_CODE) + self.count(true, PAWN_CODE) > 0
+    }
+
+    /// Whether some side has exactly one of a piece type other than the king
+    pub fn has_unique_pieces(&self) -> bool {
+        self.counts.iter().any(|side| side[1..6].contains(&1))
+    }
+
+    pub fn is_symmetric(&self) -> bool {
+        self.counts[0] == self.counts[1]
+    }
+}
+
+pub fn piece_code(kind: PieceType) -> u8 {
+    kind as u8 + 1
+}
+
+/// Table code of a piece, black pieces have the colour bit set
+pub fn coloured_piece_code(piece: Piece) -> u8 {
+    piece_code(piece.kind) | if piece.colour == Colour::Black { BLACK_PIECE } else { 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_round_trip() {
+        let material = Material::from_name("KRBvKNP").unwrap();
+        assert_eq!(material.name(), "KRBvKNP");
+        assert_eq!(material.flipped().name(), "KNPvKRB");
+        assert_eq!(material.piece_count(), 6);
+        assert!(material.has_pawns());
+        assert!(material.has_unique_pieces());
+        assert!(!material.is_symmetric());
+
+        assert!(Material::from_name("KQvKR").unwrap().flipped() == Material::from_name("KRvKQ").unwrap());
+        assert!(Material::from_name("KRRvKR").unwrap().has_unique_pieces());
+        assert!(!Material::from_name("KRRvKBB").unwrap().has_unique_pieces());
+        assert!(Material::from_name("KQvQ").is_none());
+        assert!(Material::from_name("KXvK").is_none());
+    }
+
+    #[test]
+    fn test_from_game() {
+        let mut game = Game::new();
+        game.set_fenstr("8/8/4k3/8/2r5/8/1Q6/4K3 w - - 0 1");
+        assert_eq!(Material::from_game(&game).name(), "KQvKR");
+    }
+}
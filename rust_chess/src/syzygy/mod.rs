@@ -0,0 +1,3 @@
+pub mod material;
+pub mod table;
+pub mod tablebase;
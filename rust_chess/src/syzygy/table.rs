@@ -0,0 +1,974 @@
+use once_cell::sync::Lazy;
+
+use crate::syzygy::material::Material;
+use crate::syzygy::tablebase::Wdl;
+
+pub const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+pub const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+/// Largest table size the index encoding supports
+pub const MAX_PIECES: usize = 7;
+
+// Per-table flags stored in front of the compression parameters
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+// Flags in the first byte of the file
+const FILE_SPLIT: u8 = 1;
+const FILE_HAS_PAWNS: u8 = 2;
+
+/// Piece codes used by the tables: 1..6 for white pawn..king, plus 8 for black
+pub const BLACK_PIECE: u8 = 8;
+pub const PAWN_CODE: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+/// A position reduced to what the tables need: piece codes and squares (a1 = 0)
+pub struct TbPosition {
+    pub pieces: Vec<(u8, u8)>,
+    pub black_to_move: bool,
+}
+
+/// Lookup tables for turning piece placements into table indices
+struct Encoding {
+    // b1-h1-h7 triangle (below the a1-h8 diagonal) to 0..27
+    map_b1h1h7: [u64; 64],
+    // a1-d1-d4 triangle to 0..9, diagonal squares last
+    map_a1d1d4: [u64; 64],
+    // Legal placements of two kings with the first in the a1-d1-d4 triangle to 0..461
+    map_kk: [[u64; 64]; 10],
+    binomial: [[u64; 64]; MAX_PIECES],
+    // Pawn squares a2-h7 to 0..47, highest for the pawn nearest the edge and lowest rank
+    map_pawns: [u64; 64],
+    lead_pawn_idx: [[u64; 64]; MAX_PIECES],
+    lead_pawns_size: [[u64; 4]; MAX_PIECES],
+}
+
+static ENCODING: Lazy<Box<Encoding>> = Lazy::new(|| Box::new(Encoding::new()));
+
+fn file_of(square: usize) -> usize {
+    square & 7
+}
+
+fn rank_of(square: usize) -> usize {
+    square >> 3
+}
+
+/// Positive above the a1-h8 diagonal, negative below, zero on it
+fn off_diagonal(square: usize) -> i32 {
+    rank_of(square) as i32 - file_of(square) as i32
+}
+
+fn is_adjacent_or_same(a: usize, b: usize) -> bool {
+    file_of(a).abs_diff(file_of(b)) <= 1 && rank_of(a).abs_diff(rank_of(b)) <= 1
+}
+
+impl Encoding {
+    fn new() -> Self {
+        let mut encoding = Encoding {
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            binomial: [[0; 64]; MAX_PIECES],
+            map_pawns: [0; 64],
+            lead_pawn_idx: [[0; 64]; MAX_PIECES],
+            lead_pawns_size: [[0; 4]; MAX_PIECES],
+        };
+
+        let mut code = 0;
+        for square in 0..64 {
+            if off_diagonal(square) < 0 {
+                encoding.map_b1h1h7[square] = code;
+                code += 1;
+            }
+        }
+
+        let mut triangle = Vec::new();
+        let mut diagonal = Vec::new();
+        for square in 0..=27 {
+            if file_of(square) <= 3 && off_diagonal(square) < 0 {
+                triangle.push(square);
+            } else if file_of(square) <= 3 && off_diagonal(square) == 0 {
+                diagonal.push(square);
+            }
+        }
+        triangle.extend(diagonal);
+        for (code, &square) in triangle.iter().enumerate() {
+            encoding.map_a1d1d4[square] = code as u64;
+        }
+
+        // If the first king is on the diagonal the second may not be above it.
+        // Placements with both kings on the diagonal are numbered last.
+        let mut code = 0;
+        let mut both_on_diagonal = Vec::new();
+        for (idx, &first) in triangle.iter().enumerate() {
+            for second in 0..64 {
+                if is_adjacent_or_same(first, second) {
+                    continue;
+                }
+                if off_diagonal(first) == 0 && off_diagonal(second) > 0 {
+                    continue;
+                }
+                if off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                    both_on_diagonal.push((idx, second));
+                } else {
+                    encoding.map_kk[idx][second] = code;
+                    code += 1;
+                }
+            }
+        }
+        for (idx, second) in both_on_diagonal {
+            encoding.map_kk[idx][second] = code;
+            code += 1;
+        }
+
+        encoding.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..MAX_PIECES.min(n + 1) {
+                encoding.binomial[k][n] = if k > 0 { encoding.binomial[k - 1][n - 1] } else { 0 }
+                    + if k < n { encoding.binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        let mut available_squares: i64 = 47;
+        for lead_pawns in 1..MAX_PIECES {
+            for file in 0..4 {
+                // The index restarts on every file since pawn tables are split by lead pawn file
+                let mut idx = 0;
+                for rank in 1..7 {
+                    let square = rank * 8 + file;
+                    if lead_pawns == 1 {
+                        encoding.map_pawns[square] = available_squares as u64;
+                        encoding.map_pawns[square ^ 7] = (available_squares - 1) as u64;
+                        available_squares -= 2;
+                    }
+                    encoding.lead_pawn_idx[lead_pawns][square] = idx;
+                    idx += encoding.binomial[lead_pawns - 1][encoding.map_pawns[square] as usize];
+                }
+                encoding.lead_pawns_size[lead_pawns][file] = idx;
+            }
+        }
+
+        encoding
+    }
+}
+
+/// Decoding parameters for one sub-table (one side to move and, with pawns, one lead pawn file)
+#[derive(Clone, Default)]
+struct PairsData {
+    flags: u8,
+    pieces: [u8; MAX_PIECES],
+    group_len: [usize; MAX_PIECES + 1],
+    group_idx: [u64; MAX_PIECES + 1],
+    block_size: usize,
+    span: u64,
+    num_indices: usize,
+    num_blocks: usize,
+    block_length_size: usize,
+    min_sym_len: u8,
+    lowest_sym: usize,
+    base64: Vec<u64>,
+    symlen: Vec<u8>,
+    btree: usize,
+    sparse_index: usize,
+    block_length: usize,
+    data: usize,
+    map_idx: [u16; 4],
+}
+
+/// A loaded `.rtbw` or `.rtbz` file
+pub struct Table {
+    data: Vec<u8>,
+    kind: TableKind,
+    piece_count: usize,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    // pawns of the leading colour, then of the other colour
+    pawn_count: [usize; 2],
+    symmetric: bool,
+    // indexed [lead pawn file][side to move]
+    pairs: Vec<Vec<PairsData>>,
+    dtz_map: usize,
+}
+
+impl Table {
+    /// Parse a table file for the given material (as named by the file, stronger side first).
+    /// Returns None if the file is not a valid table for that material.
+    pub fn new(data: Vec<u8>, kind: TableKind, material: &Material) -> Option<Self> {
+        let magic = match kind {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        };
+        if data.len() < 5 || data[0..4] != magic {
+            return None;
+        }
+
+        let white_pawns = material.count(false, PAWN_CODE) as usize;
+        let black_pawns = material.count(true, PAWN_CODE) as usize;
+        // The side with fewer pawns leads, since that compresses better
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+
+        let mut table = Table {
+            data,
+            kind,
+            piece_count: material.piece_count(),
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces: material.has_unique_pieces(),
+            pawn_count: if white_leads { [white_pawns, black_pawns] } else { [black_pawns, white_pawns] },
+            symmetric: material.is_symmetric(),
+            pairs: Vec::new(),
+            dtz_map: 0,
+        };
+
+        if table.piece_count > MAX_PIECES
+            || table.has_pawns != (table.data[4] & FILE_HAS_PAWNS != 0)
+            || table.symmetric == (table.data[4] & FILE_SPLIT != 0)
+        {
+            return None;
+        }
+
+        table.parse()?;
+        Some(table)
+    }
+
+    fn sides(&self) -> usize {
+        if self.kind == TableKind::Wdl && !self.symmetric { 2 } else { 1 }
+    }
+
+    fn files(&self) -> usize {
+        if self.has_pawns { 4 } else { 1 }
+    }
+
+    fn parse(&mut self) -> Option<()> {
+        let sides = self.sides();
+        let files = self.files();
+        let both_have_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut pos = 5;
+
+        self.pairs = vec![vec![PairsData::default(); sides]; files];
+        for file in 0..files {
+            let order_bytes = self.data.get(pos..pos + 1 + both_have_pawns as usize)?;
+            let second = if both_have_pawns { order_bytes[1] } else { 0xFF };
+            let order = [[order_bytes[0] & 0xF, second & 0xF], [order_bytes[0] >> 4, second >> 4]];
+            pos += order_bytes.len();
+
+            for k in 0..self.piece_count {
+                let byte = *self.data.get(pos)?;
+                for side in 0..sides {
+                    self.pairs[file][side].pieces[k] = if side == 1 { byte >> 4 } else { byte & 0xF };
+                }
+                pos += 1;
+            }
+
+            for (side, order) in order.iter().enumerate().take(sides) {
+                self.set_groups(file, side, *order);
+            }
+        }
+
+        pos += pos & 1;
+
+        for file in 0..files {
+            for side in 0..sides {
+                pos = self.set_sizes(file, side, pos)?;
+            }
+        }
+
+        if self.kind == TableKind::Dtz {
+            pos = self.set_dtz_map(pos)?;
+        }
+
+        for file in 0..files {
+            for side in 0..sides {
+                let pairs = &mut self.pairs[file][side];
+                pairs.sparse_index = pos;
+                pos += pairs.num_indices * 6;
+            }
+        }
+
+        for file in 0..files {
+            for side in 0..sides {
+                let pairs = &mut self.pairs[file][side];
+                pairs.block_length = pos;
+                pos += pairs.block_length_size * 2;
+            }
+        }
+
+        for file in 0..files {
+            for side in 0..sides {
+                let pairs = &mut self.pairs[file][side];
+                pos = (pos + 0x3F) & !0x3F;
+                pairs.data = pos;
+                pos += pairs.num_blocks * pairs.block_size;
+
+                if pairs.num_blocks > 0 && pos > self.data.len() {
+                    return None;
+                }
+            }
+        }
+
+        Some(())
+    }
+
+    /// Split the piece sequence into groups of identical pieces and work out
+    /// the multiplier each group's index is scaled by
+    fn set_groups(&mut self, file: usize, side: usize, order: [u8; 2]) {
+        let encoding = &*ENCODING;
+        let both_have_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let d = &mut self.pairs[file][side];
+
+        // The leading group is the kings plus a unique piece if there is one, or the lead pawns
+        let mut first_len: i32 = if self.has_pawns { 0 } else if self.has_unique_pieces { 3 } else { 2 };
+        let mut n = 0;
+        d.group_len[0] = 1;
+        for i in 1..self.piece_count {
+            first_len -= 1;
+            if first_len > 0 || d.pieces[i] == d.pieces[i - 1] {
+                d.group_len[n] += 1;
+            } else {
+                n += 1;
+                d.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        d.group_len[n] = 0;
+
+        let mut next = if both_have_pawns { 2 } else { 1 };
+        let mut free_squares = 64 - d.group_len[0] - if both_have_pawns { d.group_len[1] } else { 0 };
+        let mut idx: u64 = 1;
+        let mut k = 0;
+        while next < n || k == order[0] || k == order[1] {
+            if k == order[0] {
+                d.group_idx[0] = idx;
+                idx *= if self.has_pawns {
+                    encoding.lead_pawns_size[d.group_len[0]][file]
+                } else if self.has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] {
+                d.group_idx[1] = idx;
+                idx *= encoding.binomial[d.group_len[1]][48 - d.group_len[0]];
+            } else {
+                d.group_idx[next] = idx;
+                idx *= encoding.binomial[d.group_len[next]][free_squares];
+                free_squares -= d.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        d.group_idx[n] = idx;
+    }
+
+    fn set_sizes(&mut self, file: usize, side: usize, mut pos: usize) -> Option<usize> {
+        let data = &self.data;
+        let d = &mut self.pairs[file][side];
+
+        d.flags = *data.get(pos)?;
+        pos += 1;
+        if d.flags & FLAG_SINGLE_VALUE != 0 {
+            // The single value every position maps to is stored in place of the symbol length
+            d.min_sym_len = *data.get(pos)?;
+            return Some(pos + 1);
+        }
+
+        let header = data.get(pos..pos + 10)?;
+        let group_count = d.group_len.iter().position(|&len| len == 0).unwrap();
+        let table_size = d.group_idx[group_count];
+        d.block_size = 1 << header[0];
+        d.span = 1 << header[1];
+        d.num_indices = table_size.div_ceil(d.span) as usize;
+        let padding = header[2] as usize;
+        d.num_blocks = u32::from_le_bytes(header[3..7].try_into().unwrap()) as usize;
+        d.block_length_size = d.num_blocks + padding;
+        let max_sym_len = header[7];
+        d.min_sym_len = header[8];
+        pos += 9;
+
+        if max_sym_len < d.min_sym_len || d.min_sym_len == 0 {
+            return None;
+        }
+
+        // Canonical Huffman code: longer codes have lower values, so base64[len] is the
+        // lowest code of each length, left aligned in 64 bits
+        d.lowest_sym = pos;
+        let lengths = (max_sym_len - d.min_sym_len + 1) as usize;
+        data.get(pos..pos + lengths * 2)?;
+        d.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            let lowest = read_u16_le(data, d.lowest_sym + 2 * i) as u64;
+            let next_lowest = read_u16_le(data, d.lowest_sym + 2 * (i + 1)) as u64;
+            d.base64[i] = (d.base64[i + 1] + lowest).wrapping_sub(next_lowest) / 2;
+        }
+        for (i, base) in d.base64.iter_mut().enumerate() {
+            *base = base.checked_shl(64 - i as u32 - d.min_sym_len as u32).unwrap_or(0);
+        }
+        pos += lengths * 2;
+
+        let symbol_count = read_u16_le(data, pos) as usize;
+        pos += 2;
+        d.btree = pos;
+        data.get(pos..pos + symbol_count * 3)?;
+
+        // Each symbol stands for a pair of smaller symbols; symlen is how many values it expands to, minus one
+        d.symlen = vec![0; symbol_count];
+        let mut visited = vec![false; symbol_count];
+        for symbol in 0..symbol_count {
+            if !visited[symbol] {
+                Self::set_symlen(data, d, symbol, &mut visited)?;
+            }
+        }
+
+        Some(pos + symbol_count * 3 + (symbol_count & 1))
+    }
+
+    fn set_symlen(data: &[u8], d: &mut PairsData, symbol: usize, visited: &mut [bool]) -> Option<()> {
+        visited[symbol] = true;
+        let (left, right) = read_pair(data, d.btree, symbol);
+        if right == 0xFFF {
+            d.symlen[symbol] = 0;
+            return Some(());
+        }
+        if left >= d.symlen.len() || right >= d.symlen.len() {
+            return None;
+        }
+        if !visited[left] {
+            Self::set_symlen(data, d, left, visited)?;
+        }
+        if !visited[right] {
+            Self::set_symlen(data, d, right, visited)?;
+        }
+        d.symlen[symbol] = d.symlen[left].wrapping_add(d.symlen[right]).wrapping_add(1);
+        Some(())
+    }
+
+    /// DTZ tables may remap stored values through a per-result lookup table
+    fn set_dtz_map(&mut self, mut pos: usize) -> Option<usize> {
+        self.dtz_map = pos;
+        for file in 0..self.files() {
+            let flags = self.pairs[file][0].flags;
+            if flags & FLAG_MAPPED == 0 {
+                continue;
+            }
+
+            if flags & FLAG_WIDE != 0 {
+                pos += pos & 1;
+                for i in 0..4 {
+                    self.pairs[file][0].map_idx[i] = ((pos - self.dtz_map) / 2 + 1) as u16;
+                    pos += 2 * read_u16_le(self.data.get(..pos + 2)?, pos) as usize + 2;
+                }
+            } else {
+                for i in 0..4 {
+                    self.pairs[file][0].map_idx[i] = (pos - self.dtz_map + 1) as u16;
+                    pos += *self.data.get(pos)? as usize + 1;
+                }
+            }
+        }
+        Some(pos + (pos & 1))
+    }
+
+    /// DTZ tables only store one side to move; false means the other side has to be searched
+    fn has_side_to_move(&self, stm: usize, file: usize) -> bool {
+        let flags = self.pairs[file][0].flags;
+        (flags & FLAG_STM) as usize == stm || (self.symmetric && !self.has_pawns)
+    }
+
+    /// Look up the stored value for a position. `flipped` is set when the position's
+    /// stronger side is black, i.e. it matches the table with colours swapped.
+    /// `wdl` is only used by DTZ tables. Returns None if a DTZ table doesn't store this side to move.
+    pub fn probe(&self, position: &TbPosition, flipped: bool, wdl: Wdl) -> Option<i32> {
+        let encoding = &*ENCODING;
+
+        // Symmetric tables only store white to move, so mirror black to move positions
+        let flip = flipped || (self.symmetric && position.black_to_move);
+        let flip_colour = if flip { BLACK_PIECE } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let stm = flip as usize ^ position.black_to_move as usize;
+
+        let mut squares = [0usize; MAX_PIECES];
+        let mut pieces = [0u8; MAX_PIECES];
+        let mut size = 0;
+        let mut lead_pawns = 0;
+        let mut lead_pawn_code = 0;
+        let mut file = 0;
+
+        if self.has_pawns {
+            // Pawns of the leading colour come first in every sub-table
+            lead_pawn_code = self.pairs[0][0].pieces[0] ^ flip_colour;
+            for &(code, square) in &position.pieces {
+                if code == lead_pawn_code {
+                    squares[size] = square as usize ^ flip_squares;
+                    pieces[size] = code ^ flip_colour;
+                    size += 1;
+                }
+            }
+            lead_pawns = size;
+
+            let lead = (0..lead_pawns).max_by_key(|&i| encoding.map_pawns[squares[i]]).unwrap();
+            squares.swap(0, lead);
+            file = file_of(squares[0]).min(7 - file_of(squares[0]));
+        }
+
+        if self.kind == TableKind::Dtz && !self.has_side_to_move(stm, file) {
+            return None;
+        }
+
+        for &(code, square) in &position.pieces {
+            if !self.has_pawns || code != lead_pawn_code {
+                squares[size] = square as usize ^ flip_squares;
+                pieces[size] = code ^ flip_colour;
+                size += 1;
+            }
+        }
+
+        let d = &self.pairs[file][if self.kind == TableKind::Wdl { stm } else { 0 }];
+
+        // Reorder to the piece sequence the table was compressed with
+        for i in lead_pawns..size.saturating_sub(1) {
+            if let Some(j) = (i + 1..size).find(|&j| pieces[j] == d.pieces[i]) {
+                pieces.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+
+        let idx = self.encode(d, &mut squares[..size], lead_pawns);
+        let value = self.decompress_pairs(d, idx);
+        Some(self.map_score(file, value as i32, wdl))
+    }
+
+    /// Index of a position, with squares already in table piece order
+    fn encode(&self, d: &PairsData, squares: &mut [usize], lead_pawns: usize) -> u64 {
+        let encoding = &*ENCODING;
+
+        // Mirror so the lead piece is on files a-d
+        if file_of(squares[0]) > 3 {
+            squares.iter_mut().for_each(|square| *square ^= 7);
+        }
+
+        let mut idx;
+        if self.has_pawns {
+            idx = encoding.lead_pawn_idx[lead_pawns][squares[0]];
+            squares[1..lead_pawns].sort_by_key(|&square| encoding.map_pawns[square]);
+            for (i, &square) in squares.iter().enumerate().take(lead_pawns).skip(1) {
+                idx += encoding.binomial[i][encoding.map_pawns[square] as usize];
+            }
+        } else {
+            // Without pawns we can also mirror ranks and the a1-h8 diagonal
+            if rank_of(squares[0]) > 3 {
+                squares.iter_mut().for_each(|square| *square ^= 56);
+            }
+
+            for i in 0..d.group_len[0] {
+                let off = off_diagonal(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+                if off > 0 {
+                    for square in squares[i..].iter_mut() {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+                break;
+            }
+
+            if self.has_unique_pieces {
+                let adjust1 = (squares[1] > squares[0]) as u64;
+                let adjust2 = (squares[2] > squares[0]) as u64 + (squares[2] > squares[1]) as u64;
+                let (s1, s2) = (squares[1] as u64, squares[2] as u64);
+                let (r0, r1, r2) = (rank_of(squares[0]) as u64, rank_of(squares[1]) as u64, rank_of(squares[2]) as u64);
+
+                idx = if off_diagonal(squares[0]) != 0 {
+                    (encoding.map_a1d1d4[squares[0]] * 63 + (s1 - adjust1)) * 62 + s2 - adjust2
+                } else if off_diagonal(squares[1]) != 0 {
+                    (6 * 63 + r0 * 28 + encoding.map_b1h1h7[squares[1]]) * 62 + s2 - adjust2
+                } else if off_diagonal(squares[2]) != 0 {
+                    6 * 63 * 62 + 4 * 28 * 62 + r0 * 7 * 28 + (r1 - adjust1) * 28 + encoding.map_b1h1h7[squares[2]]
+                } else {
+                    6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + r0 * 7 * 6 + (r1 - adjust1) * 6 + (r2 - adjust2)
+                };
+            } else {
+                idx = encoding.map_kk[encoding.map_a1d1d4[squares[0]] as usize][squares[1]];
+            }
+        }
+
+        idx *= d.group_idx[0];
+
+        // Remaining groups are encoded as combinations of the squares not taken by earlier groups
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut group_start = d.group_len[0];
+        let mut next = 1;
+        while d.group_len[next] != 0 {
+            let len = d.group_len[next];
+            squares[group_start..group_start + len].sort_unstable();
+
+            let mut n = 0;
+            for i in 0..len {
+                let square = squares[group_start + i];
+                let adjust = squares[..group_start].iter().filter(|&&earlier| square > earlier).count();
+                let pawn_offset = if remaining_pawns { 8 } else { 0 };
+                n += encoding.binomial[i + 1][square - adjust - pawn_offset];
+            }
+
+            remaining_pawns = false;
+            idx += n * d.group_idx[next];
+            group_start += len;
+            next += 1;
+        }
+
+        idx
+    }
+
+    fn decompress_pairs(&self, d: &PairsData, idx: u64) -> u16 {
+        if d.flags & FLAG_SINGLE_VALUE != 0 {
+            return d.min_sym_len as u16;
+        }
+
+        let data = &self.data;
+
+        // The sparse index points at a block and offset roughly every `span` values
+        let k = (idx / d.span) as usize;
+        let mut block = read_u32_le(data, d.sparse_index + 6 * k) as usize;
+        let mut offset = read_u16_le(data, d.sparse_index + 6 * k + 4) as i64;
+        offset += (idx % d.span) as i64 - (d.span / 2) as i64;
+
+        // Each block holds block_length + 1 values; walk to the block containing idx
+        let block_length = |block: usize| read_u16_le(data, d.block_length + 2 * block) as i64;
+        while offset < 0 {
+            block -= 1;
+            offset += block_length(block) + 1;
+        }
+        while offset > block_length(block) {
+            offset -= block_length(block) + 1;
+            block += 1;
+        }
+
+        let mut ptr = d.data + block * d.block_size;
+        let mut buf64 = u64::from_be_bytes(data[ptr..ptr + 8].try_into().unwrap());
+        ptr += 8;
+        let mut buf64_size = 64;
+
+        let mut symbol;
+        loop {
+            let mut len = 0;
+            while buf64 < d.base64[len] {
+                len += 1;
+            }
+
+            symbol = ((buf64 - d.base64[len]) >> (64 - len - d.min_sym_len as usize)) as usize;
+            symbol += read_u16_le(data, d.lowest_sym + 2 * len) as usize;
+
+            if offset < d.symlen[symbol] as i64 + 1 {
+                break;
+            }
+
+            offset -= d.symlen[symbol] as i64 + 1;
+            let bits = len + d.min_sym_len as usize;
+            buf64 <<= bits;
+            buf64_size -= bits;
+
+            if buf64_size <= 32 {
+                buf64_size += 32;
+                buf64 |= (u32::from_be_bytes(data[ptr..ptr + 4].try_into().unwrap()) as u64) << (64 - buf64_size);
+                ptr += 4;
+            }
+        }
+
+        // Expand the pair tree until we reach the single value at our offset
+        while d.symlen[symbol] != 0 {
+            let (left, right) = read_pair(data, d.btree, symbol);
+            if offset < d.symlen[left] as i64 + 1 {
+                symbol = left;
+            } else {
+                offset -= d.symlen[left] as i64 + 1;
+                symbol = right;
+            }
+        }
+
+        read_pair(data, d.btree, symbol).0 as u16
+    }
+
+    fn map_score(&self, file: usize, value: i32, wdl: Wdl) -> i32 {
+        if self.kind == TableKind::Wdl {
+            return value - 2;
+        }
+
+        // Map index per result, ordered as the generator writes them
+        const WDL_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+
+        let d = &self.pairs[file][0];
+        let mut value = value;
+        if d.flags & FLAG_MAPPED != 0 {
+            let idx = d.map_idx[WDL_MAP[(wdl as i32 + 2) as usize]] as usize + value as usize;
+            value = if d.flags & FLAG_WIDE != 0 {
+                read_u16_le(&self.data, self.dtz_map + 2 * idx) as i32
+            } else {
+                self.data[self.dtz_map + idx] as i32
+            };
+        }
+
+        // Values are stored in full moves unless flagged as plies
+        let in_moves = match wdl {
+            Wdl::Win => d.flags & FLAG_WIN_PLIES == 0,
+            Wdl::Loss => d.flags & FLAG_LOSS_PLIES == 0,
+            Wdl::CursedWin | Wdl::BlessedLoss => true,
+            Wdl::Draw => false,
+        };
+        if in_moves {
+            value *= 2;
+        }
+
+        value + 1
+    }
+}
+
+fn read_u16_le(data: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([data[pos], data[pos + 1]])
+}
+
+fn read_u32_le(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap())
+}
+
+/// Children of a symbol in the pair tree, packed as two 12-bit values
+fn read_pair(data: &[u8], btree: usize, symbol: usize) -> (usize, usize) {
+    let bytes = &data[btree + 3 * symbol..btree + 3 * symbol + 3];
+    let left = (((bytes[1] & 0xF) as usize) << 8) | bytes[0] as usize;
+    let right = ((bytes[2] as usize) << 4) | (bytes[1] >> 4) as usize;
+    (left, right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encoding_table_sizes() {
+        let encoding = &*ENCODING;
+
+        // 462 legal non-mirrored king placements
+        let kk_codes = (0..10).flat_map(|i| encoding.map_kk[i]).max().unwrap();
+        assert_eq!(kk_codes, 461);
+
+        assert_eq!(encoding.binomial[2][5], 10);
+        assert_eq!(encoding.binomial[3][62], 37820);
+
+        // Pawn squares a2..h7 map onto 0..47 exactly once
+        let mut pawn_codes: Vec<u64> = (8..56).map(|square| encoding.map_pawns[square]).collect();
+        pawn_codes.sort();
+        assert_eq!(pawn_codes, (0..48).collect::<Vec<_>>());
+
+        // A single lead pawn can be on one of six ranks of its file
+        assert_eq!(encoding.lead_pawns_size[1], [6, 6, 6, 6]);
+    }
+
+    /// KQvK WDL table with white to move stored pairs-compressed and black to move as a single
+    /// loss. Returns the file and the value stored for every index of the white to move side.
+    fn pairs_compressed_kqvk() -> (Vec<u8>, Vec<u16>) {
+        const TABLE_SIZE: usize = 31332;
+        const BLOCK_BITS: usize = 256;
+        const SPAN: usize = 1 << 10;
+
+        // Symbols 0 and 1 are the values 4 (win) and 2 (draw), 2 is the pair (0, 1) and 3 the
+        // pair (2, 2). Canonical codes give the longest codes the lowest symbols: 000 and 001
+        // for symbols 0 and 1, 01 for symbol 2 and 1 for symbol 3.
+        let codes = [(0b000, 3), (0b001, 3), (0b01, 2), (0b1, 1)];
+        let expansions: [&[u16]; 4] = [&[4], &[2], &[4, 2], &[4, 2, 4, 2]];
+
+        // Cut a fixed pattern of symbols into blocks of at most BLOCK_BITS bits
+        let pattern = [3, 0, 2, 1, 3, 3, 1];
+        let mut values = Vec::new();
+        let mut blocks: Vec<Vec<usize>> = vec![Vec::new()];
+        let mut block_bits = 0;
+        let mut i = 0;
+        while values.len() < TABLE_SIZE {
+            let symbol = pattern[i % pattern.len()];
+            i += 1;
+            if block_bits + codes[symbol].1 > BLOCK_BITS {
+                blocks.push(Vec::new());
+                block_bits = 0;
+            }
+            block_bits += codes[symbol].1;
+            blocks.last_mut().unwrap().push(symbol);
+            values.extend_from_slice(expansions[symbol]);
+        }
+        let block_values: Vec<usize> = blocks.iter()
+            .map(|block| block.iter().map(|&symbol| expansions[symbol].len()).sum())
+            .collect();
+
+        let mut data = WDL_MAGIC.to_vec();
+        // split, no pawns; group order; pieces K Q k for both sides; alignment padding
+        data.extend_from_slice(&[0x01, 0x00, 0x66, 0x55, 0xEE, 0x00]);
+
+        // White to move: block size, span, padding, block count, max and min symbol length
+        data.extend_from_slice(&[0x00, (BLOCK_BITS / 8).trailing_zeros() as u8, SPAN.trailing_zeros() as u8, 0]);
+        data.extend_from_slice(&(blocks.len() as u32).to_le_bytes());
+        data.extend_from_slice(&[3, 1]);
+        // Lowest symbol of each code length, shortest first
+        for lowest in [3u16, 2, 0] {
+            data.extend_from_slice(&lowest.to_le_bytes());
+        }
+        data.extend_from_slice(&4u16.to_le_bytes());
+        for (left, right) in [(4usize, 0xFFFusize), (2, 0xFFF), (0, 1), (2, 2)] {
+            data.extend_from_slice(&[left as u8, (left >> 8) as u8 | (right << 4) as u8, (right >> 4) as u8]);
+        }
+
+        // Black to move always loses
+        data.extend_from_slice(&[0x80, 0]);
+
+        // Sparse index: the block and offset of the value in the middle of each span
+        let block_starts: Vec<usize> = block_values.iter().scan(0, |start, len| {
+            let block_start = *start;
+            *start += len;
+            Some(block_start)
+        }).collect();
+        for k in 0..TABLE_SIZE.div_ceil(SPAN) {
+            let middle = k * SPAN + SPAN / 2;
+            let block = block_starts.partition_point(|&start| start <= middle) - 1;
+            data.extend_from_slice(&(block as u32).to_le_bytes());
+            data.extend_from_slice(&((middle - block_starts[block]) as u16).to_le_bytes());
+        }
+        for len in &block_values {
+            data.extend_from_slice(&(*len as u16 - 1).to_le_bytes());
+        }
+
+        data.resize(data.len().next_multiple_of(64), 0);
+        for block in &blocks {
+            let mut bits = 0u128;
+            let mut bit_count = 0;
+            let mut bytes = Vec::new();
+            for &symbol in block {
+                let (code, len) = codes[symbol];
+                bits = (bits << len) | code;
+                bit_count += len;
+                while bit_count >= 8 {
+                    bytes.push((bits >> (bit_count - 8)) as u8);
+                    bit_count -= 8;
+                }
+            }
+            if bit_count > 0 {
+                bytes.push((bits << (8 - bit_count)) as u8);
+            }
+            bytes.resize(BLOCK_BITS / 8, 0);
+            data.extend_from_slice(&bytes);
+        }
+        // The decoder reads a few bytes ahead of the last block
+        data.extend_from_slice(&[0; 16]);
+
+        values.truncate(TABLE_SIZE);
+        (data, values)
+    }
+
+    #[test]
+    fn test_decompress_pairs_compressed_table() {
+        let (data, values) = pairs_compressed_kqvk();
+        let material = Material::from_name("KQvK").unwrap();
+        let table = Table::new(data, TableKind::Wdl, &material).unwrap();
+        let d = &table.pairs[0][0];
+        assert!(d.num_blocks > 1 && d.num_indices > 1);
+        assert_eq!(d.symlen, [0, 0, 1, 3]);
+
+        for (idx, &value) in values.iter().enumerate() {
+            assert_eq!(table.decompress_pairs(d, idx as u64), value, "Index {}", idx);
+        }
+
+        // Probing goes through the same path: white king a1, queen b1, black king h8
+        let position = TbPosition { pieces: vec![(6, 0), (5, 1), (14, 63)], black_to_move: false };
+        let mut squares = [0, 1, 63];
+        let idx = table.encode(d, &mut squares, 0) as usize;
+        assert_eq!(table.probe(&position, false, Wdl::Draw), Some(values[idx] as i32 - 2));
+        let position = TbPosition { black_to_move: true, ..position };
+        assert_eq!(table.probe(&position, false, Wdl::Draw), Some(-2));
+    }
+
+    fn position(pieces: &[(u8, u8)]) -> TbPosition {
+        TbPosition { pieces: pieces.to_vec(), black_to_move: false }
+    }
+
+    #[test]
+    fn test_pawn_indices_mirror_files() {
+        let mut table = Table {
+            data: Vec::new(),
+            kind: TableKind::Wdl,
+            piece_count: 3,
+            has_pawns: true,
+            has_unique_pieces: true,
+            pawn_count: [1, 0],
+            symmetric: false,
+            pairs: vec![vec![PairsData::default(); 2]; 4],
+            dtz_map: 0,
+        };
+        for file in 0..4 {
+            table.pairs[file][0].pieces[..3].copy_from_slice(&[1, 6, 14]);
+            table.set_groups(file, 0, [0, 0xF]);
+            assert_eq!(table.pairs[file][0].group_idx[3], 6 * 63 * 62);
+        }
+
+        for pawn in 8..56 {
+            for king in 0..64 {
+                for other_king in 0..64 {
+                    if king == pawn || other_king == pawn || is_adjacent_or_same(king, other_king) {
+                        continue;
+                    }
+                    let file = file_of(pawn).min(7 - file_of(pawn));
+                    let d = &table.pairs[file][0];
+                    let idx = table.encode(d, &mut [pawn, king, other_king], 1);
+                    assert!(idx < d.group_idx[3]);
+                    assert_eq!(table.encode(d, &mut [pawn ^ 7, king ^ 7, other_king ^ 7], 1), idx);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_indices_stay_within_table_size() {
+        // KRvK: unique pieces, so kings and rook are encoded together
+        let material = Material::from_name("KRvK").unwrap();
+        let mut table = Table {
+            data: Vec::new(),
+            kind: TableKind::Wdl,
+            piece_count: 3,
+            has_pawns: false,
+            has_unique_pieces: true,
+            pawn_count: [0, 0],
+            symmetric: material.is_symmetric(),
+            pairs: vec![vec![PairsData::default(); 2]],
+            dtz_map: 0,
+        };
+        table.pairs[0][0].pieces[..3].copy_from_slice(&[6, 4, 14]);
+        table.set_groups(0, 0, [0, 0xF]);
+        let size = table.pairs[0][0].group_idx[1];
+        assert_eq!(size, 31332);
+
+        let encode = |squares: [u8; 3]| {
+            let pos = position(&[(6, squares[0]), (4, squares[1]), (14, squares[2])]);
+            let mut squares: Vec<usize> = pos.pieces.iter().map(|&(_, square)| square as usize).collect();
+            table.encode(&table.pairs[0][0], &mut squares, 0)
+        };
+        let transpose = |square: u8| ((square >> 3) | (square << 3)) & 63;
+
+        for king in 0..64u8 {
+            for rook in 0..64u8 {
+                for other_king in 0..64u8 {
+                    if rook == king || rook == other_king || is_adjacent_or_same(king as usize, other_king as usize) {
+                        continue;
+                    }
+                    let idx = encode([king, rook, other_king]);
+                    assert!(idx < size, "Index {} out of range", idx);
+
+                    // Mirrored boards are the same position to the table
+                    assert_eq!(encode([king ^ 7, rook ^ 7, other_king ^ 7]), idx);
+                    assert_eq!(encode([king ^ 56, rook ^ 56, other_king ^ 56]), idx);
+                    assert_eq!(encode([transpose(king), transpose(rook), transpose(other_king)]), idx);
+                }
+            }
+        }
+    }
+}
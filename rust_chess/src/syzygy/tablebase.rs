@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::ops::Neg;
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::OnceCell;
+
+use crate::enums::{Colour, PieceType};
+use crate::game_classes::game::Game;
+use crate::moves::compact_move::{Move, MoveKind};
+use crate::moves::move_generator::MoveGenerator;
+use crate::syzygy::material::{coloured_piece_code, Material};
+use crate::syzygy::table::{Table, TableKind, TbPosition, MAX_PIECES};
+use crate::variants::{Standard, Variant};
+
+/// Game theoretic result for the side to move. Cursed wins and blessed losses
+/// are wins and losses that the fifty move rule turns into draws.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Self {
+        match value {
+            -2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+
+    /// DTZ of a position whose best move resets the fifty move counter
+    fn dtz_before_zeroing(self) -> i32 {
+        match self {
+            Wdl::Win => 1,
+            Wdl::CursedWin => 101,
+            Wdl::Draw => 0,
+            Wdl::BlessedLoss => -101,
+            Wdl::Loss => -1,
+        }
+    }
+
+    fn signum(self) -> i32 {
+        (self as i32).signum()
+    }
+}
+
+impl Neg for Wdl {
+    type Output = Wdl;
+
+    fn neg(self) -> Wdl {
+        Wdl::from_value(-(self as i32))
+    }
+}
+
+// Moves are ranked by distance to zeroing, shifted so wins sort above draws above losses
+const MAX_DTZ: i32 = 1 << 18;
+
+/// Outcome of probing the table itself, before captures are taken into account
+enum ProbeState {
+    Ok,
+    // Best move is a capture or pawn move, so the DTZ table can't be trusted
+    ZeroingBestMove,
+}
+
+struct TableFiles {
+    material: Material,
+    wdl_path: Option<PathBuf>,
+    dtz_path: Option<PathBuf>,
+    wdl: OnceCell<Option<Table>>,
+    dtz: OnceCell<Option<Table>>,
+}
+
+/// Syzygy tablebases found in one or more directories. Files are read lazily on first probe.
+#[derive(Default)]
+pub struct Tablebase {
+    tables: HashMap<Material, TableFiles>,
+    max_pieces: usize,
+}
+
+impl Tablebase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build from a `SyzygyPath` style list of directories (`:` separated, `;` on Windows)
+    pub fn from_path_list(paths: &str) -> io::Result<Self> {
+        let mut tablebase = Self::new();
+        for dir in std::env::split_paths(paths) {
+            if !dir.as_os_str().is_empty() {
+                tablebase.add_directory(&dir)?;
+            }
+        }
+        Ok(tablebase)
+    }
+
+    /// Register every `.rtbw`/`.rtbz` file in a directory, returning how many were found
+    pub fn add_directory<P: AsRef<Path>>(&mut self, dir: P) -> io::Result<usize> {
+        let mut found = 0;
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let kind = match path.extension().and_then(|ext| ext.to_str()) {
+                Some("rtbw") => TableKind::Wdl,
+                Some("rtbz") => TableKind::Dtz,
+                _ => continue,
+            };
+            let Some(material) = path.file_stem().and_then(|stem| stem.to_str()).and_then(Material::from_name) else {
+                continue;
+            };
+            if material.piece_count() > MAX_PIECES {
+                continue;
+            }
+
+            let files = self.tables.entry(material).or_insert_with(|| TableFiles {
+                material,
+                wdl_path: None,
+                dtz_path: None,
+                wdl: OnceCell::new(),
+                dtz: OnceCell::new(),
+            });
+            match kind {
+                TableKind::Wdl => files.wdl_path = Some(path),
+                TableKind::Dtz => files.dtz_path = Some(path),
+            }
+
+            self.max_pieces = self.max_pieces.max(material.piece_count());
+            found += 1;
+        }
+
+        Ok(found)
+    }
+
+    /// Most pieces of any table found, 0 if there are none
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Whether the position is orthodox chess, small enough and has no castling rights, so
+    /// tables could cover it
+    pub fn can_probe(&self, game: &Game) -> bool {
+        game.variant().name() == Standard.name()
+            && game.get_board().all_occ().num_set_bits() as usize <= self.max_pieces
+            && game.get_game_state().get_castling_rights().is_empty()
+    }
+
+    /// Win/draw/loss for the side to move, None if the tables don't cover the position
+    pub fn probe_wdl(&self, game: &mut Game) -> Option<Wdl> {
+        if !self.can_probe(game) {
+            return None;
+        }
+        self.search(game, false).map(|(wdl, _)| wdl)
+    }
+
+    /// Distance to zeroing the fifty move counter in plies, signed by the result:
+    /// positive when winning, negative when losing, 0 for draws
+    pub fn probe_dtz(&self, game: &mut Game) -> Option<i32> {
+        if !self.can_probe(game) {
+            return None;
+        }
+
+        let (wdl, state) = self.search(game, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if let ProbeState::ZeroingBestMove = state {
+            return Some(wdl.dtz_before_zeroing());
+        }
+
+        if let Some(dtz) = self.probe_table(game, TableKind::Dtz, wdl)? {
+            let cursed_offset = if matches!(wdl, Wdl::CursedWin | Wdl::BlessedLoss) { 100 } else { 0 };
+            return Some((dtz + cursed_offset) * wdl.signum());
+        }
+
+        // The table stores the other side to move, so take the best reply one ply down
+        let colour = game.get_game_state().get_turn();
+        let mut moves = Vec::new();
+        MoveGenerator::generate_legal_moves_into(game, colour, true, &mut moves);
+
+        let mut min_dtz = i32::MAX;
+        for mv in moves {
+            let zeroing = is_zeroing(game, mv);
+            game.make_compact_move(mv);
+
+            // A zeroing move's DTZ is counted before the move; the child search only gives its sign
+            let probed = if zeroing {
+                self.search(game, false).map(|(wdl, _)| -wdl.dtz_before_zeroing())
+            } else {
+                self.probe_dtz(game).map(|dtz| -dtz)
+            };
+            let Some(mut dtz) = probed else {
+                game.undo_last_move();
+                return None;
+            };
+
+            if dtz == 1 && is_checkmate(game) {
+                min_dtz = 1;
+            }
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+            if dtz < min_dtz && dtz.signum() == wdl.signum() {
+                min_dtz = dtz;
+            }
+
+            game.undo_last_move();
+        }
+
+        // No legal moves: we are mated
+        Some(if min_dtz == i32::MAX { -1 } else { min_dtz })
+    }
+
+    /// Rank the root moves by their tablebase result, higher is better.
+    /// None if any probe fails, in which case the search should use all moves as usual.
+    pub fn rank_root_moves(&self, game: &mut Game, moves: &[Move]) -> Option<Vec<(Move, i32)>> {
+        if !self.can_probe(game) {
+            return None;
+        }
+
+        let halfmove_clock = game.get_game_state().get_halfmove_clock() as i32;
+        let use_dtz = self.has_dtz_tables();
+        let mut ranked = Vec::with_capacity(moves.len());
+
+        for &mv in moves {
+            game.make_compact_move(mv);
+
+            let dtz = if !use_dtz || game.get_game_state().get_halfmove_clock() == 0 {
+                // After a zeroing move (or without DTZ tables) only the result matters
+                self.probe_wdl(game).map(|wdl| (-wdl).dtz_before_zeroing())
+            } else {
+                self.probe_dtz(game).map(|dtz| -dtz - dtz.signum())
+            };
+            let mate = is_checkmate(game);
+            game.undo_last_move();
+
+            let dtz = if mate { 1 } else { dtz? };
+
+            // Prefer the quickest win, wins the fifty move rule doesn't spoil first.
+            // Prefer the slowest loss, losses the fifty move rule saves first. Draws are all equal.
+            let rank = if dtz > 0 {
+                if dtz + halfmove_clock <= 100 { MAX_DTZ - dtz } else { MAX_DTZ / 2 - dtz }
+            } else if dtz < 0 {
+                if -dtz + halfmove_clock <= 100 { -MAX_DTZ - dtz } else { -MAX_DTZ / 2 - dtz }
+            } else {
+                0
+            };
+            ranked.push((mv, rank));
+        }
+
+        Some(ranked)
+    }
+
+    /// Keep only the root moves with the best tablebase rank.
+    /// Returns false (and leaves the moves alone) if the position could not be probed.
+    pub fn filter_root_moves(&self, game: &mut Game, moves: &mut Vec<Move>) -> bool {
+        let Some(ranked) = self.rank_root_moves(game, moves) else {
+            return false;
+        };
+        let Some(best) = ranked.iter().map(|&(_, rank)| rank).max() else {
+            return false;
+        };
+
+        moves.retain(|mv| ranked.iter().any(|&(ranked_mv, rank)| ranked_mv == *mv && rank == best));
+        true
+    }
+
+    fn has_dtz_tables(&self) -> bool {
+        self.tables.values().any(|files| files.dtz_path.is_some())
+    }
+
+    /// Captures have to be searched: tables store "don't care" values where the side to
+    /// move has a winning capture, and nothing about en passant.
+    fn search(&self, game: &mut Game, check_zeroing_moves: bool) -> Option<(Wdl, ProbeState)> {
+        let colour = game.get_game_state().get_turn();
+        let mut moves = Vec::new();
+        MoveGenerator::generate_legal_moves_into(game, colour, true, &mut moves);
+
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+        for &mv in &moves {
+            let pawn_move = game.get_board().piece_at(mv.from_sq()).is_some_and(|piece| piece.kind == PieceType::Pawn);
+            let zeroing = is_capture(game, mv) || (check_zeroing_moves && pawn_move);
+            if !zeroing {
+                continue;
+            }
+            searched += 1;
+
+            game.make_compact_move(mv);
+            let result = self.search(game, false);
+            game.undo_last_move();
+
+            let value = -result?.0;
+            if value > best {
+                best = value;
+                if value >= Wdl::Win {
+                    return Some((value, ProbeState::ZeroingBestMove));
+                }
+            }
+        }
+
+        // When every move was searched the stored value can't be trusted, e.g. with en passant
+        let all_searched = searched > 0 && searched == moves.len();
+        let value = if all_searched {
+            best
+        } else {
+            Wdl::from_value(self.probe_table(game, TableKind::Wdl, Wdl::Draw)??)
+        };
+
+        if best >= value {
+            let state = if best > Wdl::Draw || all_searched { ProbeState::ZeroingBestMove } else { ProbeState::Ok };
+            return Some((best, state));
+        }
+        Some((value, ProbeState::Ok))
+    }
+
+    /// Raw table lookup. The outer None means no table, the inner None that a DTZ
+    /// table only stores the other side to move.
+    fn probe_table(&self, game: &Game, kind: TableKind, wdl: Wdl) -> Option<Option<i32>> {
+        let material = Material::from_game(game);
+
+        // Bare kings are not stored anywhere
+        if material.piece_count() == 2 {
+            return Some(Some(0));
+        }
+
+        // Files are named with the stronger side first, so the position may match with colours swapped
+        let (files, flipped) = match self.tables.get(&material) {
+            Some(files) => (files, false),
+            None => (self.tables.get(&material.flipped())?, true),
+        };
+        let table = files.load(kind)?;
+
+        let board = game.get_board();
+        let mut pieces = Vec::with_capacity(material.piece_count());
+        for square in board.all_occ().get_set_coords() {
+            let piece = board.get_coords(&square).unwrap();
+            pieces.push((coloured_piece_code(piece), square.to_index() as u8));
+        }
+        let position = TbPosition { pieces, black_to_move: game.get_game_state().get_turn() == Colour::Black };
+
+        Some(table.probe(&position, flipped, wdl))
+    }
+}
+
+impl TableFiles {
+    fn load(&self, kind: TableKind) -> Option<&Table> {
+        let (cell, path) = match kind {
+            TableKind::Wdl => (&self.wdl, &self.wdl_path),
+            TableKind::Dtz => (&self.dtz, &self.dtz_path),
+        };
+        cell.get_or_init(|| {
+            let data = fs::read(path.as_ref()?).ok()?;
+            Table::new(data, kind, &self.material)
+        }).as_ref()
+    }
+}
+
+fn is_capture(game: &Game, mv: Move) -> bool {
+    mv.kind() == MoveKind::EnPassant || game.get_board().piece_at(mv.to_sq()).is_some()
+}
+
+fn is_zeroing(game: &Game, mv: Move) -> bool {
+    is_capture(game, mv) || game.get_board().piece_at(mv.from_sq()).is_some_and(|piece| piece.kind == PieceType::Pawn)
+}
+
+fn is_checkmate(game: &mut Game) -> bool {
+    let colour = game.get_game_state().get_turn();
+    let mut moves = Vec::new();
+    MoveGenerator::generate_legal_moves_into(game, colour, true, &mut moves);
+    moves.is_empty() && game.is_player_in_check(colour, true)
+}
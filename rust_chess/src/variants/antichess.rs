@@ -0,0 +1,132 @@
+use crate::enums::{Colour, PieceType};
+use crate::engine::evaluator::Evaluator;
+use crate::game_classes::game::{Game, GameResult};
+use crate::moves::compact_move::{Move, MoveKind};
+use crate::moves::move_generator::MoveGenerator;
+use crate::variants::Variant;
+
+// Material is taken off this many times over, outweighing the orthodox material score so
+// that it counts against its owner
+const MATERIAL_PENALTY: i32 = 3;
+
+/// Chess won by losing every piece or having no move. Captures are compulsory, there is no
+/// check or castling and the king is an ordinary piece that can be taken. Pawns can't promote
+/// to a king, a packed `Move` has no room for it.
+pub struct Antichess;
+
+fn material(game: &Game, colour: Colour) -> i32 {
+    game.get_player_pieces(colour).iter()
+        .filter(|(piece, _)| piece.kind != PieceType::King)
+        .map(|(piece, _)| Evaluator::get_piece_value(piece.kind))
+        .sum()
+}
+
+impl Variant for Antichess {
+    fn name(&self) -> &'static str {
+        "antichess"
+    }
+
+    fn start_fenstr(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1"
+    }
+
+    fn generate_legal_moves_into(&self, game: &mut Game, player: Colour, _magic_bitboard: bool, out_moves: &mut Vec<Move>) {
+        MoveGenerator::generate_pseudo_legal_compact_moves_into(game, player, false, out_moves);
+
+        let all_occ = game.get_board().all_occ().bits();
+        let is_capture = |mv: &Move| mv.kind() == MoveKind::EnPassant || all_occ & mv.to_sq().bit() != 0;
+        if out_moves.iter().any(is_capture) {
+            out_moves.retain(is_capture);
+        }
+    }
+
+    fn game_result(&self, game: &Game) -> Option<GameResult> {
+        let turn = game.get_game_state().get_turn();
+        game.get_board().get_colour_occ(turn).is_empty().then_some(GameResult::VariantLoss(turn.other()))
+    }
+
+    fn no_moves_result(&self, _game: &mut Game, player: Colour, _magic_bitboard: bool) -> GameResult {
+        GameResult::VariantLoss(player.other())
+    }
+
+    fn evaluation_adjustment(&self, game: &Game) -> i32 {
+        let turn = game.get_game_state().get_turn();
+        -MATERIAL_PENALTY * (material(game, turn) - material(game, turn.other()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::engine::minimax::Minimax;
+    use crate::moves::move_parser::MoveParser;
+    use crate::square::Square;
+
+    #[test]
+    fn test_captures_are_compulsory() {
+        let mut game = Game::with_variant(Arc::new(Antichess));
+        game.make_move(&MoveParser::parse_str("e2e4", &game).unwrap());
+        game.make_move(&MoveParser::parse_str("d7d5", &game).unwrap());
+
+        let mut moves = Vec::new();
+        MoveGenerator::generate_legal_moves_into(&mut game, Colour::White, true, &mut moves);
+        assert_eq!(moves, vec![Move::normal(Square::E4, Square::D5)]);
+    }
+
+    #[test]
+    fn test_king_can_be_captured_and_walk_into_attack() {
+        let mut game = Game::with_variant(Arc::new(Antichess));
+        game.set_fenstr("8/8/8/8/8/8/r7/K7 w - - 0 1");
+
+        let mut moves = Vec::new();
+        MoveGenerator::generate_legal_moves_into(&mut game, Colour::White, true, &mut moves);
+        assert_eq!(moves, vec![Move::normal(Square::A1, Square::A2)]);
+
+        // With nothing to take the king may step onto the rook's rank
+        game.set_fenstr("8/8/8/8/8/8/8/K6r w - - 0 1");
+        MoveGenerator::generate_legal_moves_into(&mut game, Colour::White, true, &mut moves);
+        assert_eq!(moves.len(), 3);
+        assert!(moves.contains(&Move::normal(Square::A1, Square::B1)));
+    }
+
+    #[test]
+    fn test_losing_every_piece_wins() {
+        let mut game = Game::with_variant(Arc::new(Antichess));
+        game.set_fenstr("8/8/8/8/8/8/r7/K7 w - - 0 1");
+        game.make_move(&MoveParser::parse_str("a1a2", &game).unwrap());
+
+        let mut moves = Vec::new();
+        MoveGenerator::generate_legal_moves_into(&mut game, Colour::Black, true, &mut moves);
+        assert!(matches!(game.is_game_over_with_moves(&moves, true), Some(GameResult::VariantLoss(Colour::White))));
+    }
+
+    #[test]
+    fn test_less_material_scores_higher() {
+        let mut game = Game::with_variant(Arc::new(Antichess));
+        game.set_fenstr("4k3/8/8/8/8/8/8/Q3K3 w - - 0 1");
+        assert!(Evaluator::evaluate_game_result(&mut game, None, 0, Colour::White) < 0);
+    }
+
+    #[test]
+    fn test_kingless_position_evaluates_and_searches() {
+        // Black has no king, so the passed pawn has no enemy king to measure against
+        let mut game = Game::with_variant(Arc::new(Antichess));
+        game.set_fenstr("8/8/8/8/8/P7/8/4K2r w - - 0 1");
+        Evaluator::evaluate_game_result(&mut game, None, 0, Colour::White);
+
+        let mut engine = Minimax::new(3, 4, true, true);
+        assert!(engine.find_best_move(&mut game, Colour::White).is_some());
+    }
+
+    #[test]
+    fn test_search_gives_away_the_last_piece() {
+        let mut game = Game::with_variant(Arc::new(Antichess));
+        game.set_fenstr("1r6/8/8/8/8/8/8/K7 w - - 0 1");
+
+        // Stepping onto the b-file forces the rook to take the king
+        let mut engine = Minimax::new(3, 4, true, true);
+        let best_move = engine.find_best_move(&mut game, Colour::White).unwrap();
+        assert!(["a1b1", "a1b2"].contains(&best_move.to_string().as_str()), "{}", best_move);
+    }
+}
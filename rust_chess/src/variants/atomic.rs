@@ -0,0 +1,202 @@
+use crate::coords::Coords;
+use crate::enums::{Colour, PieceType, ChessMove, ExecutedMove};
+use crate::game_classes::board_classes::bit_board::BitBoard;
+use crate::game_classes::board_classes::piece_attacks::KING_ATTACKS;
+use crate::game_classes::game::{Game, GameResult};
+use crate::moves::compact_move::{Move, MoveKind};
+use crate::moves::move_generator::MoveGenerator;
+use crate::piece::Piece;
+use crate::square::Square;
+use crate::variants::Variant;
+
+/// Chess where a capture explodes, taking the capturing piece and every piece but pawns around
+/// the capture square off the board. Blowing up the enemy king wins, so kings can't capture and
+/// touching kings can't give each other check.
+pub struct Atomic;
+
+fn king_square(game: &Game, colour: Colour) -> Option<Square> {
+    game.get_board().get_piece_bit_board(Piece { kind: PieceType::King, colour }).lsb().map(Square::from_index)
+}
+
+fn kings_touch(game: &Game) -> bool {
+    match (king_square(game, Colour::White), king_square(game, Colour::Black)) {
+        (Some(white), Some(black)) => white.distance(black) == 1,
+        _ => false,
+    }
+}
+
+// With `player`'s move made: their own king survived, and either the enemy king didn't,
+// the kings touch, or nothing attacks their king
+fn is_legal_after(game: &Game, player: Colour) -> bool {
+    let Some(king_sq) = king_square(game, player) else {
+        return false;
+    };
+    king_square(game, player.other()).is_none() || kings_touch(game) || game.attackers_of(king_sq, player.other()).is_empty()
+}
+
+impl Variant for Atomic {
+    fn name(&self) -> &'static str {
+        "atomic"
+    }
+
+    // A capture takes the capturing piece and every piece but pawns next to the capture square
+    // off the board along with the captured one
+    fn after_move(&self, game: &mut Game, chess_move: &ChessMove, executed_move: ExecutedMove) -> ExecutedMove {
+        if !executed_move.is_capture() {
+            return executed_move;
+        }
+
+        let centre = Square::from(chess_move.to());
+        let neighbours = BitBoard::from_bits(KING_ATTACKS[centre.index()]).squares().map(Square::from_index);
+
+        let mut exploded = Vec::new();
+        for square in std::iter::once(centre).chain(neighbours) {
+            match game.get_board().piece_at(square) {
+                Some(piece) if square == centre || piece.kind != PieceType::Pawn => {
+                    game.remove_piece_at(square);
+                    let (game_state, hash, zobrist) = game.game_state_with_hash();
+                    game_state.revoke_castling_rights_at(Coords::from(square), hash, zobrist);
+                    exploded.push((piece, Coords::from(square)));
+                }
+                _ => {}
+            }
+        }
+
+        ExecutedMove::Explosion { capture: Box::new(executed_move), exploded }
+    }
+
+    fn undo_move(&self, game: &mut Game, executed_move: ExecutedMove) -> ExecutedMove {
+        match executed_move {
+            ExecutedMove::Explosion { capture, exploded } => {
+                for (piece, coords) in exploded.into_iter().rev() {
+                    game.restore_piece_at(Square::from(coords), piece);
+                }
+                *capture
+            }
+            executed_move => executed_move,
+        }
+    }
+
+    fn generate_legal_moves_into(&self, game: &mut Game, player: Colour, _magic_bitboard: bool, out_moves: &mut Vec<Move>) {
+        MoveGenerator::generate_pseudo_legal_compact_moves_into(game, player, true, out_moves);
+
+        let all_occ = game.get_board().all_occ().bits();
+        out_moves.retain(|&mv| {
+            let is_king = game.get_board().piece_at(mv.from_sq()).map(|piece| piece.kind) == Some(PieceType::King);
+            if is_king && mv.kind() == MoveKind::Normal && all_occ & mv.to_sq().bit() != 0 {
+                return false;
+            }
+
+            game.make_compact_move(mv);
+            let legal = is_legal_after(game, player);
+            game.undo_last_move();
+            legal
+        });
+    }
+
+    fn game_result(&self, game: &Game) -> Option<GameResult> {
+        let turn = game.get_game_state().get_turn();
+        king_square(game, turn).is_none().then_some(GameResult::VariantLoss(turn))
+    }
+
+    fn no_moves_result(&self, game: &mut Game, player: Colour, magic_bitboard: bool) -> GameResult {
+        if !kings_touch(game) && game.is_player_in_check(player, magic_bitboard) {
+            GameResult::Checkmate(player)
+        } else {
+            GameResult::Stalemate
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::engine::evaluator::Evaluator;
+    use crate::engine::minimax::Minimax;
+    use crate::game_classes::game_state::CastlingRights;
+    use crate::moves::move_parser::MoveParser;
+
+    #[test]
+    fn test_capture_explodes_neighbours_but_not_pawns() {
+        let mut game = Game::with_variant(Arc::new(Atomic));
+        game.set_fenstr("r3k3/8/4p3/4nb2/4P3/8/8/4K2R w Kq - 0 1");
+        let hash_before = game.get_current_hash();
+
+        game.make_move(&MoveParser::parse_str("e4f5", &game).unwrap());
+        let board = game.get_board();
+        assert_eq!(board.piece_at(Square::F5), None);
+        assert_eq!(board.piece_at(Square::E5), None);
+        assert_eq!(board.piece_at(Square::E6), Some(Piece { kind: PieceType::Pawn, colour: Colour::Black }));
+        assert!(game.get_last_move().unwrap().is_capture());
+        assert!(game.validate().is_empty(), "{:?}", game.validate());
+
+        game.undo_last_move();
+        assert_eq!(game.get_board().piece_at(Square::E5), Some(Piece { kind: PieceType::Knight, colour: Colour::Black }));
+        assert_eq!(game.get_board().piece_at(Square::E4), Some(Piece { kind: PieceType::Pawn, colour: Colour::White }));
+        assert_eq!(game.get_current_hash(), hash_before);
+    }
+
+    #[test]
+    fn test_exploding_a_rook_takes_its_castling_right() {
+        let mut game = Game::with_variant(Arc::new(Atomic));
+        game.set_fenstr("4k1nr/8/8/8/8/8/8/4K1R1 w k - 0 1");
+
+        game.make_move(&MoveParser::parse_str("g1g8", &game).unwrap());
+        assert_eq!(game.get_board().piece_at(Square::H8), None);
+        assert!(!game.get_game_state().can_castle(CastlingRights::BLACK_KINGSIDE));
+        assert!(game.validate().is_empty(), "{:?}", game.validate());
+    }
+
+    #[test]
+    fn test_exploding_the_king_wins() {
+        let mut game = Game::with_variant(Arc::new(Atomic));
+        game.set_fenstr("3qk3/8/8/8/8/8/8/3QK3 w - - 0 1");
+
+        let mut moves = Vec::new();
+        MoveGenerator::generate_legal_moves_into(&mut game, Colour::White, true, &mut moves);
+        // Taking the queen blows up the king beside it
+        assert!(moves.contains(&Move::normal(Square::D1, Square::D8)));
+
+        game.make_move(&MoveParser::parse_str("d1d8", &game).unwrap());
+        MoveGenerator::generate_legal_moves_into(&mut game, Colour::Black, true, &mut moves);
+        assert!(moves.is_empty());
+        assert!(matches!(game.is_game_over_with_moves(&moves, true), Some(GameResult::VariantLoss(Colour::Black))));
+    }
+
+    #[test]
+    fn test_kings_cannot_capture_and_touching_kings_are_safe() {
+        let mut game = Game::with_variant(Arc::new(Atomic));
+        game.set_fenstr("8/8/8/8/8/3k4/3r4/3K4 w - - 0 1");
+
+        let mut moves = Vec::new();
+        MoveGenerator::generate_legal_moves_into(&mut game, Colour::White, true, &mut moves);
+        assert!(!moves.contains(&Move::normal(Square::D1, Square::D2)));
+        // The rook attacks e2, but a king touching the black king can't be taken
+        assert!(moves.contains(&Move::normal(Square::D1, Square::E2)));
+    }
+
+    #[test]
+    fn test_search_blows_up_the_king() {
+        let mut game = Game::with_variant(Arc::new(Atomic));
+        game.set_fenstr("4k3/3n4/1N6/8/8/8/8/4K3 w - - 0 1");
+
+        let mut engine = Minimax::new(3, 4, true, true);
+        let best_move = engine.find_best_move(&mut game, Colour::White).unwrap();
+        assert_eq!(best_move.to_string(), "b6d7");
+    }
+
+    #[test]
+    fn test_kingless_position_evaluates_and_searches() {
+        // With a passed pawn on the board the search stands pat after the king explodes
+        let mut game = Game::with_variant(Arc::new(Atomic));
+        game.set_fenstr("4k3/3n4/1N6/8/8/P7/8/4K3 w - - 0 1");
+
+        let mut engine = Minimax::new(3, 4, true, true);
+        let best_move = engine.find_best_move(&mut game, Colour::White).unwrap();
+        assert_eq!(best_move.to_string(), "b6d7");
+
+        game.make_move(&best_move);
+        Evaluator::evaluate_game_result(&mut game, None, 0, Colour::Black);
+    }
+}
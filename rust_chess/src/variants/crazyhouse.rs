@@ -0,0 +1,176 @@
+use crate::enums::piece_types::PIECE_TYPES;
+use crate::enums::{Colour, PieceType, ChessMove, ExecutedMove};
+use crate::game_classes::board_classes::bit_board::BitBoard;
+use crate::engine::evaluator::Evaluator;
+use crate::game_classes::game::Game;
+use crate::moves::compact_move::Move;
+use crate::moves::move_generator::MoveGenerator;
+use crate::piece::Piece;
+use crate::square::Square;
+use crate::variants::Variant;
+
+// Share of its value a piece in hand is worth, in percent. A piece that can be dropped
+// anywhere is worth more than one on the board.
+const POCKET_VALUE_PERCENT: i32 = 120;
+
+/// Chess where captured pieces change sides, going into the capturer's pocket to be dropped
+/// back onto an empty square instead of moving
+pub struct Crazyhouse;
+
+fn pocket_value(game: &Game, colour: Colour) -> i32 {
+    PIECE_TYPES[..5].iter()
+        .map(|&kind| game.get_game_state().pocket_count(colour, kind) as i32 * Evaluator::get_piece_value(kind))
+        .sum()
+}
+
+impl Variant for Crazyhouse {
+    fn name(&self) -> &'static str {
+        "crazyhouse"
+    }
+
+    fn start_fenstr(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1"
+    }
+
+    // A captured piece goes into the capturer's pocket, as a pawn again if it had been promoted,
+    // and promoted pieces are followed as they move. The undo record restores both.
+    fn after_move(&self, game: &mut Game, chess_move: &ChessMove, executed_move: ExecutedMove) -> ExecutedMove {
+        let captured = match executed_move {
+            ExecutedMove::Normal { captured_piece, .. } | ExecutedMove::Promotion { captured_piece, .. } => captured_piece,
+            ExecutedMove::EnPassant { mv } => Some(Piece { kind: PieceType::Pawn, colour: mv.colour.other() }),
+            _ => None,
+        };
+
+        let (game_state, hash, zobrist) = game.game_state_with_hash();
+        let promoted = game_state.get_promoted().bits();
+        let (from, to) = (Square::from(chess_move.from()), Square::from(chess_move.to()));
+        if let Some(captured) = captured {
+            let kind = if promoted & to.bit() != 0 { PieceType::Pawn } else { captured.kind };
+            game_state.add_to_pocket(chess_move.colour(), kind, hash, zobrist);
+        }
+
+        let stays_promoted = promoted & from.bit() != 0 || matches!(chess_move, ChessMove::Promotion(_));
+        let mut promoted = promoted & !from.bit() & !to.bit();
+        if stays_promoted {
+            promoted |= to.bit();
+        }
+        game_state.set_promoted(BitBoard::from_bits(promoted));
+
+        executed_move
+    }
+
+    fn generate_legal_moves_into(&self, game: &mut Game, player: Colour, magic_bitboard: bool, out_moves: &mut Vec<Move>) {
+        MoveGenerator::generate_standard_legal_moves_into(game, player, magic_bitboard, out_moves);
+        MoveGenerator::generate_drop_moves_into(game, player, out_moves);
+    }
+
+    fn evaluation_adjustment(&self, game: &Game) -> i32 {
+        let turn = game.get_game_state().get_turn();
+        (pocket_value(game, turn) - pocket_value(game, turn.other())) * POCKET_VALUE_PERCENT / 100
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::engine::minimax::Minimax;
+    use crate::game_classes::game::GameResult;
+    use crate::moves::move_parser::MoveParser;
+
+    fn crazyhouse_game(fenstr: &str) -> Game {
+        let mut game = Game::with_variant(Arc::new(Crazyhouse));
+        game.set_fenstr(fenstr);
+        game
+    }
+
+    #[test]
+    fn test_captured_piece_goes_to_pocket_and_drops_back() {
+        let mut game = Game::with_variant(Arc::new(Crazyhouse));
+        let hash_start = game.get_current_hash();
+        for mv in ["e2e4", "d7d5", "e4d5"] {
+            game.make_move(&MoveParser::parse_str(mv, &game).unwrap());
+        }
+        assert_eq!(game.get_game_state().pocket_count(Colour::White, PieceType::Pawn), 1);
+        let hash_before_drop = game.get_current_hash();
+
+        game.make_move(&MoveParser::parse_str("d8d5", &game).unwrap());
+        let drop = MoveParser::parse_str("P@e6", &game).unwrap();
+        assert_eq!(drop.to_uci(false), "P@e6");
+        game.make_move(&drop);
+        assert_eq!(game.get_board().piece_at(Square::E6), Some(Piece { kind: PieceType::Pawn, colour: Colour::White }));
+        assert_eq!(game.get_game_state().pocket_count(Colour::White, PieceType::Pawn), 0);
+        assert_eq!(game.get_game_state().pocket_count(Colour::Black, PieceType::Pawn), 1);
+        assert!(game.validate().is_empty(), "{:?}", game.validate());
+
+        game.undo_last_move();
+        game.undo_last_move();
+        assert_eq!(game.get_current_hash(), hash_before_drop);
+        for _ in 0..3 {
+            game.undo_last_move();
+        }
+        assert_eq!(game.get_current_hash(), hash_start);
+    }
+
+    #[test]
+    fn test_pockets_and_promoted_pieces_from_fenstr() {
+        let bracketed = crazyhouse_game("4k3/8/8/8/8/8/8/Q~3K3[NPpp] w - - 0 1");
+        assert_eq!(bracketed.get_game_state().pocket_count(Colour::White, PieceType::Knight), 1);
+        assert_eq!(bracketed.get_game_state().pocket_count(Colour::Black, PieceType::Pawn), 2);
+        assert!(bracketed.get_game_state().get_promoted().contains_square(Square::A1.index()));
+
+        // The pockets can also be written as a ninth rank
+        let ninth_rank = crazyhouse_game("4k3/8/8/8/8/8/8/Q~3K3/NPpp w - - 0 1");
+        assert_eq!(ninth_rank.get_current_hash(), bracketed.get_current_hash());
+
+        let empty = crazyhouse_game("4k3/8/8/8/8/8/8/Q~3K3[] w - - 0 1");
+        assert_ne!(empty.get_current_hash(), bracketed.get_current_hash());
+    }
+
+    #[test]
+    fn test_promoted_piece_is_captured_as_pawn() {
+        let mut game = crazyhouse_game("4k3/8/8/8/8/8/4K3/Q~6r[] b - - 0 1");
+        game.make_move(&MoveParser::parse_str("h1a1", &game).unwrap());
+        assert_eq!(game.get_game_state().pocket_count(Colour::Black, PieceType::Pawn), 1);
+        assert_eq!(game.get_game_state().pocket_count(Colour::Black, PieceType::Queen), 0);
+        assert!(game.get_game_state().get_promoted().is_empty());
+
+        // Promoting marks the new piece, and the mark follows it
+        let mut game = crazyhouse_game("8/P3k3/8/8/8/8/8/4K3[] w - - 0 1");
+        game.make_move(&MoveParser::parse_str("a7a8q", &game).unwrap());
+        game.make_move(&MoveParser::parse_str("e7e6", &game).unwrap());
+        game.make_move(&MoveParser::parse_str("a8a5", &game).unwrap());
+        assert!(game.get_game_state().get_promoted().contains_square(Square::A5.index()));
+        assert!(game.validate().is_empty(), "{:?}", game.validate());
+    }
+
+    #[test]
+    fn test_drop_moves_respect_rank_limits_and_checks() {
+        let mut game = crazyhouse_game("4k3/8/8/8/8/8/8/4K3[P] w - - 0 1");
+        let mut moves = Vec::new();
+        MoveGenerator::generate_legal_moves_into(&mut game, Colour::White, true, &mut moves);
+        let drops: Vec<Move> = moves.iter().copied().filter(|mv| mv.drop_piece().is_some()).collect();
+        assert_eq!(drops.len(), 48);
+        assert!(!drops.contains(&Move::drop(PieceType::Pawn, Square::A8)));
+
+        // In check from a rook only drops on the squares between block it
+        let mut game = crazyhouse_game("4k3/8/8/8/8/8/8/r3K3[N] w - - 0 1");
+        MoveGenerator::generate_legal_moves_into(&mut game, Colour::White, true, &mut moves);
+        let drops: Vec<String> = moves.iter().filter(|mv| mv.drop_piece().is_some()).map(|mv| mv.to_string()).collect();
+        assert_eq!(drops, vec!["N@b1", "N@c1", "N@d1"]);
+    }
+
+    #[test]
+    fn test_search_finds_mate_by_drop() {
+        let mut game = crazyhouse_game("6k1/5ppp/8/8/8/8/8/4K3[R] w - - 0 1");
+
+        let mut engine = Minimax::new(2, 4, true, true);
+        let best_move = engine.find_best_move(&mut game, Colour::White).unwrap();
+        assert!(matches!(best_move, ChessMove::Drop(_)), "{}", best_move);
+
+        game.make_move(&best_move);
+        let mut moves = Vec::new();
+        MoveGenerator::generate_legal_moves_into(&mut game, Colour::Black, true, &mut moves);
+        assert!(matches!(game.is_game_over_with_moves(&moves, true), Some(GameResult::Checkmate(Colour::Black))));
+    }
+}
@@ -0,0 +1,76 @@
+use crate::enums::PieceType;
+use crate::game_classes::game::{Game, GameResult};
+use crate::piece::Piece;
+use crate::square::Square;
+use crate::variants::Variant;
+
+const HILL: [Square; 4] = [Square::D4, Square::E4, Square::D5, Square::E5];
+// Worth of the king being one, two or three steps from the hill
+const HILL_DISTANCE_BONUS: [i32; 3] = [120, 40, 10];
+
+/// Orthodox chess that is also won by bringing the king to one of the four centre squares
+pub struct KingOfTheHill;
+
+// Steps `king` needs to reach the hill, None when it isn't on the board
+fn hill_distance(game: &Game, king: Piece) -> Option<u8> {
+    let king_sq = game.get_board().get_piece_bit_board(king).lsb().map(Square::from_index)?;
+    HILL.iter().map(|&square| king_sq.distance(square)).min()
+}
+
+impl Variant for KingOfTheHill {
+    fn name(&self) -> &'static str {
+        "kingofthehill"
+    }
+
+    fn game_result(&self, game: &Game) -> Option<GameResult> {
+        let turn = game.get_game_state().get_turn();
+        let king = Piece { kind: PieceType::King, colour: turn.other() };
+        (hill_distance(game, king) == Some(0)).then_some(GameResult::VariantLoss(turn))
+    }
+
+    fn evaluation_adjustment(&self, game: &Game) -> i32 {
+        let turn = game.get_game_state().get_turn();
+        let bonus = |colour| match hill_distance(game, Piece { kind: PieceType::King, colour }) {
+            Some(distance @ 1..=3) => HILL_DISTANCE_BONUS[distance as usize - 1],
+            _ => 0,
+        };
+        bonus(turn) - bonus(turn.other())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::enums::Colour;
+    use crate::engine::evaluator::Evaluator;
+    use crate::moves::move_generator::MoveGenerator;
+    use crate::moves::move_parser::MoveParser;
+
+    #[test]
+    fn test_reaching_the_hill_wins() {
+        let mut game = Game::with_variant(Arc::new(KingOfTheHill));
+        game.set_fenstr("7k/8/8/8/8/4K3/8/8 w - - 0 1");
+
+        game.make_move(&MoveParser::parse_str("e3e4", &game).unwrap());
+        let mut moves = Vec::new();
+        let turn = game.get_game_state().get_turn();
+        MoveGenerator::generate_legal_moves_into(&mut game, turn, true, &mut moves);
+        assert!(!moves.is_empty());
+        assert!(matches!(game.is_game_over_with_moves(&moves, true), Some(GameResult::VariantLoss(Colour::Black))));
+    }
+
+    #[test]
+    fn test_king_near_hill_scores_higher() {
+        let mut near = Game::with_variant(Arc::new(KingOfTheHill));
+        near.set_fenstr("7k/8/8/8/8/4K3/8/8 w - - 0 1");
+        let mut far = Game::with_variant(Arc::new(KingOfTheHill));
+        far.set_fenstr("7k/8/8/8/8/8/8/K7 w - - 0 1");
+        let mut standard = Game::new();
+        standard.set_fenstr("7k/8/8/8/8/4K3/8/8 w - - 0 1");
+
+        let score = |game: &mut Game| Evaluator::evaluate_game_result(game, None, 0, Colour::White);
+        assert!(score(&mut near) > score(&mut far));
+        assert_eq!(score(&mut near), score(&mut standard) + 120 - 10);
+    }
+}
@@ -0,0 +1,153 @@
+pub mod standard;
+pub mod three_check;
+pub mod king_of_the_hill;
+pub mod atomic;
+pub mod antichess;
+pub mod crazyhouse;
+
+use std::sync::Arc;
+
+use crate::enums::{Colour, ChessMove, ExecutedMove};
+use crate::game_classes::game::{Game, GameResult};
+use crate::moves::compact_move::Move;
+use crate::moves::move_generator::MoveGenerator;
+
+pub use standard::Standard;
+pub use three_check::ThreeCheck;
+pub use king_of_the_hill::KingOfTheHill;
+pub use atomic::Atomic;
+pub use antichess::Antichess;
+pub use crazyhouse::Crazyhouse;
+
+pub const STANDARD_START_FENSTR: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// The rules a `Game` is played under. Every hook defaults to orthodox chess, so a variant
+/// only overrides what it changes.
+pub trait Variant: Send + Sync {
+    /// Name as used by the UCI `UCI_Variant` option
+    fn name(&self) -> &'static str;
+
+    fn start_fenstr(&self) -> &'static str {
+        STANDARD_START_FENSTR
+    }
+
+    /// Legal moves for `player`. Variants can build on the orthodox moves, filtering or
+    /// adding to them, or generate their own.
+    fn generate_legal_moves_into(&self, game: &mut Game, player: Colour, magic_bitboard: bool, out_moves: &mut Vec<Move>) {
+        MoveGenerator::generate_standard_legal_moves_into(game, player, magic_bitboard, out_moves);
+    }
+
+    /// A result the variant's own rules decide whatever moves are left, such as a third check.
+    /// Checked at every node of the search, so it should be cheap.
+    fn game_result(&self, _game: &Game) -> Option<GameResult> {
+        None
+    }
+
+    /// Called once `chess_move` is on the board, before the clocks advance, to apply the
+    /// variant's side effects. Returns the move as it should be kept for undoing, wrapped up
+    /// with whatever `undo_move` needs to take those effects back.
+    fn after_move(&self, _game: &mut Game, _chess_move: &ChessMove, executed_move: ExecutedMove) -> ExecutedMove {
+        executed_move
+    }
+
+    /// Puts back the board changes `after_move` made, returning the move underneath for
+    /// `Game` to undo. The game state and hashes are restored from the undo record.
+    fn undo_move(&self, _game: &mut Game, executed_move: ExecutedMove) -> ExecutedMove {
+        executed_move
+    }
+
+    /// Result when `player` has no legal moves
+    fn no_moves_result(&self, game: &mut Game, player: Colour, magic_bitboard: bool) -> GameResult {
+        if game.is_player_in_check(player, magic_bitboard) {
+            GameResult::Checkmate(player)
+        } else {
+            GameResult::Stalemate
+        }
+    }
+
+    /// Added to the static evaluation, from the side to move's point of view
+    fn evaluation_adjustment(&self, _game: &Game) -> i32 {
+        0
+    }
+}
+
+/// The variant called `name`, as given to `UCI_Variant`
+pub fn from_name(name: &str) -> Option<Arc<dyn Variant>> {
+    match name.to_lowercase().as_str() {
+        "chess" | "standard" => Some(Arc::new(Standard)),
+        "3check" | "threecheck" => Some(Arc::new(ThreeCheck)),
+        "kingofthehill" | "koth" => Some(Arc::new(KingOfTheHill)),
+        "atomic" => Some(Arc::new(Atomic)),
+        "antichess" | "giveaway" => Some(Arc::new(Antichess)),
+        "crazyhouse" | "zh" => Some(Arc::new(Crazyhouse)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::evaluator::Evaluator;
+    use crate::piece::Piece;
+    use crate::enums::PieceType;
+    use crate::square::Square;
+
+    // Only pawns move, and a side with no pawns left has lost
+    struct PawnsOnly;
+
+    impl Variant for PawnsOnly {
+        fn name(&self) -> &'static str {
+            "pawnsonly"
+        }
+
+        fn start_fenstr(&self) -> &'static str {
+            "4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1"
+        }
+
+        fn generate_legal_moves_into(&self, game: &mut Game, player: Colour, magic_bitboard: bool, out_moves: &mut Vec<Move>) {
+            MoveGenerator::generate_standard_legal_moves_into(game, player, magic_bitboard, out_moves);
+            out_moves.retain(|mv| game.get_board().piece_at(mv.from_sq()).map(|piece| piece.kind) == Some(PieceType::Pawn));
+        }
+
+        fn game_result(&self, game: &Game) -> Option<GameResult> {
+            let colour = game.get_game_state().get_turn();
+            let pawns = game.get_board().get_piece_bit_board(Piece { kind: PieceType::Pawn, colour });
+            pawns.is_empty().then_some(GameResult::VariantLoss(colour))
+        }
+
+        fn evaluation_adjustment(&self, _game: &Game) -> i32 {
+            100
+        }
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(from_name("Chess").map(|variant| variant.name()), Some("chess"));
+        assert!(from_name("shogi").is_none());
+    }
+
+    #[test]
+    fn test_game_follows_variant_hooks() {
+        let mut game = Game::with_variant(Arc::new(PawnsOnly));
+        assert_eq!(game.get_board().piece_at(Square::D1), None);
+
+        let mut moves = Vec::new();
+        MoveGenerator::generate_legal_moves_into(&mut game, Colour::White, true, &mut moves);
+        assert_eq!(moves.len(), 16);
+        assert!(game.is_game_over_with_moves(&moves, true).is_none());
+
+        let standard_score = {
+            let mut standard = Game::new();
+            standard.set_fenstr(PawnsOnly.start_fenstr());
+            Evaluator::evaluate_game_result(&mut standard, None, 0, Colour::White)
+        };
+        assert_eq!(Evaluator::evaluate_game_result(&mut game, None, 0, Colour::White), standard_score + 100);
+
+        game.set_fenstr("4k3/pppppppp/8/8/8/8/8/4K3 w - - 0 1");
+        MoveGenerator::generate_legal_moves_into(&mut game, Colour::White, true, &mut moves);
+        assert!(moves.is_empty());
+        let result = game.is_game_over_with_moves(&moves, true);
+        assert!(matches!(result, Some(GameResult::VariantLoss(Colour::White))));
+        assert!(Evaluator::evaluate_game_result(&mut game, result, 0, Colour::White) < -20_000);
+    }
+}
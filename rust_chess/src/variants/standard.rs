@@ -0,0 +1,10 @@
+use crate::variants::Variant;
+
+/// Orthodox chess, which every hook of `Variant` already describes
+pub struct Standard;
+
+impl Variant for Standard {
+    fn name(&self) -> &'static str {
+        "chess"
+    }
+}
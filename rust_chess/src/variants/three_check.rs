@@ -0,0 +1,91 @@
+use crate::enums::{ChessMove, ExecutedMove};
+use crate::game_classes::game::{Game, GameResult};
+use crate::variants::Variant;
+
+// Worth of having given one or two checks, the third ends the game
+const CHECK_BONUS: [i32; 3] = [0, 80, 250];
+
+/// Orthodox chess that is also won by giving check three times
+pub struct ThreeCheck;
+
+impl Variant for ThreeCheck {
+    fn name(&self) -> &'static str {
+        "3check"
+    }
+
+    // Checks given are counted in the game state, which the undo record restores
+    fn after_move(&self, game: &mut Game, chess_move: &ChessMove, executed_move: ExecutedMove) -> ExecutedMove {
+        if !game.checkers().is_empty() {
+            let (game_state, hash, zobrist) = game.game_state_with_hash();
+            game_state.add_check(chess_move.colour(), hash, zobrist);
+        }
+        executed_move
+    }
+
+    fn game_result(&self, game: &Game) -> Option<GameResult> {
+        let turn = game.get_game_state().get_turn();
+        (game.get_game_state().get_checks_given(turn.other()) >= 3).then_some(GameResult::VariantLoss(turn))
+    }
+
+    fn evaluation_adjustment(&self, game: &Game) -> i32 {
+        let game_state = game.get_game_state();
+        let turn = game_state.get_turn();
+        let bonus = |checks: u8| CHECK_BONUS[(checks as usize).min(2)];
+        bonus(game_state.get_checks_given(turn)) - bonus(game_state.get_checks_given(turn.other()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::enums::Colour;
+    use crate::engine::evaluator::Evaluator;
+    use crate::moves::move_parser::MoveParser;
+
+    #[test]
+    fn test_third_check_wins() {
+        let mut game = Game::with_variant(Arc::new(ThreeCheck));
+        game.set_fenstr("4k3/8/8/8/8/8/8/4K2R w - - 1+3 0 1");
+        assert_eq!(game.get_game_state().get_checks_given(Colour::White), 2);
+        let hash_before = game.get_current_hash();
+
+        let check = MoveParser::parse_str("h1h8", &game).unwrap();
+        game.make_move(&check);
+        assert_eq!(game.get_game_state().get_checks_given(Colour::White), 3);
+        assert!(matches!(game.is_game_over_with_moves(&[], true), Some(GameResult::VariantLoss(Colour::Black))));
+        assert!(game.validate().is_empty(), "{:?}", game.validate());
+
+        game.undo_last_move();
+        assert_eq!(game.get_game_state().get_checks_given(Colour::White), 2);
+        assert_eq!(game.get_current_hash(), hash_before);
+    }
+
+    #[test]
+    fn test_checks_given_notation_and_hashing() {
+        // Stockfish writes checks given after the clocks, lichess checks remaining before them
+        let mut given = Game::with_variant(Arc::new(ThreeCheck));
+        given.set_fenstr("4k3/8/8/8/8/8/8/4K2R w - - 0 1 +1+0");
+        let mut remaining = Game::with_variant(Arc::new(ThreeCheck));
+        remaining.set_fenstr("4k3/8/8/8/8/8/8/4K2R w - - 2+3 0 1");
+        assert_eq!(given.get_current_hash(), remaining.get_current_hash());
+
+        // The same position with a different count is a different position
+        let mut none = Game::with_variant(Arc::new(ThreeCheck));
+        none.set_fenstr("4k3/8/8/8/8/8/8/4K2R w - - 0 1");
+        assert_ne!(none.get_current_hash(), given.get_current_hash());
+        assert!(Evaluator::evaluate_game_result(&mut given, None, 0, Colour::White)
+            > Evaluator::evaluate_game_result(&mut none, None, 0, Colour::White));
+    }
+
+    #[test]
+    fn test_checks_not_counted_in_standard_chess() {
+        let mut game = Game::new();
+        game.set_fenstr("4k3/8/8/8/8/8/8/4K2R w - - 0 1");
+        let hash = game.get_current_hash();
+        game.make_move(&MoveParser::parse_str("h1h8", &game).unwrap());
+        assert_eq!(game.get_game_state().get_checks_given(Colour::White), 0);
+        game.undo_last_move();
+        assert_eq!(game.get_current_hash(), hash);
+    }
+}
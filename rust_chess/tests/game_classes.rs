@@ -77,6 +77,20 @@ fn test_castling_rights_revoked_after_king_move() {
     assert!(!game.get_game_state().can_castle(CastlingRights::WHITE_QUEENSIDE));
 }
 
+#[test]
+fn test_castling_right_revoked_when_rook_captured() {
+    let mut game = Game::new();
+    game.set_fenstr("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+
+    let mv = normal_move(PieceType::Rook, Colour::White, Coords::new(1, File::A), Coords::new(8, File::A));
+    game.make_move(&mv);
+
+    assert!(!game.get_game_state().can_castle(CastlingRights::WHITE_QUEENSIDE));
+    assert!(!game.get_game_state().can_castle(CastlingRights::BLACK_QUEENSIDE));
+    assert!(game.get_game_state().can_castle(CastlingRights::BLACK_KINGSIDE));
+    assert_eq!(game.validate(), Vec::<String>::new());
+}
+
 #[test]
 #[should_panic]
 fn test_illegal_move_wrong_turn_panics() {
@@ -86,3 +100,26 @@ fn test_illegal_move_wrong_turn_panics() {
     let mut mv = normal_move(PieceType::Pawn, Colour::Black, Coords::new(7, File::E), Coords::new(5, File::E));
     game.make_move(&mut mv); // should panic
 }
+
+#[test]
+fn test_hashes_match_across_games() {
+    let e2e4 = normal_move(PieceType::Pawn, Colour::White, Coords::new(2, File::E), Coords::new(4, File::E));
+
+    let mut first = Game::new();
+    let mut second = Game::new();
+    assert_eq!(first.get_current_hash(), second.get_current_hash());
+
+    first.make_move(&e2e4);
+    second.set_fenstr("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+    assert_eq!(first.get_current_hash(), second.get_current_hash(), "Same position should hash the same in different games");
+
+    // A game with its own seed hashes the same position differently
+    let mut seeded = Game::with_zobrist_seed(42);
+    seeded.make_move(&e2e4);
+    assert_ne!(seeded.get_current_hash(), first.get_current_hash());
+    assert_eq!(seeded.get_current_hash(), {
+        let mut again = Game::with_zobrist_seed(42);
+        again.make_move(&e2e4);
+        again.get_current_hash()
+    });
+}
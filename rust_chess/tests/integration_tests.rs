@@ -1,5 +1,10 @@
 use std::time::Instant;
 use rust_chess::{enums::moves, PyMinimax};
+use rust_chess::engine::evaluator::Evaluator;
+use rust_chess::game_classes::game::Game;
+use rust_chess::move_ordering::order_moves;
+use rust_chess::moves::compact_move::Move;
+use rust_chess::moves::move_generator::MoveGenerator;
 
 const STARTPOS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
@@ -403,4 +408,66 @@ fn test_find_mate_in_one() {
         .map(|(_, score)| *score)
         .unwrap();
     assert!(actual_score > 25000, "{} should be winning (mate in 1). Eval: {}", mate_str, actual_score);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_move_ordering_mailbox_speedup() {
+    // Capture-heavy middlegame positions where most moves need a square lookup
+    let fens = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    ];
+    let iterations = 2000;
+
+    // The same MVV-LVA ordering, but finding pieces by testing every bitboard as before the mailbox
+    fn order_moves_by_scanning(moves: &mut [Move], game: &Game) {
+        let board = game.get_board();
+        moves.sort_unstable_by_key(|mv| match board.scan_bit_boards(mv.to_sq()) {
+            Some(victim) => {
+                let attacker = board.scan_bit_boards(mv.from_sq()).unwrap();
+                100_000 + Evaluator::get_piece_value(victim.kind) - Evaluator::get_piece_value(attacker.kind)
+            }
+            None if mv.promotion_piece().is_some() => 50_000,
+            None => 0,
+        });
+        moves.reverse();
+    }
+
+    let mut scan_duration = std::time::Duration::ZERO;
+    let mut mailbox_duration = std::time::Duration::ZERO;
+
+    for fen in fens {
+        let mut game = Game::new();
+        game.set_fenstr(fen);
+        let colour = game.get_game_state().get_turn();
+        let mut generated = Vec::new();
+        MoveGenerator::generate_legal_moves_into(&mut game, colour, true, &mut generated);
+
+        let mut moves = generated.clone();
+        let start = Instant::now();
+        for _ in 0..iterations {
+            moves.copy_from_slice(&generated);
+            order_moves_by_scanning(&mut moves, &game);
+        }
+        scan_duration += start.elapsed();
+        let scanned_captures: Vec<Move> = moves.iter().copied().take_while(|mv| game.get_board().piece_at(mv.to_sq()).is_some()).collect();
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            moves.copy_from_slice(&generated);
+            order_moves(&mut moves, &game);
+        }
+        mailbox_duration += start.elapsed();
+
+        // Both orderings must put the same captures first
+        let mut mailbox_captures: Vec<Move> = moves.iter().copied().take_while(|mv| game.get_board().piece_at(mv.to_sq()).is_some()).collect();
+        let mut scanned_captures = scanned_captures;
+        mailbox_captures.sort_by_key(|mv| mv.raw());
+        scanned_captures.sort_by_key(|mv| mv.raw());
+        assert_eq!(mailbox_captures, scanned_captures);
+    }
+
+    // A few thousand sorts of short move lists finish in microseconds, so a busy machine can easily swap the two
+    println!("Move ordering: bitboard scan = {:?}, mailbox = {:?}", scan_duration, mailbox_duration);
+}
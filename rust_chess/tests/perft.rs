@@ -0,0 +1,293 @@
+use std::time::Instant;
+
+use rust_chess::coords::Coords;
+use rust_chess::enums::moves::DropMove;
+use rust_chess::enums::{ChessMove, PieceType};
+use rust_chess::game_classes::game::Game;
+use rust_chess::moves::compact_move::Move;
+use rust_chess::moves::move_generator::MoveGenerator;
+use rust_chess::moves::perft::{perft, perft_divide};
+use rust_chess::square::Square;
+use rust_chess::variants;
+
+const STARTPOS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+const POSITION_6: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+// Chess960 positions with Shredder-FEN castling fields
+const CHESS960_1: &str = "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9";
+const CHESS960_2: &str = "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9";
+const CHESS960_3: &str = "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9";
+
+const CRAZYHOUSE_1: &str = "r1bqk2r/pppp1ppp/2n5/4p3/1b2P3/2N2N2/PPPP1PPP/R1BQKB1R[Pn] w KQkq - 0 5";
+const CRAZYHOUSE_2: &str = "4k3/8/8/3q4/8/8/3P4/r3K3[QRBNPqrbnp] w - - 0 1";
+
+fn game_from_fen(fen: &str) -> Game {
+    let mut game = Game::new();
+    game.set_fenstr(fen);
+    game
+}
+
+fn assert_perft(fen: &str, expected: &[u64]) {
+    let mut game = game_from_fen(fen);
+    for (i, &nodes) in expected.iter().enumerate() {
+        let depth = i + 1;
+        assert_eq!(perft(&mut game, depth, true), nodes, "perft({}) mismatch for {}", depth, fen);
+    }
+}
+
+#[test]
+fn test_perft_startpos() {
+    assert_perft(STARTPOS, &[20, 400, 8902, 197281]);
+}
+
+#[test]
+fn test_perft_kiwipete() {
+    assert_perft(KIWIPETE, &[48, 2039, 97862]);
+}
+
+#[test]
+fn test_perft_position_3() {
+    assert_perft(POSITION_3, &[14, 191, 2812, 43238]);
+}
+
+#[test]
+fn test_perft_position_4() {
+    assert_perft(POSITION_4, &[6, 264, 9467]);
+}
+
+#[test]
+fn test_perft_position_5() {
+    assert_perft(POSITION_5, &[44, 1486, 62379]);
+}
+
+#[test]
+fn test_perft_position_6() {
+    assert_perft(POSITION_6, &[46, 2079, 89890]);
+}
+
+#[test]
+fn test_perft_chess960() {
+    assert_perft(CHESS960_1, &[21, 528, 12189, 326672]);
+    assert_perft(CHESS960_2, &[21, 807, 18002, 667366]);
+    assert_perft(CHESS960_3, &[20, 479, 10471, 273318]);
+}
+
+#[test]
+fn test_perft_variants() {
+    // Atomic first parts from orthodox chess at depth 4, where captures start exploding
+    // Crazyhouse only parts from it at depth 5, the first drop coming after the first capture
+    for (name, expected) in [
+        ("atomic", [20, 400, 8902, 197326]),
+        ("antichess", [20, 400, 8067, 153299]),
+        ("crazyhouse", [20, 400, 8902, 197281]),
+    ] {
+        let mut game = Game::with_variant(variants::from_name(name).unwrap());
+        for (i, &nodes) in expected.iter().enumerate() {
+            assert_eq!(perft(&mut game, i + 1, true), nodes, "perft({}) mismatch for {}", i + 1, name);
+        }
+    }
+}
+
+#[test]
+fn test_perft_matches_filtered_generation() {
+    // The pin-aware generator must produce exactly the moves that survive make/undo filtering
+    for fen in [STARTPOS, KIWIPETE, POSITION_3, POSITION_4, POSITION_5, POSITION_6, CHESS960_1, CHESS960_2, CHESS960_3] {
+        let mut game = game_from_fen(fen);
+        let to_move = game.get_game_state().get_turn();
+
+        let mut legal = Vec::new();
+        MoveGenerator::generate_legal_moves_into(&mut game, to_move, true, &mut legal);
+        let mut filtered = Vec::new();
+        MoveGenerator::generate_legal_moves_by_filtering_into(&mut game, to_move, true, &mut filtered);
+
+        let mut legal: Vec<String> = legal.iter().map(|m| m.to_string()).collect();
+        let mut filtered: Vec<String> = filtered.iter().map(|m| Move::from(m).to_string()).collect();
+        legal.sort();
+        filtered.sort();
+        assert_eq!(legal, filtered, "Legal move sets differ for {}", fen);
+
+        // Move rays and magic bitboards should agree one ply deeper as well
+        let magic = perft_divide(&mut game, 2, true);
+        let rays = perft_divide(&mut game, 2, false);
+        let mut magic: Vec<_> = magic.into_iter().collect();
+        let mut rays: Vec<_> = rays.into_iter().collect();
+        magic.sort();
+        rays.sort();
+        assert_eq!(magic, rays, "perft divide differs between generators for {}", fen);
+    }
+}
+
+#[test]
+fn test_drops_match_filtered_generation() {
+    // Every drop of a piece in hand onto an empty square that doesn't leave the king in check,
+    // pawns kept off the back ranks, checked two plies deep
+    fn walk(game: &mut Game, depth: usize) {
+        let to_move = game.get_game_state().get_turn();
+        let mut legal = Vec::new();
+        MoveGenerator::generate_legal_moves_into(game, to_move, true, &mut legal);
+        let mut legal: Vec<String> = legal.iter().filter(|mv| mv.drop_piece().is_some()).map(|mv| mv.to_string()).collect();
+
+        let mut filtered = Vec::new();
+        for piece_type in [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+            if game.get_game_state().pocket_count(to_move, piece_type) == 0 {
+                continue;
+            }
+            for square in 0..64 {
+                let to = Coords::from(Square::new(square));
+                if game.get_board().get_coords(&to).is_some() || (piece_type == PieceType::Pawn && (to.rank == 1 || to.rank == 8)) {
+                    continue;
+                }
+                let drop = ChessMove::Drop(DropMove { colour: to_move, piece_type, to });
+                game.make_move(&drop);
+                if !game.is_player_in_check(to_move, true) {
+                    filtered.push(drop.to_uci(false));
+                }
+                game.undo_last_move();
+            }
+        }
+
+        legal.sort();
+        filtered.sort();
+        assert_eq!(legal, filtered, "Drops differ after {:?}", game.get_last_move().map(|mv| mv.to_string()));
+
+        if depth > 1 {
+            let mut moves = Vec::new();
+            MoveGenerator::generate_legal_moves_into(game, to_move, true, &mut moves);
+            for mv in moves {
+                game.make_compact_move(mv);
+                walk(game, depth - 1);
+                game.undo_last_move();
+            }
+        }
+    }
+
+    for fen in [CRAZYHOUSE_1, CRAZYHOUSE_2] {
+        let mut game = Game::with_variant(variants::from_name("crazyhouse").unwrap());
+        game.set_fenstr(fen);
+        walk(&mut game, 2);
+    }
+}
+
+#[test]
+fn test_perft_speed_comparison() {
+    let depth = 3;
+    let mut game = game_from_fen(KIWIPETE);
+    MoveGenerator::init();
+
+    let start = Instant::now();
+    let legal_nodes = perft(&mut game, depth, true);
+    let legal_duration = start.elapsed();
+
+    // Same tree, but each ply filtered with make/undo as before
+    fn filtered_perft(game: &mut Game, depth: usize) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let colour = game.get_game_state().get_turn();
+        let mut moves = Vec::new();
+        MoveGenerator::generate_legal_moves_by_filtering_into(game, colour, true, &mut moves);
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        let mut nodes = 0;
+        for mv in &moves {
+            game.make_move(mv);
+            nodes += filtered_perft(game, depth - 1);
+            game.undo_last_move();
+        }
+        nodes
+    }
+
+    let start = Instant::now();
+    let filtered_nodes = filtered_perft(&mut game, depth);
+    let filtered_duration = start.elapsed();
+
+    println!(
+        "perft({}) kiwipete: pin-aware = {:?} ({:.0} nps), make/undo filtering = {:?} ({:.0} nps)",
+        depth,
+        legal_duration,
+        legal_nodes as f64 / legal_duration.as_secs_f64(),
+        filtered_duration,
+        filtered_nodes as f64 / filtered_duration.as_secs_f64(),
+    );
+
+    // Timings are only reported, they vary too much between runs to assert on
+    assert_eq!(legal_nodes, filtered_nodes);
+}
+
+#[test]
+fn test_make_unmake_restores_position() {
+    // Walk every line of the tree and check that incremental updates match a full recompute
+    fn walk(game: &mut Game, depth: usize) {
+        let board = game.get_board().clone();
+        let hash = game.get_current_hash();
+        let (halfmove, fullmove) = (game.get_game_state().get_halfmove_clock(), game.get_game_state().get_fullmove_number());
+
+        game.hash_position();
+        assert_eq!(game.get_current_hash(), hash, "Incremental hash differs from recomputed hash");
+
+        if depth == 0 {
+            return;
+        }
+
+        let colour = game.get_game_state().get_turn();
+        let mut moves = Vec::new();
+        MoveGenerator::generate_legal_moves_into(game, colour, true, &mut moves);
+        for mv in moves {
+            game.make_compact_move(mv);
+            walk(game, depth - 1);
+            game.undo_last_move();
+
+            assert_eq!(game.get_board(), &board, "Board not restored after {}", mv);
+            assert_eq!(game.get_current_hash(), hash, "Hash not restored after {}", mv);
+            assert_eq!(game.get_game_state().get_halfmove_clock(), halfmove);
+            assert_eq!(game.get_game_state().get_fullmove_number(), fullmove);
+        }
+    }
+
+    for fen in [KIWIPETE, POSITION_3, POSITION_4, POSITION_5] {
+        walk(&mut game_from_fen(fen), 3);
+    }
+}
+
+#[test]
+fn test_perft_make_unmake_benchmark() {
+    MoveGenerator::init();
+
+    // No bulk counting at the last ply, so every leaf is actually made and unmade
+    fn perft_all_leaves(game: &mut Game, depth: usize) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let colour = game.get_game_state().get_turn();
+        let mut moves = Vec::new();
+        MoveGenerator::generate_legal_moves_into(game, colour, true, &mut moves);
+        let mut nodes = 0;
+        for mv in moves {
+            game.make_compact_move(mv);
+            nodes += perft_all_leaves(game, depth - 1);
+            game.undo_last_move();
+        }
+        nodes
+    }
+
+    for (name, fen, depth) in [("startpos", STARTPOS, 4), ("kiwipete", KIWIPETE, 3)] {
+        let mut game = game_from_fen(fen);
+
+        let start = Instant::now();
+        let nodes = perft_all_leaves(&mut game, depth);
+        let duration = start.elapsed();
+
+        println!(
+            "perft({}) {}: {} nodes in {:?} ({:.0} nps)",
+            depth,
+            name,
+            nodes,
+            duration,
+            nodes as f64 / duration.as_secs_f64(),
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use std::fs;
+use std::path::PathBuf;
+
+use rust_chess::engine::minimax::Minimax;
+use rust_chess::game_classes::game::Game;
+use rust_chess::moves::compact_move::Move;
+use rust_chess::moves::move_generator::MoveGenerator;
+use rust_chess::syzygy::table::{DTZ_MAGIC, WDL_MAGIC};
+use rust_chess::syzygy::tablebase::{Tablebase, Wdl};
+
+fn game_from_fen(fen: &str) -> Game {
+    let mut game = Game::new();
+    game.set_fenstr(fen);
+    game
+}
+
+/// KQvK tables where every position has the same stored value: white to move wins,
+/// black to move loses, and the DTZ table stores 5 moves for white to move.
+/// Real tables are the same format, just with compressed per-position data.
+fn write_kqvk_tables(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rust_chess_syzygy_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    // header flags (split, no pawns), group order, pieces K Q k for both sides, alignment padding
+    let header = [0x01, 0x00, 0x66, 0x55, 0xEE, 0x00];
+
+    let mut wdl = WDL_MAGIC.to_vec();
+    wdl.extend_from_slice(&header);
+    // single value tables for each side to move: value - 2 is the result
+    wdl.extend_from_slice(&[0x80, 4, 0x80, 0]);
+    fs::write(dir.join("KQvK.rtbw"), wdl).unwrap();
+
+    let mut dtz = DTZ_MAGIC.to_vec();
+    dtz.extend_from_slice(&header);
+    // one side only (white to move), stored in moves
+    dtz.extend_from_slice(&[0x80, 5]);
+    fs::write(dir.join("KQvK.rtbz"), dtz).unwrap();
+
+    // Ignored: wrong extension and not a table name
+    fs::write(dir.join("README.txt"), "").unwrap();
+    fs::write(dir.join("notatable.rtbw"), "").unwrap();
+
+    dir
+}
+
+#[test]
+fn test_tables_are_found() {
+    let dir = write_kqvk_tables("found");
+    let tablebase = Tablebase::from_path_list(dir.to_str().unwrap()).unwrap();
+    assert_eq!(tablebase.max_pieces(), 3);
+
+    // Too many pieces, or castling rights, are never probed
+    let mut game = Game::new();
+    assert!(!tablebase.can_probe(&game));
+    assert_eq!(tablebase.probe_wdl(&mut game), None);
+
+    assert!(Tablebase::from_path_list("/nonexistent/syzygy").is_err());
+}
+
+#[test]
+fn test_probe_wdl() {
+    let dir = write_kqvk_tables("wdl");
+    let tablebase = Tablebase::from_path_list(dir.to_str().unwrap()).unwrap();
+
+    let mut game = game_from_fen("8/8/8/4k3/8/8/8/QK6 w - - 0 1");
+    assert_eq!(tablebase.probe_wdl(&mut game), Some(Wdl::Win));
+
+    let mut game = game_from_fen("8/8/8/4k3/8/8/8/QK6 b - - 0 1");
+    assert_eq!(tablebase.probe_wdl(&mut game), Some(Wdl::Loss));
+
+    // Same material with colours swapped is looked up in the same table
+    let mut game = game_from_fen("8/8/8/4K3/8/8/8/qk6 w - - 0 1");
+    assert_eq!(tablebase.probe_wdl(&mut game), Some(Wdl::Loss));
+    let mut game = game_from_fen("8/8/8/4K3/8/8/8/qk6 b - - 0 1");
+    assert_eq!(tablebase.probe_wdl(&mut game), Some(Wdl::Win));
+
+    // Captures are searched, so a hanging queen is a draw whatever the table says
+    let mut game = game_from_fen("8/8/8/8/8/8/2k5/1Q2K3 b - - 0 1");
+    assert_eq!(tablebase.probe_wdl(&mut game), Some(Wdl::Draw));
+
+    // Material with no table
+    let mut game = game_from_fen("8/8/8/4k3/8/8/8/RK6 w - - 0 1");
+    assert_eq!(tablebase.probe_wdl(&mut game), None);
+}
+
+#[test]
+fn test_probe_dtz() {
+    let dir = write_kqvk_tables("dtz");
+    let tablebase = Tablebase::from_path_list(dir.to_str().unwrap()).unwrap();
+
+    // Stored in moves, reported in plies plus one
+    let mut game = game_from_fen("8/8/8/4k3/8/8/8/QK6 w - - 0 1");
+    assert_eq!(tablebase.probe_dtz(&mut game), Some(11));
+
+    // Black to move isn't stored, so it comes from a one ply search over white's replies
+    let mut game = game_from_fen("8/8/8/4k3/8/8/8/QK6 b - - 0 1");
+    assert_eq!(tablebase.probe_dtz(&mut game), Some(-12));
+
+    let mut game = game_from_fen("8/8/8/8/8/8/2k5/1Q2K3 b - - 0 1");
+    assert_eq!(tablebase.probe_dtz(&mut game), Some(0));
+}
+
+#[test]
+fn test_root_moves_filtered() {
+    let dir = write_kqvk_tables("root");
+    let tablebase = Tablebase::from_path_list(dir.to_str().unwrap()).unwrap();
+    MoveGenerator::init();
+
+    let mut game = game_from_fen("8/8/8/4k3/8/8/8/K2Q4 w - - 0 1");
+    let colour = game.get_game_state().get_turn();
+    let mut moves = Vec::new();
+    MoveGenerator::generate_legal_moves_into(&mut game, colour, true, &mut moves);
+    let all_moves = moves.len();
+
+    assert!(tablebase.filter_root_moves(&mut game, &mut moves));
+    assert!(!moves.is_empty() && moves.len() < all_moves);
+
+    // Hanging the queen next to the king is dropped, keeping it safe is not
+    let move_strings: Vec<String> = moves.iter().map(Move::to_string).collect();
+    assert!(!move_strings.contains(&"d1d4".to_string()));
+    assert!(move_strings.contains(&"d1d2".to_string()));
+}
+
+#[test]
+fn test_minimax_searches_only_tablebase_moves() {
+    let dir = write_kqvk_tables("minimax");
+    let mut minimax = Minimax::new(2, 2, true, true);
+    minimax.tablebase = Some(Tablebase::from_path_list(dir.to_str().unwrap()).unwrap());
+
+    let mut game = game_from_fen("8/8/8/4k3/8/8/8/K2Q4 w - - 0 1");
+    let colour = game.get_game_state().get_turn();
+    let moves: Vec<String> = minimax.find_sorted_moves(&mut game, colour).iter().map(|(mv, _)| mv.to_string()).collect();
+    assert!(!moves.is_empty());
+    assert!(!moves.contains(&"d1d4".to_string()));
+
+    // Below the probe limit nothing is filtered
+    minimax.engine_options.syzygy_probe_limit = 2;
+    assert!(minimax.find_sorted_moves(&mut game, colour).len() > moves.len());
+}